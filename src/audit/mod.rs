@@ -0,0 +1,358 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{AuditConfig, LogRotation};
+use crate::git;
+use crate::logging::{
+    log_file_set, open_log_file, target_file_name, RotatingFileWriter, RotationPolicy,
+};
+use crate::snapshot::SnapshotTrigger;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("no audit directory configured and no user data directory found")]
+    NoDirectory,
+
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// One journal record; serialized as a single JSON line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// RFC 3339 UTC timestamp
+    pub time: String,
+    /// Absolute path of the target
+    pub target: String,
+    /// User the recording process ran as
+    pub user: String,
+    #[serde(flatten)]
+    pub action: AuditAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum AuditAction {
+    /// A snapshot commit was created
+    Snapshot {
+        commit: String,
+        files: Vec<String>,
+        trigger: SnapshotTrigger,
+        duration_ms: u64,
+    },
+    /// The target's snapshot history was deleted
+    Purge,
+    /// Deleted files were restored from snapshot history
+    Restore { files: Vec<String> },
+    /// `gsd git` ran a subcommand that can rewrite history or restore files
+    Git {
+        subcommand: String,
+        args: Vec<String>,
+        /// `None` if git was killed by a signal
+        exit_code: Option<i32>,
+    },
+}
+
+impl AuditEntry {
+    pub fn new(target: &Path, action: AuditAction) -> Self {
+        Self {
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            target: target.to_string_lossy().to_string(),
+            user: current_user(),
+            action,
+        }
+    }
+
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.time)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    }
+}
+
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Git subcommands that can rewrite or drop history, or restore files from it
+const HISTORY_CHANGING_SUBCOMMANDS: &[&str] = &[
+    "am",
+    "branch",
+    "checkout",
+    "cherry-pick",
+    "clean",
+    "commit",
+    "filter-branch",
+    "gc",
+    "merge",
+    "prune",
+    "rebase",
+    "replace",
+    "reset",
+    "restore",
+    "revert",
+    "rm",
+    "stash",
+    "switch",
+    "tag",
+    "update-ref",
+];
+
+/// The subcommand of a `gsd git` invocation if it belongs in the journal
+pub fn history_changing_subcommand(args: &[String]) -> Option<&str> {
+    let index = git::subcommand_index(args)?;
+    let subcommand = args[index].as_str();
+    let changes_history = match subcommand {
+        // Only expiring or deleting entries changes the reflog
+        "reflog" => matches!(
+            args.get(index + 1).map(String::as_str),
+            Some("expire" | "delete")
+        ),
+        other => HISTORY_CHANGING_SUBCOMMANDS.contains(&other),
+    };
+    changes_history.then_some(subcommand)
+}
+
+/// Directory holding the journals
+pub fn journal_dir(config: &AuditConfig) -> Result<PathBuf, AuditError> {
+    match &config.directory {
+        Some(dir) => Ok(dir.clone()),
+        None => dirs::data_local_dir()
+            .map(|dir| dir.join("gsd").join("audit"))
+            .ok_or(AuditError::NoDirectory),
+    }
+}
+
+/// Journal file for a target, named after its full path so names don't collide
+pub fn journal_path(dir: &Path, target: &Path) -> PathBuf {
+    dir.join(format!("{}.jsonl", target_file_name(target)))
+}
+
+/// Append an entry to the target's journal and sync it to disk.
+///
+/// Does nothing when the journal is disabled. Blocking; call from
+/// `spawn_blocking` in async code.
+pub fn record(config: &AuditConfig, entry: &AuditEntry) -> Result<(), AuditError> {
+    if !config.enabled {
+        return Ok(());
+    }
+
+    let dir = journal_dir(config)?;
+    std::fs::create_dir_all(&dir)?;
+    let path = journal_path(&dir, Path::new(&entry.target));
+    // The CLI and the daemon both append; hold the lock across the size
+    // check, rotation and write so only one of them rotates
+    let _lock = lock_journal(&path)?;
+
+    let policy = RotationPolicy {
+        rotation: LogRotation::Size,
+        max_bytes: config.max_bytes,
+        max_files: config.max_files,
+        max_age: None,
+        compress: false,
+    };
+    let mut writer = RotatingFileWriter::new(path, policy)?;
+
+    let mut line = serde_json::to_vec(entry).map_err(io::Error::other)?;
+    line.push(b'\n');
+    // One write per entry keeps lines whole when the CLI and daemon append at once
+    writer.write_all(&line)?;
+    writer.sync()?;
+    Ok(())
+}
+
+/// Take an exclusive advisory lock on `<journal>.lock`, released when the
+/// returned file is closed
+fn lock_journal(journal: &Path) -> io::Result<File> {
+    let mut name = journal.as_os_str().to_owned();
+    name.push(".lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(PathBuf::from(name))?;
+    // SAFETY: flock(2) on a descriptor owned by `file`
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+/// Read a target's journal, including rotated files, oldest entry first
+pub fn read_entries(
+    config: &AuditConfig,
+    target: &Path,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<AuditEntry>, AuditError> {
+    let path = journal_path(&journal_dir(config)?, target);
+    let target = target.to_string_lossy();
+
+    let mut entries = Vec::new();
+    for file in log_file_set(&path) {
+        for line in open_log_file(&file)?.lines() {
+            let line = line?;
+            // Skip lines torn by a crash rather than failing the whole query
+            let Ok(entry) = serde_json::from_str::<AuditEntry>(&line) else {
+                continue;
+            };
+            if entry.target != target {
+                continue;
+            }
+            let recent = match (since, entry.timestamp()) {
+                (Some(since), Some(time)) => time >= since,
+                _ => true,
+            };
+            if recent {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_read_with_rotation() {
+        let temp = TempDir::new().unwrap();
+        let config = AuditConfig {
+            directory: Some(temp.path().to_path_buf()),
+            max_bytes: 300,
+            ..Default::default()
+        };
+        let target = Path::new("/home/user/notes");
+
+        for i in 0..5 {
+            let entry = AuditEntry::new(
+                target,
+                AuditAction::Snapshot {
+                    commit: format!("commit{}", i),
+                    files: vec!["a.md".to_string()],
+                    trigger: SnapshotTrigger::Interval,
+                    duration_ms: 5,
+                },
+            );
+            record(&config, &entry).unwrap();
+        }
+        record(&config, &AuditEntry::new(target, AuditAction::Purge)).unwrap();
+
+        // Entries survive rotation and come back in order
+        let journal = journal_path(temp.path(), target);
+        assert!(journal.with_extension("jsonl.1").exists());
+        let entries = read_entries(&config, target, None).unwrap();
+        assert_eq!(entries.len(), 6);
+        assert!(matches!(
+            &entries[0].action,
+            AuditAction::Snapshot { commit, .. } if commit == "commit0"
+        ));
+        assert!(matches!(entries[5].action, AuditAction::Purge));
+
+        let future = Utc::now() + chrono::Duration::hours(1);
+        assert!(read_entries(&config, target, Some(future))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_similar_paths_keep_separate_journals() {
+        let temp = TempDir::new().unwrap();
+        let config = AuditConfig {
+            directory: Some(temp.path().to_path_buf()),
+            ..Default::default()
+        };
+        let first = Path::new("/srv/a_b/c");
+        let second = Path::new("/srv/a/b_c");
+        assert_ne!(
+            journal_path(temp.path(), first),
+            journal_path(temp.path(), second)
+        );
+
+        record(&config, &AuditEntry::new(first, AuditAction::Purge)).unwrap();
+        assert_eq!(read_entries(&config, first, None).unwrap().len(), 1);
+        assert!(read_entries(&config, second, None).unwrap().is_empty());
+
+        // Entries for another target are ignored even if they share a file
+        let stray = AuditEntry::new(second, AuditAction::Purge);
+        let mut line = serde_json::to_string(&stray).unwrap();
+        line.push('\n');
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(journal_path(temp.path(), first))
+            .unwrap();
+        file.write_all(line.as_bytes()).unwrap();
+        assert_eq!(read_entries(&config, first, None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_writers_lose_no_entries() {
+        let temp = TempDir::new().unwrap();
+        let config = AuditConfig {
+            directory: Some(temp.path().to_path_buf()),
+            max_bytes: 400,
+            max_files: 100,
+            ..Default::default()
+        };
+        let target = Path::new("/home/user/notes");
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        record(&config, &AuditEntry::new(target, AuditAction::Purge)).unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(read_entries(&config, target, None).unwrap().len(), 40);
+    }
+
+    #[test]
+    fn test_history_changing_subcommands() {
+        let check = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            history_changing_subcommand(&args).map(str::to_string)
+        };
+        assert_eq!(check(&["reset", "--hard"]), Some("reset".to_string()));
+        assert_eq!(
+            check(&["-C", "sub", "reset", "--hard", "HEAD~3"]),
+            Some("reset".to_string())
+        );
+        assert_eq!(
+            check(&["--git-dir", "other", "-c", "a=b", "gc", "--prune=now"]),
+            Some("gc".to_string())
+        );
+        assert_eq!(
+            check(&["reflog", "expire", "--all"]),
+            Some("reflog".to_string())
+        );
+        assert_eq!(check(&["reflog", "show"]), None);
+        assert_eq!(check(&["--no-pager", "log"]), None);
+        assert_eq!(check(&["-C", "reset"]), None);
+    }
+
+    #[test]
+    fn test_disabled_journal_writes_nothing() {
+        let temp = TempDir::new().unwrap();
+        let config = AuditConfig {
+            enabled: false,
+            directory: Some(temp.path().to_path_buf()),
+            ..Default::default()
+        };
+        record(
+            &config,
+            &AuditEntry::new(Path::new("/tmp/x"), AuditAction::Purge),
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,223 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::git::{run_snapshot_git, GitError};
+
+/// Checkpoints are annotated tags under this prefix in `.gsd`
+pub const TAG_PREFIX: &str = "checkpoint/";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CheckpointError {
+    #[error("invalid checkpoint name '{0}'")]
+    InvalidName(String),
+
+    #[error("checkpoint '{0}' already exists (use --force to move it)")]
+    Exists(String),
+
+    #[error("no snapshot or checkpoint named '{0}'")]
+    NotFound(String),
+
+    #[error(transparent)]
+    Git(#[from] GitError),
+}
+
+/// A named snapshot
+#[derive(Debug, Clone, Serialize)]
+pub struct Checkpoint {
+    pub name: String,
+    pub commit: String,
+    /// RFC 3339 time the checkpoint was created
+    pub time: String,
+    pub message: String,
+}
+
+/// Tag-safe form of a checkpoint name: whitespace runs become '-', so
+/// "before refactor" is stored as `checkpoint/before-refactor`
+pub fn normalize_name(name: &str) -> Result<String, CheckpointError> {
+    let normalized = name.split_whitespace().collect::<Vec<_>>().join("-");
+    let valid = !normalized.is_empty()
+        && normalized
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+        && normalized
+            .split('/')
+            .all(|part| !part.is_empty() && !part.starts_with('.') && !part.ends_with(".lock"))
+        && !normalized.contains("..")
+        && !normalized.starts_with('-');
+    if valid {
+        Ok(normalized)
+    } else {
+        Err(CheckpointError::InvalidName(name.to_string()))
+    }
+}
+
+fn tag_ref(name: &str) -> String {
+    format!("refs/tags/{}{}", TAG_PREFIX, name)
+}
+
+fn failed(result: crate::git::GitCommandResult) -> GitError {
+    GitError::CommandFailed {
+        message: result.stderr.trim().to_string(),
+    }
+}
+
+/// Tag the current snapshot as `name`; `force` moves an existing checkpoint
+pub async fn create(
+    dir: &Path,
+    name: &str,
+    message: Option<&str>,
+    force: bool,
+) -> Result<Checkpoint, CheckpointError> {
+    let normalized = normalize_name(name)?;
+    if !force && find(dir, &normalized).await?.is_some() {
+        return Err(CheckpointError::Exists(normalized));
+    }
+
+    let tag = format!("{}{}", TAG_PREFIX, normalized);
+    let mut args = vec!["tag", "-a", &tag, "-m", message.unwrap_or(name)];
+    if force {
+        args.push("--force");
+    }
+    let result = run_snapshot_git(dir, &args, None).await?;
+    if result.exit_code != 0 {
+        return Err(failed(result).into());
+    }
+
+    find(dir, &normalized)
+        .await?
+        .ok_or(CheckpointError::NotFound(normalized))
+}
+
+/// All checkpoints, oldest first
+pub async fn list(dir: &Path) -> Result<Vec<Checkpoint>, CheckpointError> {
+    read_checkpoints(dir, &format!("refs/tags/{}", TAG_PREFIX)).await
+}
+
+async fn find(dir: &Path, normalized: &str) -> Result<Option<Checkpoint>, CheckpointError> {
+    let pattern = tag_ref(normalized);
+    Ok(read_checkpoints(dir, &pattern)
+        .await?
+        .into_iter()
+        .find(|c| c.name == normalized))
+}
+
+async fn read_checkpoints(dir: &Path, pattern: &str) -> Result<Vec<Checkpoint>, CheckpointError> {
+    let result = run_snapshot_git(
+        dir,
+        &[
+            "for-each-ref",
+            "--sort=taggerdate",
+            "--format=%(refname)%00%(*objectname)%00%(objectname)%00%(taggerdate:iso-strict)%00%(contents:subject)",
+            pattern,
+        ],
+        None,
+    )
+    .await?;
+    if result.exit_code != 0 {
+        return Err(failed(result).into());
+    }
+
+    let prefix = format!("refs/tags/{}", TAG_PREFIX);
+    Ok(result
+        .stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let name = fields.next()?.strip_prefix(&prefix)?.to_string();
+            let peeled = fields.next()?;
+            let object = fields.next()?;
+            // Lightweight tags made by hand have no peeled object
+            let commit = if peeled.is_empty() { object } else { peeled };
+            Some(Checkpoint {
+                name,
+                commit: commit.to_string(),
+                time: fields.next().unwrap_or_default().to_string(),
+                message: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Resolve a snapshot reference to a commit ID: a checkpoint name, or
+/// anything git accepts (`HEAD~3`, a commit ID, `checkpoint/<name>`)
+pub async fn resolve(dir: &Path, reference: &str) -> Result<String, CheckpointError> {
+    if reference.starts_with('-') {
+        return Err(CheckpointError::NotFound(reference.to_string()));
+    }
+    if let Ok(normalized) = normalize_name(reference) {
+        if let Some(checkpoint) = find(dir, &normalized).await? {
+            return Ok(checkpoint.commit);
+        }
+    }
+
+    let spec = format!("{}^{{commit}}", reference);
+    let result = run_snapshot_git(dir, &["rev-parse", "--verify", "--quiet", &spec], None).await?;
+    if result.exit_code != 0 {
+        return Err(CheckpointError::NotFound(reference.to_string()));
+    }
+    Ok(result.stdout.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{commit_all, ensure_repo_initialized};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(
+            normalize_name("before refactor").unwrap(),
+            "before-refactor"
+        );
+        assert_eq!(normalize_name("agent/run-42").unwrap(), "agent/run-42");
+        for bad in ["", "a..b", ".hidden", "x.lock", "a:b", "-flag", "a//b"] {
+            assert!(normalize_name(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_list_and_resolve() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        std::fs::write(dir.join("plan.md"), "v1").unwrap();
+        let first = commit_all(dir, "v1").await.unwrap();
+
+        let checkpoint = create(dir, "before refactor", None, false).await.unwrap();
+        assert_eq!(checkpoint.name, "before-refactor");
+        assert_eq!(checkpoint.commit, first);
+        assert_eq!(checkpoint.message, "before refactor");
+        assert!(matches!(
+            create(dir, "before refactor", None, false).await,
+            Err(CheckpointError::Exists(_))
+        ));
+
+        std::fs::write(dir.join("plan.md"), "v2").unwrap();
+        let second = commit_all(dir, "v2").await.unwrap();
+        create(dir, "before-refactor", Some("moved"), true)
+            .await
+            .unwrap();
+        create(dir, "done", None, false).await.unwrap();
+
+        let names: Vec<String> = list(dir)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"done".to_string()));
+
+        assert_eq!(resolve(dir, "before refactor").await.unwrap(), second);
+        assert_eq!(resolve(dir, "checkpoint/done").await.unwrap(), second);
+        assert_eq!(resolve(dir, "HEAD~1").await.unwrap(), first);
+        assert!(matches!(
+            resolve(dir, "nope").await,
+            Err(CheckpointError::NotFound(_))
+        ));
+    }
+}
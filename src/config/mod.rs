@@ -46,6 +46,50 @@ fn default_ignore_patterns() -> Vec<String> {
     ]
 }
 
+fn default_sqlite_patterns() -> Vec<String> {
+    vec![
+        "*.db".to_string(),
+        "*.sqlite".to_string(),
+        "*.sqlite3".to_string(),
+    ]
+}
+
+fn is_default_sqlite_patterns(patterns: &[String]) -> bool {
+    patterns == default_sqlite_patterns()
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn default_hook_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_message_timeout_seconds() -> u64 {
+    30
+}
+
+fn is_default_message_timeout(seconds: &u64) -> bool {
+    *seconds == default_message_timeout_seconds()
+}
+
+fn default_event_retries() -> u32 {
+    3
+}
+
+fn default_audit_enabled() -> bool {
+    true
+}
+
+fn default_audit_max_bytes() -> u64 {
+    10_485_760
+}
+
+fn default_audit_max_files() -> usize {
+    10
+}
+
 fn default_author_name() -> String {
     "gsd".to_string()
 }
@@ -65,6 +109,30 @@ pub struct Config {
     #[serde(default)]
     pub git: GitConfig,
 
+    #[serde(default, skip_serializing_if = "HooksConfig::is_empty")]
+    pub hooks: HooksConfig,
+
+    #[serde(default, skip_serializing_if = "EventsConfig::is_empty")]
+    pub events: EventsConfig,
+
+    #[serde(default, skip_serializing_if = "MetricsConfig::is_empty")]
+    pub metrics: MetricsConfig,
+
+    #[serde(default, skip_serializing_if = "AuditConfig::is_default")]
+    pub audit: AuditConfig,
+
+    #[serde(default, skip_serializing_if = "ControlConfig::is_empty")]
+    pub control: ControlConfig,
+
+    #[serde(default, skip_serializing_if = "SchedulerConfig::is_default")]
+    pub scheduler: SchedulerConfig,
+
+    #[serde(default, skip_serializing_if = "ThrottleConfig::is_default")]
+    pub throttle: ThrottleConfig,
+
+    #[serde(default, skip_serializing_if = "MessageConfig::is_default")]
+    pub message: MessageConfig,
+
     #[serde(default)]
     pub targets: Vec<TargetConfig>,
 }
@@ -85,6 +153,42 @@ pub struct LoggingConfig {
 
     #[serde(default = "default_logging_console")]
     pub console: bool,
+
+    #[serde(default)]
+    pub format: LogFormat,
+
+    /// Also write each target's events to `<directory>/targets/<name>.log`
+    #[serde(default)]
+    pub per_target_files: bool,
+
+    #[serde(default)]
+    pub rotation: LogRotation,
+
+    /// Gzip rotated log files
+    #[serde(default)]
+    pub compress: bool,
+
+    /// Delete rotated log files older than this, in addition to `max_files`
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+}
+
+/// When log files are rotated; time-based modes also rotate on `max_bytes`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    #[default]
+    Size,
+    Hourly,
+    Daily,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
 }
 
 impl Default for LoggingConfig {
@@ -95,6 +199,11 @@ impl Default for LoggingConfig {
             max_bytes: default_logging_max_bytes(),
             max_files: default_logging_max_files(),
             console: default_logging_console(),
+            format: LogFormat::default(),
+            per_target_files: false,
+            rotation: LogRotation::default(),
+            compress: false,
+            max_age_days: None,
         }
     }
 }
@@ -109,6 +218,18 @@ pub struct GitConfig {
 
     #[serde(default = "default_ignore_patterns")]
     pub default_ignore_patterns: Vec<String>,
+
+    #[serde(default)]
+    pub timeouts: GitTimeouts,
+
+    /// Let snapshot commands read the system and global git config and
+    /// `GIT_AUTHOR_*`/`GIT_COMMITTER_*` variables
+    #[serde(default)]
+    pub inherit_user_config: bool,
+
+    /// Run hooks installed in `.gsd/hooks`
+    #[serde(default)]
+    pub run_hooks: bool,
 }
 
 impl Default for GitConfig {
@@ -117,11 +238,387 @@ impl Default for GitConfig {
             author_name: default_author_name(),
             author_email: default_author_email(),
             default_ignore_patterns: default_ignore_patterns(),
+            timeouts: GitTimeouts::default(),
+            inherit_user_config: false,
+            run_hooks: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Seconds a git process may run before it is killed; 0 disables the limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitTimeouts {
+    /// Any git command not listed below
+    #[serde(default = "default_git_timeout")]
+    pub default_seconds: u64,
+
+    /// `git status`, `diff` and similar change detection
+    #[serde(default = "default_git_status_timeout")]
+    pub status_seconds: u64,
+
+    /// `git add` (staging the working tree)
+    #[serde(default = "default_git_add_timeout")]
+    pub add_seconds: u64,
+
+    #[serde(default = "default_git_commit_timeout")]
+    pub commit_seconds: u64,
+}
+
+const DEFAULT_GIT_TIMEOUT_SECONDS: u64 = 120;
+const DEFAULT_GIT_STATUS_TIMEOUT_SECONDS: u64 = 300;
+const DEFAULT_GIT_ADD_TIMEOUT_SECONDS: u64 = 600;
+const DEFAULT_GIT_COMMIT_TIMEOUT_SECONDS: u64 = 300;
+
+impl GitTimeouts {
+    pub const DEFAULT: Self = Self {
+        default_seconds: DEFAULT_GIT_TIMEOUT_SECONDS,
+        status_seconds: DEFAULT_GIT_STATUS_TIMEOUT_SECONDS,
+        add_seconds: DEFAULT_GIT_ADD_TIMEOUT_SECONDS,
+        commit_seconds: DEFAULT_GIT_COMMIT_TIMEOUT_SECONDS,
+    };
+}
+
+impl Default for GitTimeouts {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+fn default_git_timeout() -> u64 {
+    DEFAULT_GIT_TIMEOUT_SECONDS
+}
+
+fn default_git_status_timeout() -> u64 {
+    DEFAULT_GIT_STATUS_TIMEOUT_SECONDS
+}
+
+fn default_git_add_timeout() -> u64 {
+    DEFAULT_GIT_ADD_TIMEOUT_SECONDS
+}
+
+fn default_git_commit_timeout() -> u64 {
+    DEFAULT_GIT_COMMIT_TIMEOUT_SECONDS
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// Command run before each snapshot; a non-zero exit skips the snapshot
+    #[serde(default)]
+    pub pre_snapshot: Option<String>,
+
+    /// Command run after a snapshot is committed
+    #[serde(default)]
+    pub post_snapshot: Option<String>,
+
+    /// Maximum run time of each hook before it is killed
+    #[serde(default = "default_hook_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            pre_snapshot: None,
+            post_snapshot: None,
+            timeout_seconds: default_hook_timeout_seconds(),
+        }
+    }
+}
+
+impl HooksConfig {
+    /// True when no hook commands are configured
+    pub fn is_empty(&self) -> bool {
+        self.pre_snapshot.is_none() && self.post_snapshot.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// Record snapshots and history operations in a per-target journal
+    #[serde(default = "default_audit_enabled")]
+    pub enabled: bool,
+
+    /// Journal directory; defaults to `gsd/audit` in the user data directory
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+
+    #[serde(default = "default_audit_max_bytes")]
+    pub max_bytes: u64,
+
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_audit_enabled(),
+            directory: None,
+            max_bytes: default_audit_max_bytes(),
+            max_files: default_audit_max_files(),
+        }
+    }
+}
+
+impl AuditConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// Address of the Prometheus metrics listener, e.g. "127.0.0.1:9464"
+    #[serde(default)]
+    pub listen: Option<String>,
+}
+
+impl MetricsConfig {
+    pub fn is_empty(&self) -> bool {
+        self.listen.is_none()
+    }
+
+    /// Parsed listen address, if metrics are enabled
+    pub fn listen_addr(&self) -> Result<Option<std::net::SocketAddr>, ConfigError> {
+        self.listen
+            .as_deref()
+            .map(|addr| {
+                addr.parse().map_err(|_| {
+                    ConfigError::Invalid(format!("invalid metrics listen address: {}", addr))
+                })
+            })
+            .transpose()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    /// Snapshots (and target initializations) allowed to run at once
+    #[serde(default = "default_max_concurrent_snapshots")]
+    pub max_concurrent_snapshots: usize,
+
+    /// Delay each timed snapshot by a random 0..=jitter_seconds
+    #[serde(default)]
+    pub jitter_seconds: u64,
+
+    /// Start each interval timer at a random point in its first interval
+    #[serde(default = "default_spread_start")]
+    pub spread_start: bool,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_snapshots: default_max_concurrent_snapshots(),
+            jitter_seconds: 0,
+            spread_start: default_spread_start(),
+        }
+    }
+}
+
+impl SchedulerConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+fn default_max_concurrent_snapshots() -> usize {
+    8
+}
+
+fn default_spread_start() -> bool {
+    true
+}
+
+/// Auto-generated commit messages, shared by the daemon and `gsd snapshot`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageConfig {
+    /// Subject line template; see `message::PLACEHOLDERS`
+    #[serde(default = "default_message_subject")]
+    pub subject: String,
+
+    /// Add a body listing every file with its change type
+    #[serde(default)]
+    pub body: bool,
+
+    /// File names shown by `{files}` before "+N more"
+    #[serde(default = "default_message_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for MessageConfig {
+    fn default() -> Self {
+        Self {
+            subject: default_message_subject(),
+            body: false,
+            max_files: default_message_max_files(),
+        }
+    }
+}
+
+impl MessageConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+fn default_message_subject() -> String {
+    "{files}".to_string()
+}
+
+fn default_message_max_files() -> usize {
+    10
+}
+
+/// What a throttle condition does to timed snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleAction {
+    /// Run only every `stretch_factor`th scheduled snapshot
+    Stretch,
+    /// Skip scheduled snapshots entirely
+    Skip,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleConfig {
+    /// 1-minute load average above which snapshots are throttled
+    #[serde(default)]
+    pub max_load: Option<f64>,
+
+    #[serde(default = "default_on_high_load")]
+    pub on_high_load: ThrottleAction,
+
+    /// Skip snapshots while the target's filesystem has less free space
+    #[serde(default)]
+    pub min_free_disk_mb: Option<u64>,
+
+    /// Throttle while running on battery; unset means ignore power state
+    #[serde(default)]
+    pub on_battery: Option<ThrottleAction>,
+
+    #[serde(default = "default_stretch_factor")]
+    pub stretch_factor: u32,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            max_load: None,
+            on_high_load: default_on_high_load(),
+            min_free_disk_mb: None,
+            on_battery: None,
+            stretch_factor: default_stretch_factor(),
+        }
+    }
+}
+
+impl ThrottleConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+fn default_on_high_load() -> ThrottleAction {
+    ThrottleAction::Stretch
+}
+
+fn default_stretch_factor() -> u32 {
+    4
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ControlConfig {
+    /// Unix socket the daemon accepts control commands on
+    #[serde(default)]
+    pub socket: Option<PathBuf>,
+}
+
+impl ControlConfig {
+    pub fn is_empty(&self) -> bool {
+        self.socket.is_none()
+    }
+
+    /// Configured socket, or `gsd/control.sock` in the runtime (or data) dir
+    pub fn socket_path(&self) -> Option<PathBuf> {
+        self.socket.clone().or_else(|| {
+            dirs::runtime_dir()
+                .or_else(dirs::data_local_dir)
+                .map(|dir| dir.join("gsd").join("control.sock"))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EventsConfig {
+    /// Destinations every snapshot event is delivered to
+    #[serde(default)]
+    pub sinks: Vec<EventSinkConfig>,
+}
+
+impl EventsConfig {
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventSinkConfig {
+    /// POST each event as JSON to a local `http://` endpoint
+    Http {
+        url: String,
+        /// Attempts after the first before the event is queued on disk
+        #[serde(default = "default_event_retries")]
+        retries: u32,
+        /// Where undelivered events wait; defaults to the user data directory
+        #[serde(default)]
+        queue_path: Option<PathBuf>,
+    },
+    /// Append each event as one JSON line
+    Jsonl { path: PathBuf },
+    /// Run a command with the event JSON on stdin
+    Command {
+        command: String,
+        #[serde(default = "default_hook_timeout_seconds")]
+        timeout_seconds: u64,
+    },
+}
+
+impl EventSinkConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            EventSinkConfig::Http { url, .. } => {
+                if !url.starts_with("http://") {
+                    return Err(ConfigError::Invalid(format!(
+                        "event sink url must start with http://: {}",
+                        url
+                    )));
+                }
+            }
+            EventSinkConfig::Jsonl { path } => {
+                if !path.is_absolute() {
+                    return Err(ConfigError::Invalid(format!(
+                        "event sink path must be absolute: {}",
+                        path.display()
+                    )));
+                }
+            }
+            EventSinkConfig::Command {
+                timeout_seconds, ..
+            } => {
+                if *timeout_seconds == 0 {
+                    return Err(ConfigError::Invalid(
+                        "event sink timeout_seconds must be > 0".to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TargetConfig {
     /// Directory path to monitor (also serves as unique identifier)
     pub path: PathBuf,
@@ -137,9 +634,108 @@ pub struct TargetConfig {
     /// Whether this target is enabled
     #[serde(default = "default_enabled")]
     pub enabled: bool,
+
+    /// Commit consistent copies of SQLite databases instead of raw files
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub sqlite_snapshots: bool,
+
+    /// Patterns identifying SQLite databases when sqlite_snapshots is enabled
+    #[serde(
+        default = "default_sqlite_patterns",
+        skip_serializing_if = "is_default_sqlite_patterns"
+    )]
+    pub sqlite_patterns: Vec<String>,
+
+    /// Content filters applied to matching files before they are stored
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<FilterConfig>,
+
+    /// Hooks for this target, run after the global hooks
+    #[serde(default, skip_serializing_if = "HooksConfig::is_empty")]
+    pub hooks: HooksConfig,
+
+    /// Cron expression and/or active window replacing the plain interval
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<ScheduleConfig>,
+
+    /// Command that reads the staged diff on stdin and prints a commit
+    /// message; the built-in message is used if it fails
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_command: Option<String>,
+
+    /// Maximum run time of `message_command` before it is killed
+    #[serde(
+        default = "default_message_timeout_seconds",
+        skip_serializing_if = "is_default_message_timeout"
+    )]
+    pub message_timeout_seconds: u64,
+}
+
+/// Per-target schedule; times are in the local timezone
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    /// 5-field cron expression, e.g. "0 2 * * *"; replaces interval_seconds
+    #[serde(default)]
+    pub cron: Option<String>,
+
+    /// Weekdays snapshots may run on, e.g. "mon-fri"
+    #[serde(default)]
+    pub days: Option<String>,
+
+    /// Hours snapshots may run in, e.g. "09:00-18:00"
+    #[serde(default)]
+    pub hours: Option<String>,
+}
+
+/// Built-in content filter kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterKind {
+    /// Sort object keys and pretty-print JSON
+    Json,
+    /// Strip trailing spaces and tabs from every line
+    TrailingWhitespace,
+    /// Drop lines matching `pattern`
+    DropLines,
+    /// Pipe content through `command` (stdin to stdout)
+    Command,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterConfig {
+    /// Glob selecting files (matched against the file name unless it contains '/')
+    pub glob: String,
+
+    /// Filter to apply
+    pub kind: FilterKind,
+
+    /// Regex for drop_lines
+    #[serde(default)]
+    pub pattern: Option<String>,
+
+    /// Shell command for command filters
+    #[serde(default)]
+    pub command: Option<String>,
 }
 
 impl TargetConfig {
+    /// Create an enabled target with default settings
+    pub fn new(path: PathBuf, interval_seconds: u64) -> Self {
+        Self {
+            path,
+            interval_seconds,
+            ignore_patterns: Vec::new(),
+            enabled: default_enabled(),
+            sqlite_snapshots: false,
+            sqlite_patterns: default_sqlite_patterns(),
+            filters: Vec::new(),
+            hooks: HooksConfig::default(),
+            schedule: None,
+            message_command: None,
+            message_timeout_seconds: default_message_timeout_seconds(),
+        }
+    }
+
     /// Returns a display name for this target (directory name)
     pub fn name(&self) -> &str {
         self.path
@@ -149,6 +745,40 @@ impl TargetConfig {
     }
 }
 
+impl FilterConfig {
+    fn validate(&self, target: &str) -> Result<(), ConfigError> {
+        let invalid = |message: String| {
+            Err(ConfigError::Invalid(format!(
+                "target {} filter {}: {}",
+                target, self.glob, message
+            )))
+        };
+
+        if let Err(e) = globset::Glob::new(&self.glob) {
+            return invalid(format!("invalid glob: {}", e));
+        }
+
+        match self.kind {
+            FilterKind::DropLines => match &self.pattern {
+                Some(pattern) => {
+                    if let Err(e) = regex::Regex::new(pattern) {
+                        return invalid(format!("invalid pattern: {}", e));
+                    }
+                }
+                None => return invalid("drop_lines requires pattern".to_string()),
+            },
+            FilterKind::Command => {
+                if self.command.as_deref().unwrap_or("").trim().is_empty() {
+                    return invalid("command filter requires command".to_string());
+                }
+            }
+            FilterKind::Json | FilterKind::TrailingWhitespace => {}
+        }
+
+        Ok(())
+    }
+}
+
 fn default_enabled() -> bool {
     true
 }
@@ -323,6 +953,45 @@ impl Config {
             ));
         }
 
+        if self.hooks.timeout_seconds == 0 {
+            return Err(ConfigError::Invalid(
+                "hooks timeout_seconds must be > 0".to_string(),
+            ));
+        }
+
+        for sink in &self.events.sinks {
+            sink.validate()?;
+        }
+
+        self.metrics.listen_addr()?;
+
+        if self.throttle.stretch_factor == 0 {
+            return Err(ConfigError::Invalid(
+                "throttle stretch_factor must be > 0".to_string(),
+            ));
+        }
+        if self.throttle.max_load.is_some_and(|load| load <= 0.0) {
+            return Err(ConfigError::Invalid(
+                "throttle max_load must be > 0".to_string(),
+            ));
+        }
+
+        if self.scheduler.max_concurrent_snapshots == 0 {
+            return Err(ConfigError::Invalid(
+                "scheduler max_concurrent_snapshots must be > 0".to_string(),
+            ));
+        }
+
+        if let Err(e) = crate::message::validate(&self.message.subject) {
+            return Err(ConfigError::Invalid(format!("message subject: {}", e)));
+        }
+
+        if self.audit.enabled && (self.audit.max_bytes == 0 || self.audit.max_files == 0) {
+            return Err(ConfigError::Invalid(
+                "audit max_bytes and max_files must be > 0".to_string(),
+            ));
+        }
+
         let mut seen_paths = std::collections::HashSet::new();
         for target in &self.targets {
             if !target.path.is_absolute() {
@@ -345,6 +1014,39 @@ impl Config {
                     target.name()
                 )));
             }
+
+            if let Err(e) = crate::schedule::Schedule::from_target(target) {
+                return Err(ConfigError::Invalid(format!(
+                    "target {} schedule: {}",
+                    target.name(),
+                    e
+                )));
+            }
+
+            if target.sqlite_snapshots && target.sqlite_patterns.is_empty() {
+                return Err(ConfigError::Invalid(format!(
+                    "target {} sqlite_patterns must not be empty when sqlite_snapshots is enabled",
+                    target.name()
+                )));
+            }
+
+            for filter in &target.filters {
+                filter.validate(target.name())?;
+            }
+
+            if target.hooks.timeout_seconds == 0 {
+                return Err(ConfigError::Invalid(format!(
+                    "target {} hooks timeout_seconds must be > 0",
+                    target.name()
+                )));
+            }
+
+            if target.message_timeout_seconds == 0 {
+                return Err(ConfigError::Invalid(format!(
+                    "target {} message_timeout_seconds must be > 0",
+                    target.name()
+                )));
+            }
         }
 
         Ok(())
@@ -361,11 +1063,77 @@ level = "info"
 max_bytes = 104857600
 max_files = 5
 console = true
+format = "text"            # text or json
+# per_target_files = true  # also write targets/<name>.log under directory
+rotation = "size"          # size, hourly or daily
+# compress = true          # gzip rotated files
+# max_age_days = 30
 
 [git]
 author_name = "gsd"
 author_email = "gsd@local"
 default_ignore_patterns = ["*.db-wal", "*.db-shm", "*.db-journal"]
+# Snapshot commands ignore your ~/.gitconfig and /etc/gitconfig by default
+# inherit_user_config = false
+# run_hooks = false
+
+# Seconds before a hung git process is killed (0 = no limit)
+# [git.timeouts]
+# status_seconds = 300
+# add_seconds = 600
+# commit_seconds = 300
+# default_seconds = 120
+
+# Commands run around every snapshot (targets can add their own [targets.hooks])
+# [hooks]
+# pre_snapshot = "/usr/local/bin/flush-state"
+# post_snapshot = "/usr/local/bin/notify-snapshot"
+# timeout_seconds = 30
+
+# Snapshot events (created, failed, target added/removed, ...) as JSON
+# [[events.sinks]]
+# type = "http"             # http, jsonl, command
+# url = "http://127.0.0.1:8080/gsd"
+# retries = 3
+# [[events.sinks]]
+# type = "jsonl"
+# path = "/var/log/gsd/events.jsonl"
+
+# Per-target audit journal of snapshots and history operations (JSONL)
+# [audit]
+# enabled = true
+# directory = "/var/lib/gsd/audit"
+# max_bytes = 10485760
+# max_files = 10
+
+# Prometheus metrics at http://<listen>/metrics
+# [metrics]
+# listen = "127.0.0.1:9464"
+
+# Limits for daemons with many targets (read at daemon start)
+# [scheduler]
+# max_concurrent_snapshots = 8
+# jitter_seconds = 0         # random extra delay per timed snapshot
+# spread_start = true        # random first run within each target's interval
+
+# Auto-generated commit messages; placeholders: {timestamp} {target} {files}
+# {count} {added} {modified} {deleted} {renamed} {dirs} {insertions} {deletions}
+# [message]
+# subject = "{files}"
+# body = false               # list every file with its change type
+# max_files = 10             # names shown by {files}
+
+# Back off on busy or unplugged machines
+# [throttle]
+# max_load = 4.0             # 1-minute load average
+# on_high_load = "stretch"   # stretch or skip
+# min_free_disk_mb = 1024    # skip when the target's filesystem is fuller
+# on_battery = "skip"        # stretch or skip; unset to ignore
+# stretch_factor = 4         # stretched targets run every 4th scheduled time
+
+# Socket used by `gsd status` and `gsd log-level` to reach the running daemon
+# [control]
+# socket = "/run/user/1000/gsd/control.sock"
 
 # Example target configuration
 # [[targets]]
@@ -373,6 +1141,25 @@ default_ignore_patterns = ["*.db-wal", "*.db-shm", "*.db-journal"]
 # interval_seconds = 60
 # ignore_patterns = ["*.tmp"]
 # enabled = true
+# sqlite_snapshots = false
+# sqlite_patterns = ["*.db", "*.sqlite", "*.sqlite3"]
+# message_command = "~/bin/summarize-diff"   # staged diff on stdin, message on stdout
+# message_timeout_seconds = 30
+#
+# Optional schedule in local time; cron replaces interval_seconds
+# [targets.schedule]
+# cron = "0 2 * * *"
+# days = "mon-fri"
+# hours = "09:00-18:00"
+#
+# Content filters normalize files before they are stored and compared
+# [[targets.filters]]
+# glob = "*.json"
+# kind = "json"             # json, trailing_whitespace, drop_lines, command
+# [[targets.filters]]
+# glob = "*.log"
+# kind = "drop_lines"
+# pattern = "^# generated at "
 
 # You can also create a .gsdignore file in any target directory
 # for target-specific excludes (like .gitignore syntax)
@@ -387,6 +1174,14 @@ impl Default for Config {
             schema_version: default_schema_version(),
             logging: LoggingConfig::default(),
             git: GitConfig::default(),
+            hooks: HooksConfig::default(),
+            events: EventsConfig::default(),
+            metrics: MetricsConfig::default(),
+            audit: AuditConfig::default(),
+            control: ControlConfig::default(),
+            scheduler: SchedulerConfig::default(),
+            throttle: ThrottleConfig::default(),
+            message: MessageConfig::default(),
             targets: Vec::new(),
         }
     }
@@ -412,18 +1207,8 @@ mod tests {
     fn test_validate_duplicate_paths() {
         let config = Config {
             targets: vec![
-                TargetConfig {
-                    path: PathBuf::from("/tmp/same"),
-                    interval_seconds: 60,
-                    ignore_patterns: vec![],
-                    enabled: true,
-                },
-                TargetConfig {
-                    path: PathBuf::from("/tmp/same"),
-                    interval_seconds: 60,
-                    ignore_patterns: vec![],
-                    enabled: true,
-                },
+                TargetConfig::new(PathBuf::from("/tmp/same"), 60),
+                TargetConfig::new(PathBuf::from("/tmp/same"), 60),
             ],
             ..Default::default()
         };
@@ -433,14 +1218,41 @@ mod tests {
     #[test]
     fn test_validate_relative_path() {
         let config = Config {
-            targets: vec![TargetConfig {
-                path: PathBuf::from("relative/path"),
-                interval_seconds: 60,
-                ignore_patterns: vec![],
-                enabled: true,
-            }],
+            targets: vec![TargetConfig::new(PathBuf::from("relative/path"), 60)],
             ..Default::default()
         };
         assert!(config.validate().is_err());
     }
+
+    #[test]
+    fn test_parse_and_validate_filters() {
+        let toml = r#"
+            [[targets]]
+            path = "/tmp/test"
+
+            [[targets.filters]]
+            glob = "*.json"
+            kind = "json"
+
+            [[targets.filters]]
+            glob = "*.md"
+            kind = "drop_lines"
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.targets[0].filters.len(), 2);
+        assert_eq!(config.targets[0].filters[0].kind, FilterKind::Json);
+
+        // drop_lines without a pattern is rejected
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_save_omits_default_target_fields() {
+        let target = TargetConfig::new(PathBuf::from("/tmp/test"), 60);
+        let content = toml::to_string(&target).unwrap();
+        assert!(!content.contains("sqlite_snapshots"));
+        assert!(!content.contains("sqlite_patterns"));
+        assert!(!content.contains("filters"));
+        assert!(!content.contains("message_timeout_seconds"));
+    }
 }
@@ -0,0 +1,255 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream as StdUnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader as AsyncBufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// How long the CLI waits for the daemon to answer
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ControlError {
+    #[error("no control socket configured and no runtime directory found")]
+    NoSocket,
+
+    #[error("daemon is not running (no control socket at {0})")]
+    NotRunning(PathBuf),
+
+    #[error("invalid control message: {0}")]
+    Protocol(String),
+
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// A command sent to the daemon; one JSON line per connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Log at `level` for `minutes`, for one target path or the whole daemon
+    LogLevel {
+        target: Option<PathBuf>,
+        level: String,
+        minutes: u64,
+    },
+    /// Active targets and their next scheduled run
+    Status,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    pub message: String,
+    /// Filled in by `status`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<TargetStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetStatus {
+    pub name: String,
+    pub path: PathBuf,
+    /// Schedule as shown to users, e.g. "every 60s"
+    pub schedule: String,
+    /// RFC 3339 time in the daemon's timezone
+    pub next_run: Option<String>,
+    /// healthy, degraded or unhealthy
+    #[serde(default)]
+    pub health: String,
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    /// Git timeouts among the consecutive failures
+    #[serde(default)]
+    pub timeouts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+impl ControlResponse {
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: message.into(),
+            targets: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: message.into(),
+            targets: Vec::new(),
+        }
+    }
+}
+
+/// A request waiting for the service to answer it
+pub type ControlCall = (ControlRequest, oneshot::Sender<ControlResponse>);
+
+/// Send one request to the daemon and wait for its answer
+pub fn send(socket: &Path, request: &ControlRequest) -> Result<ControlResponse, ControlError> {
+    let mut stream = StdUnixStream::connect(socket).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => {
+            ControlError::NotRunning(socket.to_path_buf())
+        }
+        _ => ControlError::Io(e),
+    })?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut line = serde_json::to_vec(request).map_err(io::Error::other)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    serde_json::from_str(&response).map_err(|e| ControlError::Protocol(e.to_string()))
+}
+
+/// Listening socket; removes the socket file when dropped
+pub struct ControlListener {
+    path: PathBuf,
+    task: JoinHandle<()>,
+}
+
+impl Drop for ControlListener {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Bind the control socket and forward requests to `calls`.
+///
+/// A stale socket left by a crashed daemon is replaced; one that still
+/// answers belongs to another daemon and is left alone.
+pub fn spawn(path: PathBuf, calls: mpsc::Sender<ControlCall>) -> io::Result<ControlListener> {
+    if path.exists() {
+        if StdUnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another daemon is using this control socket",
+            ));
+        }
+        std::fs::remove_file(&path)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    info!(path = %path.display(), "Control socket listening");
+
+    let task = tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!(error = %e, "Control socket stopped");
+                    return;
+                }
+            };
+            let calls = calls.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, calls).await {
+                    debug!(error = %e, "Control request failed");
+                }
+            });
+        }
+    });
+
+    Ok(ControlListener { path, task })
+}
+
+async fn handle_connection(stream: UnixStream, calls: mpsc::Sender<ControlCall>) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    let mut reader = AsyncBufReader::new(reader);
+    let read = reader.read_line(&mut line);
+    if tokio::time::timeout(CLIENT_TIMEOUT, read).await.is_err() {
+        return Ok(());
+    }
+
+    let response = match serde_json::from_str::<ControlRequest>(&line) {
+        Ok(request) => {
+            let (reply_tx, reply_rx) = oneshot::channel();
+            if calls.send((request, reply_tx)).await.is_err() {
+                ControlResponse::error("daemon is shutting down")
+            } else {
+                reply_rx
+                    .await
+                    .unwrap_or_else(|_| ControlResponse::error("daemon is shutting down"))
+            }
+        }
+        Err(e) => ControlResponse::error(format!("invalid request: {}", e)),
+    };
+
+    let mut out = serde_json::to_vec(&response).map_err(io::Error::other)?;
+    out.push(b'\n');
+    writer.write_all(&out).await?;
+    writer.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_request_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("control.sock");
+        let (tx, mut rx) = mpsc::channel::<ControlCall>(1);
+        let listener = spawn(path.clone(), tx).unwrap();
+
+        tokio::spawn(async move {
+            while let Some((request, reply)) = rx.recv().await {
+                let message = match request {
+                    ControlRequest::LogLevel { level, minutes, .. } => {
+                        format!("{} {}", level, minutes)
+                    }
+                    ControlRequest::Status => "status".to_string(),
+                };
+                let _ = reply.send(ControlResponse::ok(message));
+            }
+        });
+
+        let request = ControlRequest::LogLevel {
+            target: None,
+            level: "debug".to_string(),
+            minutes: 5,
+        };
+        let client_path = path.clone();
+        let response = tokio::task::spawn_blocking(move || send(&client_path, &request))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(response.ok);
+        assert_eq!(response.message, "debug 5");
+
+        // A second daemon must not steal a live socket
+        let (tx, _rx) = mpsc::channel::<ControlCall>(1);
+        assert!(spawn(path.clone(), tx).is_err());
+
+        drop(listener);
+        assert!(!path.exists());
+        assert!(matches!(
+            send(
+                &path,
+                &ControlRequest::LogLevel {
+                    target: None,
+                    level: "debug".to_string(),
+                    minutes: 5,
+                }
+            ),
+            Err(ControlError::NotRunning(_))
+        ));
+    }
+}
@@ -0,0 +1,523 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::git::{self, run_snapshot_git, GitError};
+use crate::sqlite;
+
+mod prose;
+mod structured;
+
+/// Largest edit table computed before giving up on a word, block or
+/// array diff
+const MAX_CELLS: usize = 16 * 1024 * 1024;
+
+/// How `gsd diff` prints changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffMode {
+    /// Line patches, with word-level changes for markdown and text files
+    /// and key-level changes for JSON, TOML and YAML
+    Patch,
+    /// Line patches for every file
+    Lines,
+    Stat,
+    NameOnly,
+}
+
+/// One changed file, as printed by `gsd diff --json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    /// added, modified, deleted, renamed, copied or type_changed
+    pub change: &'static str,
+    /// Previous path of a renamed or copied file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// Line counts; `None` for binary files
+    pub insertions: Option<u64>,
+    pub deletions: Option<u64>,
+}
+
+/// Everything `gsd diff --json` prints
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffSummary {
+    pub from: String,
+    /// `None` for the working tree
+    pub to: Option<String>,
+    pub files: Vec<FileDiff>,
+    pub insertions: u64,
+    pub deletions: u64,
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// `code` when printing in color, otherwise nothing
+fn paint(code: &'static str, color: bool) -> &'static str {
+    if color {
+        code
+    } else {
+        ""
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Longest-common-subsequence edit script from `old` to `new`, or `None`
+/// when the changed middle is too large to compare
+fn edit_script<T: PartialEq>(old: &[T], new: &[T]) -> Option<Vec<Edit>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+    if (a.len() + 1).saturating_mul(b.len() + 1) > MAX_CELLS {
+        return None;
+    }
+
+    // lengths[i][j]: LCS length of a[i..] and b[j..]
+    let width = b.len() + 1;
+    let mut lengths = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i * width + j] = if a[i] == b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|i| Edit::Equal(i, i)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            edits.push(Edit::Equal(prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if i < a.len()
+            && (j == b.len() || lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+        {
+            edits.push(Edit::Delete(prefix + i));
+            i += 1;
+        } else {
+            edits.push(Edit::Insert(prefix + j));
+            j += 1;
+        }
+    }
+    edits.extend((0..suffix).map(|k| Edit::Equal(old.len() - suffix + k, new.len() - suffix + k)));
+    Some(edits)
+}
+
+/// Run `diff` against the working tree: snapshot git commands inside it
+/// use a private copy of the snapshot index with the working tree staged,
+/// so the diff sees new files and the same clean filters as a snapshot
+/// without locking the index the daemon commits from
+pub async fn with_working_tree<T, E, F>(dir: &Path, diff: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: From<GitError>,
+{
+    let index = TempIndex::copy_of(dir).map_err(GitError::Io)?;
+    git::with_index_file(index.path.clone(), async {
+        sqlite::refresh_databases(dir, &mut sqlite::RefreshState::default()).await?;
+        git::stage_all(dir).await?;
+        diff.await
+    })
+    .await
+}
+
+/// Copy of `.gsd/index` in its own temporary directory, removed on drop
+struct TempIndex {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl TempIndex {
+    fn copy_of(repo: &Path) -> std::io::Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "gsd-diff-{}-{:016x}",
+            std::process::id(),
+            fastrand::u64(..)
+        ));
+        std::fs::create_dir(&dir)?;
+        let index = Self {
+            path: dir.join("index"),
+            dir,
+        };
+        // Without an index yet, git starts the copy from scratch
+        match std::fs::copy(repo.join(git::GSD_DIR).join("index"), &index.path) {
+            Ok(_) => Ok(index),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(index),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for TempIndex {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// `git diff` arguments comparing `from` with `to`, or with the staged
+/// working tree when `to` is `None`
+fn diff_args<'a>(
+    options: &[&'a str],
+    from: &'a str,
+    to: Option<&'a str>,
+    paths: &'a [String],
+) -> Vec<&'a str> {
+    let mut args = vec!["diff", "-M"];
+    args.extend_from_slice(options);
+    match to {
+        Some(to) => args.extend([from, to]),
+        None => args.extend(["--cached", from]),
+    }
+    args.push("--");
+    args.extend(paths.iter().map(String::as_str));
+    args
+}
+
+async fn run_diff(dir: &Path, args: &[&str]) -> Result<String, GitError> {
+    let result = run_snapshot_git(dir, args, Some(usize::MAX)).await?;
+    if result.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            message: result.stderr.trim().to_string(),
+        });
+    }
+    Ok(result.stdout)
+}
+
+/// Diff as text: a patch, a diffstat or changed names
+pub async fn text(
+    dir: &Path,
+    from: &str,
+    to: Option<&str>,
+    paths: &[String],
+    mode: DiffMode,
+    color: bool,
+) -> Result<String, GitError> {
+    if mode == DiffMode::Patch {
+        return readable_patch(dir, from, to, paths, color).await;
+    }
+    git_text(dir, from, to, paths, mode, color).await
+}
+
+async fn git_text(
+    dir: &Path,
+    from: &str,
+    to: Option<&str>,
+    paths: &[String],
+    mode: DiffMode,
+    color: bool,
+) -> Result<String, GitError> {
+    let mut options = Vec::new();
+    match mode {
+        DiffMode::Patch | DiffMode::Lines => {}
+        DiffMode::Stat => options.push("--stat"),
+        DiffMode::NameOnly => options.push("--name-only"),
+    }
+    if color {
+        options.push("--color=always");
+    }
+    run_diff(dir, &diff_args(&options, from, to, paths)).await
+}
+
+/// A patch where modified markdown and text files show changed words under
+/// their section headings, data files show changed key paths, and
+/// everything else shows changed lines
+async fn readable_patch(
+    dir: &Path,
+    from: &str,
+    to: Option<&str>,
+    paths: &[String],
+    color: bool,
+) -> Result<String, GitError> {
+    let files = parse_name_status(
+        &run_diff(dir, &diff_args(&["--name-status", "-z"], from, to, paths)).await?,
+    );
+
+    // Rendered files, and a slot for each file left to a line diff
+    let mut parts: Vec<Option<String>> = Vec::new();
+    let mut fallback_paths = Vec::new();
+    for file in files {
+        let prose = prose::is_prose(&file.path);
+        let format = structured::Format::from_path(&file.path);
+        if matches!(file.change, "modified" | "renamed") && (prose || format.is_some()) {
+            let old_path = file.from.as_deref().unwrap_or(&file.path);
+            let old = read_blob(dir, Some(from), old_path).await?;
+            let new = read_blob(dir, to, &file.path).await?;
+            let rendered = match format {
+                Some(format) => structured::render(format, old_path, &file.path, &old, &new, color),
+                None => prose::render(old_path, &file.path, &old, &new, color),
+            };
+            if rendered.is_some() {
+                parts.push(rendered);
+                continue;
+            }
+        }
+
+        parts.push(None);
+        fallback_paths.push(file.path);
+        fallback_paths.extend(file.from);
+    }
+    if fallback_paths.is_empty() {
+        return Ok(parts.into_iter().flatten().collect());
+    }
+
+    // One diff for every other file; names are exact, not pathspec patterns
+    let mut options = Vec::new();
+    if color {
+        options.push("--color=always");
+    }
+    let mut args = vec!["--literal-pathspecs"];
+    args.extend(diff_args(&options, from, to, &fallback_paths));
+    let patch = run_diff(dir, &args).await?;
+
+    // git lists files in the same order, so fill the slots in turn
+    let mut patches = split_patch(&patch).into_iter();
+    let mut output = String::new();
+    for part in parts {
+        match part {
+            Some(rendered) => output.push_str(&rendered),
+            None => output.push_str(patches.next().unwrap_or_default()),
+        }
+    }
+    output.extend(patches);
+    Ok(output)
+}
+
+/// Split a patch into one piece per file, at each `diff --git` header
+fn split_patch(patch: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = Vec::new();
+    let mut offset = 0;
+    for line in patch.split_inclusive('\n') {
+        // Skip a color code before the header
+        let plain = match line.strip_prefix('\x1b') {
+            Some(rest) => rest.split_once('m').map_or(rest, |(_, rest)| rest),
+            None => line,
+        };
+        if plain.starts_with("diff --git ") {
+            starts.push(offset);
+        }
+        offset += line.len();
+    }
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts.push(patch.len());
+    starts
+        .windows(2)
+        .map(|w| &patch[w[0]..w[1]])
+        .filter(|piece| !piece.is_empty())
+        .collect()
+}
+
+/// Contents of `path` at `commit`, or in the index when `commit` is `None`
+async fn read_blob(dir: &Path, commit: Option<&str>, path: &str) -> Result<String, GitError> {
+    let spec = format!("{}:{}", commit.unwrap_or(""), path);
+    run_diff(dir, &["cat-file", "blob", &spec]).await
+}
+
+/// Per-file changes with line counts
+pub async fn summarize(
+    dir: &Path,
+    from: &str,
+    to: Option<&str>,
+    paths: &[String],
+) -> Result<DiffSummary, GitError> {
+    let statuses = run_diff(dir, &diff_args(&["--name-status", "-z"], from, to, paths)).await?;
+    let numstat = run_diff(dir, &diff_args(&["--numstat", "-z"], from, to, paths)).await?;
+
+    let counts = parse_numstat(&numstat);
+    let mut files = parse_name_status(&statuses);
+    for file in &mut files {
+        if let Some((insertions, deletions)) = counts.get(&file.path) {
+            file.insertions = *insertions;
+            file.deletions = *deletions;
+        }
+    }
+
+    Ok(DiffSummary {
+        from: from.to_string(),
+        to: to.map(str::to_string),
+        insertions: files.iter().filter_map(|f| f.insertions).sum(),
+        deletions: files.iter().filter_map(|f| f.deletions).sum(),
+        files,
+    })
+}
+
+/// Parse `--name-status -z`: a status field, then one path, or two for
+/// renames and copies
+fn parse_name_status(output: &str) -> Vec<FileDiff> {
+    let mut fields = output.split('\0').filter(|f| !f.is_empty());
+    let mut files = Vec::new();
+    while let Some(status) = fields.next() {
+        let (change, paths) = match status.chars().next() {
+            Some('A') => ("added", 1),
+            Some('D') => ("deleted", 1),
+            Some('R') => ("renamed", 2),
+            Some('C') => ("copied", 2),
+            Some('T') => ("type_changed", 1),
+            _ => ("modified", 1),
+        };
+        let from = if paths == 2 {
+            fields.next().map(str::to_string)
+        } else {
+            None
+        };
+        let Some(path) = fields.next() else {
+            break;
+        };
+        files.push(FileDiff {
+            path: path.to_string(),
+            change,
+            from,
+            insertions: None,
+            deletions: None,
+        });
+    }
+    files
+}
+
+/// Parse `--numstat -z` into counts by (new) path; binary files map to `None`
+fn parse_numstat(output: &str) -> HashMap<String, (Option<u64>, Option<u64>)> {
+    let mut fields = output.split('\0');
+    let mut counts = HashMap::new();
+    while let Some(record) = fields.next() {
+        let mut parts = record.splitn(3, '\t');
+        let (Some(insertions), Some(deletions), Some(path)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        // Renames leave the path empty and follow with old and new paths
+        let path = if path.is_empty() {
+            fields.next();
+            match fields.next() {
+                Some(path) => path,
+                None => break,
+            }
+        } else {
+            path
+        };
+        counts.insert(
+            path.to_string(),
+            (insertions.parse().ok(), deletions.parse().ok()),
+        );
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{commit_all, ensure_repo_initialized};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_name_status_and_numstat() {
+        let files = parse_name_status("M\0a.md\0R087\0old.md\0new.md\0D\0gone.md\0");
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[1].change, "renamed");
+        assert_eq!(files[1].from.as_deref(), Some("old.md"));
+        assert_eq!(files[1].path, "new.md");
+
+        let counts = parse_numstat("3\t1\ta.md\0-\t-\timg.png\x001\t0\t\0old.md\0new.md\0");
+        assert_eq!(counts["a.md"], (Some(3), Some(1)));
+        assert_eq!(counts["img.png"], (None, None));
+        assert_eq!(counts["new.md"], (Some(1), Some(0)));
+    }
+
+    #[tokio::test]
+    async fn test_summarize_against_working_tree() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        std::fs::write(dir.join("plan.md"), "one\ntwo\n").unwrap();
+        let first = commit_all(dir, "v1").await.unwrap();
+
+        std::fs::write(dir.join("plan.md"), "one\nthree\n").unwrap();
+        std::fs::write(dir.join("new.md"), "fresh\n").unwrap();
+        let index_path = dir.join(git::GSD_DIR).join("index");
+        let index_before = std::fs::read(&index_path).unwrap();
+
+        let only_plan = vec!["plan.md".to_string()];
+        let (summary, names) = with_working_tree(dir, async {
+            let summary = summarize(dir, &first, None, &[]).await?;
+            let names = text(dir, &first, None, &only_plan, DiffMode::NameOnly, false).await?;
+            Ok::<_, GitError>((summary, names))
+        })
+        .await
+        .unwrap();
+
+        let changes: Vec<(&str, &str)> = summary
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.change))
+            .collect();
+        assert_eq!(changes, vec![("new.md", "added"), ("plan.md", "modified")]);
+        assert_eq!((summary.insertions, summary.deletions), (2, 1));
+        assert_eq!(names, "plan.md\n");
+
+        // The daemon's index is left alone
+        assert_eq!(std::fs::read(&index_path).unwrap(), index_before);
+    }
+
+    #[tokio::test]
+    async fn test_readable_patch_keeps_file_order() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        for name in ["[ab].log", "a.log", "b.md", "z.log"] {
+            std::fs::write(dir.join(name), "one two\n").unwrap();
+        }
+        let first = commit_all(dir, "v1").await.unwrap();
+        for name in ["[ab].log", "b.md", "z.log"] {
+            std::fs::write(dir.join(name), "one three\n").unwrap();
+        }
+        let patch = with_working_tree(dir, text(dir, &first, None, &[], DiffMode::Patch, false))
+            .await
+            .unwrap();
+        let headers: Vec<&str> = patch
+            .lines()
+            .filter(|l| l.starts_with("diff --git") || l.starts_with("+++"))
+            .collect();
+        // `[ab].log` is a name, not a pattern matching a.log
+        assert_eq!(
+            headers,
+            vec![
+                "diff --git a/[ab].log b/[ab].log",
+                "+++ b/[ab].log",
+                "+++ b/b.md",
+                "diff --git a/z.log b/z.log",
+                "+++ b/z.log",
+            ]
+        );
+        assert!(patch.contains("  one [-two-] {+three+}\n"), "{patch}");
+        assert_eq!(
+            split_patch("diff --git a\n-x\n\x1b[1mdiff --git b\n").len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt::Write;
+
+use super::{edit_script, paint, Edit, BOLD, CYAN, GREEN, RED, RESET};
+
+/// Extensions rendered as prose rather than line diffs
+const PROSE_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Unchanged words shown on each side of a change
+const CONTEXT_WORDS: usize = 8;
+
+/// Whether `path` is a markdown or plain text file
+pub fn is_prose(path: &str) -> bool {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| PROSE_EXTENSIONS.iter().any(|p| e.eq_ignore_ascii_case(p)))
+}
+
+/// A paragraph, heading or list item, with the headings it sits under
+#[derive(Debug)]
+struct Block<'a> {
+    words: Vec<&'a str>,
+    /// Enclosing headings, outermost first
+    section: Vec<String>,
+}
+
+/// Split a document into blocks. Blank lines, headings and list items
+/// start new blocks; line breaks inside a block are ignored, so reflowing
+/// a paragraph does not change it.
+fn blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+
+    let section = |headings: &[(usize, String)]| headings.iter().map(|(_, h)| h.clone()).collect();
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            current.extend(line.split_whitespace());
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush(&mut current, &mut blocks, section(&headings));
+        } else if let Some(level) = heading_level(trimmed) {
+            flush(&mut current, &mut blocks, section(&headings));
+            blocks.push(Block {
+                words: trimmed.split_whitespace().collect(),
+                section: section(&headings),
+            });
+            headings.retain(|(l, _)| *l < level);
+            headings.push((level, trimmed.trim_end().to_string()));
+        } else {
+            if is_list_item(trimmed) {
+                flush(&mut current, &mut blocks, section(&headings));
+            }
+            current.extend(line.split_whitespace());
+        }
+    }
+    flush(&mut current, &mut blocks, section(&headings));
+    blocks
+}
+
+fn flush<'a>(current: &mut Vec<&'a str>, blocks: &mut Vec<Block<'a>>, section: Vec<String>) {
+    if !current.is_empty() {
+        blocks.push(Block {
+            words: std::mem::take(current),
+            section,
+        });
+    }
+}
+
+/// Level of an ATX heading (`## Goals` is 2)
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' '))).then_some(level)
+}
+
+fn is_list_item(line: &str) -> bool {
+    if line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ") {
+        return true;
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && (line[digits..].starts_with(". ") || line[digits..].starts_with(") "))
+}
+
+/// Render the word-level changes between two versions of a prose file, or
+/// `None` if the documents are too different to compare block by block
+pub fn render(old_path: &str, new_path: &str, old: &str, new: &str, color: bool) -> Option<String> {
+    let old_blocks = blocks(old);
+    let new_blocks = blocks(new);
+    let old_words: Vec<&[&str]> = old_blocks.iter().map(|b| b.words.as_slice()).collect();
+    let new_words: Vec<&[&str]> = new_blocks.iter().map(|b| b.words.as_slice()).collect();
+    let edits = edit_script(&old_words, &new_words)?;
+
+    let reset = paint(RESET, color);
+    let mut out = String::new();
+    let _ = writeln!(out, "{}--- a/{}{}", paint(BOLD, color), old_path, reset);
+    let _ = writeln!(out, "{}+++ b/{}{}", paint(BOLD, color), new_path, reset);
+
+    let mut hunks = 0;
+    let mut k = 0;
+    while k < edits.len() {
+        if let Edit::Equal(..) = edits[k] {
+            k += 1;
+            continue;
+        }
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        while let Some(edit) = edits.get(k) {
+            match *edit {
+                Edit::Delete(i) => removed.push(i),
+                Edit::Insert(j) => added.push(j),
+                Edit::Equal(..) => break,
+            }
+            k += 1;
+        }
+        hunks += 1;
+
+        let section = match (added.first(), removed.first()) {
+            (Some(&j), _) => &new_blocks[j].section,
+            (None, Some(&i)) => &old_blocks[i].section,
+            (None, None) => continue,
+        };
+        let title = if section.is_empty() {
+            "(top)".to_string()
+        } else {
+            section.join(" › ")
+        };
+        let _ = writeln!(out, "{}@@ {} @@{}", paint(CYAN, color), title, reset);
+
+        let before = hunk_words(removed.iter().map(|&i| &old_blocks[i]));
+        let after = hunk_words(added.iter().map(|&j| &new_blocks[j]));
+        render_words(&mut out, &before, &after, color);
+    }
+
+    if hunks == 0 {
+        out.push_str("  (only whitespace or line wrapping changed)\n");
+    }
+    Some(out)
+}
+
+/// Words of consecutive blocks, with an empty word marking each break
+fn hunk_words<'a>(blocks: impl Iterator<Item = &'a Block<'a>>) -> Vec<&'a str> {
+    let mut words = Vec::new();
+    for block in blocks {
+        if !words.is_empty() {
+            words.push("");
+        }
+        words.extend_from_slice(&block.words);
+    }
+    words
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Same,
+    Removed,
+    Added,
+}
+
+/// Write one hunk as `[-removed-]{+added+}` runs, trimming long unchanged
+/// stretches to a few words of context
+fn render_words(out: &mut String, before: &[&str], after: &[&str], color: bool) {
+    let tokens: Vec<(Kind, &str)> = match edit_script(before, after) {
+        Some(edits) => edits
+            .into_iter()
+            .map(|edit| match edit {
+                Edit::Equal(i, _) => (Kind::Same, before[i]),
+                Edit::Delete(i) => (Kind::Removed, before[i]),
+                Edit::Insert(j) => (Kind::Added, after[j]),
+            })
+            .collect(),
+        None => before
+            .iter()
+            .map(|w| (Kind::Removed, *w))
+            .chain(after.iter().map(|w| (Kind::Added, *w)))
+            .collect(),
+    };
+
+    // Group into runs of one kind
+    let mut runs: Vec<(Kind, Vec<&str>)> = Vec::new();
+    for (kind, word) in tokens {
+        match runs.last_mut() {
+            Some((last, words)) if *last == kind => words.push(word),
+            _ => runs.push((kind, vec![word])),
+        }
+    }
+
+    let mut line = String::from("  ");
+    let last = runs.len().saturating_sub(1);
+    for (index, (kind, words)) in runs.iter().enumerate() {
+        let shown: Vec<&str> = match kind {
+            Kind::Same => trim_context(words, index == 0, index == last),
+            _ => words.clone(),
+        };
+        let (open, close, code) = match kind {
+            Kind::Same => ("", "", ""),
+            Kind::Removed => ("[-", "-]", RED),
+            Kind::Added => ("{+", "+}", GREEN),
+        };
+
+        let mut segments = shown.split(|w| w.is_empty()).peekable();
+        while let Some(segment) = segments.next() {
+            if !segment.is_empty() {
+                if !line.trim().is_empty() && !line.ends_with(' ') {
+                    line.push(' ');
+                }
+                if color && !code.is_empty() {
+                    line.push_str(code);
+                }
+                line.push_str(open);
+                line.push_str(&segment.join(" "));
+                line.push_str(close);
+                if color && !code.is_empty() {
+                    line.push_str(RESET);
+                }
+            }
+            // A block break inside the run starts a new line
+            if segments.peek().is_some() && !line.trim().is_empty() {
+                out.push_str(&line);
+                out.push('\n');
+                line = String::from("  ");
+            }
+        }
+    }
+    if !line.trim().is_empty() {
+        out.push_str(&line);
+        out.push('\n');
+    }
+}
+
+/// Keep a few unchanged words around each change; `…` marks what was cut
+fn trim_context<'a>(words: &[&'a str], first: bool, last: bool) -> Vec<&'a str> {
+    let keep_start = if first { 0 } else { CONTEXT_WORDS };
+    let keep_end = if last { 0 } else { CONTEXT_WORDS };
+    if words.len() <= keep_start + keep_end + 1 {
+        return words.to_vec();
+    }
+    // Context never reaches across a block break
+    let head = &words[..keep_start];
+    let head = &head[..head.iter().position(|w| w.is_empty()).unwrap_or(head.len())];
+    let tail = &words[words.len() - keep_end..];
+    let tail = &tail[tail.iter().rposition(|w| w.is_empty()).map_or(0, |p| p + 1)..];
+
+    let mut trimmed = head.to_vec();
+    trimmed.push("…");
+    trimmed.extend_from_slice(tail);
+    trimmed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reflow_is_not_a_change() {
+        let old = "# Plan\n\nShip the beta by Friday and\ntell the team.\n";
+        let new = "# Plan\n\nShip the beta\nby Friday and tell the team.\n";
+        let out = render("plan.md", "plan.md", old, new, false).unwrap();
+        assert!(
+            out.contains("only whitespace or line wrapping changed"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn test_word_changes_with_section() {
+        let old = "# Roadmap\n\nIntro.\n\n## Goals\n\nShip the beta by Friday and then tell the whole team about it over lunch.\n\n## Later\n\nMaybe.\n";
+        let new = "# Roadmap\n\nIntro.\n\n## Goals\n\nShip the release by Friday and then tell the whole team about it over\nlunch.\n\n## Later\n\nMaybe.\n";
+        let out = render("plan.md", "plan.md", old, new, false).unwrap();
+        assert!(out.contains("@@ # Roadmap › ## Goals @@\n"), "{out}");
+        assert!(
+            out.contains(
+                "  Ship the [-beta-] {+release+} by Friday and then tell the whole team …\n"
+            ),
+            "{out}"
+        );
+        assert!(!out.contains("Maybe"), "{out}");
+    }
+
+    #[test]
+    fn test_added_paragraph_and_list_items() {
+        let old = "Notes\n\n- one\n- two\n";
+        let new = "Notes\n\n- one\n- two and a half\n\nA new paragraph.\n";
+        let out = render("notes.txt", "notes.txt", old, new, false).unwrap();
+        assert!(out.contains("@@ (top) @@\n"), "{out}");
+        assert!(
+            out.contains("  - two {+and a half+}\n  {+A new paragraph.+}\n"),
+            "{out}"
+        );
+        assert!(is_prose("docs/Plan.MD"));
+        assert!(!is_prose("config.toml"));
+    }
+}
@@ -0,0 +1,307 @@
+use std::fmt::Write;
+
+use serde_json::{Map, Value};
+
+use super::{edit_script, paint, Edit, BOLD, GREEN, RED, RESET, YELLOW};
+
+/// Longest value printed before it is cut short
+const MAX_VALUE_CHARS: usize = 120;
+
+/// The key TOML's serde support wraps date-times in
+const TOML_DATETIME: &str = "$__toml_private_datetime";
+
+/// A data format compared by key path rather than by line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Parse a document into a JSON value, or `None` if it does not parse
+    fn parse(self, text: &str) -> Option<Value> {
+        match self {
+            Format::Json => serde_json::from_str(text).ok(),
+            Format::Toml => toml::from_str(text).ok().map(unwrap_datetimes),
+            Format::Yaml => serde_yaml_ng::from_str(text).ok(),
+        }
+    }
+}
+
+/// Replace TOML's wrapped date-times with their string form
+fn unwrap_datetimes(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            if map.len() == 1 {
+                if let Some(Value::String(time)) = map.get(TOML_DATETIME) {
+                    return Value::String(time.clone());
+                }
+            }
+            Value::Object(
+                map.into_iter()
+                    .map(|(k, v)| (k, unwrap_datetimes(v)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(unwrap_datetimes).collect()),
+        other => other,
+    }
+}
+
+/// One difference between two documents
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    Added(String, Value),
+    Removed(String, Value),
+    Changed(String, Value, Value),
+}
+
+/// Differences from `old` to `new` by key path. Object keys are compared
+/// by name, so reordering them is not a change; array items are matched in
+/// order.
+fn compare(path: &str, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => compare_objects(path, old, new, changes),
+        (Value::Array(old), Value::Array(new)) => compare_arrays(path, old, new, changes),
+        _ if old != new => {
+            changes.push(Change::Changed(path.to_string(), old.clone(), new.clone()))
+        }
+        _ => {}
+    }
+}
+
+fn compare_objects(
+    path: &str,
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    changes: &mut Vec<Change>,
+) {
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let child = key_path(path, key);
+        match (old.get(key), new.get(key)) {
+            (Some(old), Some(new)) => compare(&child, old, new, changes),
+            (Some(old), None) => changes.push(Change::Removed(child, old.clone())),
+            (None, Some(new)) => changes.push(Change::Added(child, new.clone())),
+            (None, None) => {}
+        }
+    }
+}
+
+fn compare_arrays(path: &str, old: &[Value], new: &[Value], changes: &mut Vec<Change>) {
+    let Some(edits) = edit_script(old, new) else {
+        changes.push(Change::Changed(
+            path.to_string(),
+            Value::Array(old.to_vec()),
+            Value::Array(new.to_vec()),
+        ));
+        return;
+    };
+
+    // Pair each run of removed items with the added items that replace them
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for edit in edits.into_iter().chain([Edit::Equal(0, 0)]) {
+        match edit {
+            Edit::Delete(i) => removed.push(i),
+            Edit::Insert(j) => added.push(j),
+            Edit::Equal(..) => {
+                for pair in 0..removed.len().max(added.len()) {
+                    match (removed.get(pair), added.get(pair)) {
+                        (Some(&i), Some(&j)) => {
+                            compare(&format!("{}[{}]", path, j), &old[i], &new[j], changes)
+                        }
+                        (Some(&i), None) => changes
+                            .push(Change::Removed(format!("{}[{}]", path, i), old[i].clone())),
+                        (None, Some(&j)) => {
+                            changes.push(Change::Added(format!("{}[{}]", path, j), new[j].clone()))
+                        }
+                        (None, None) => {}
+                    }
+                }
+                removed.clear();
+                added.clear();
+            }
+        }
+    }
+}
+
+/// `parent.key`, quoting keys that are not plain identifiers
+fn key_path(parent: &str, key: &str) -> String {
+    let plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    let key = if plain {
+        key.to_string()
+    } else {
+        serde_json::to_string(key).unwrap_or_default()
+    };
+    if parent.is_empty() {
+        key
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Compact JSON form of a value, cut short if long
+fn show(value: &Value) -> String {
+    let text = serde_json::to_string(value).unwrap_or_default();
+    if text.chars().count() <= MAX_VALUE_CHARS {
+        text
+    } else {
+        let cut: String = text.chars().take(MAX_VALUE_CHARS).collect();
+        format!("{}…", cut)
+    }
+}
+
+/// Render the key-level changes between two versions of a data file, or
+/// `None` if either version does not parse
+pub fn render(
+    format: Format,
+    old_path: &str,
+    new_path: &str,
+    old: &str,
+    new: &str,
+    color: bool,
+) -> Option<String> {
+    let old_value = format.parse(old)?;
+    let new_value = format.parse(new)?;
+    let mut changes = Vec::new();
+    compare("", &old_value, &new_value, &mut changes);
+
+    let reset = paint(RESET, color);
+    let mut out = String::new();
+    let _ = writeln!(out, "{}--- a/{}{}", paint(BOLD, color), old_path, reset);
+    let _ = writeln!(out, "{}+++ b/{}{}", paint(BOLD, color), new_path, reset);
+    if changes.is_empty() {
+        out.push_str("  (only formatting or key order changed)\n");
+    }
+    for change in changes {
+        let _ = match change {
+            Change::Added(path, value) => {
+                writeln!(
+                    out,
+                    "{}+ {}: {}{}",
+                    paint(GREEN, color),
+                    root(&path),
+                    show(&value),
+                    reset
+                )
+            }
+            Change::Removed(path, value) => {
+                writeln!(
+                    out,
+                    "{}- {}: {}{}",
+                    paint(RED, color),
+                    root(&path),
+                    show(&value),
+                    reset
+                )
+            }
+            Change::Changed(path, old, new) => writeln!(
+                out,
+                "{}~ {}: {} → {}{}",
+                paint(YELLOW, color),
+                root(&path),
+                show(&old),
+                show(&new),
+                reset
+            ),
+        };
+    }
+    Some(out)
+}
+
+fn root(path: &str) -> &str {
+    if path.is_empty() {
+        "(document)"
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reordered_keys_are_not_a_change() {
+        let old = "[server]\nhost = \"a\"\nport = 80\n\n[log]\nlevel = \"info\"\n";
+        let new = "[log]\nlevel = \"info\"\n\n[server]\nport = 80\nhost = \"a\"\n";
+        let out = render(Format::Toml, "c.toml", "c.toml", old, new, false).unwrap();
+        assert!(
+            out.contains("only formatting or key order changed"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn test_key_paths_across_formats() {
+        let out = render(
+            Format::Json,
+            "a.json",
+            "a.json",
+            r#"{"server": {"port": 80, "debug": true}, "tags": ["a", "b"], "x.y": 1}"#,
+            r#"{"tags": ["z", "a", "b"], "server": {"port": 8080, "tls": {"on": true}}, "x.y": 2}"#,
+            false,
+        )
+        .unwrap();
+        let lines: Vec<&str> = out.lines().skip(2).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- server.debug: true",
+                "~ server.port: 80 → 8080",
+                "+ server.tls: {\"on\":true}",
+                "+ tags[0]: \"z\"",
+                "~ \"x.y\": 1 → 2",
+            ]
+        );
+
+        let out = render(
+            Format::Yaml,
+            "a.yml",
+            "a.yml",
+            "steps:\n  - name: build\n    run: make\n",
+            "steps:\n  - run: make all\n    name: build\n",
+            false,
+        )
+        .unwrap();
+        assert!(
+            out.contains("~ steps[0].run: \"make\" → \"make all\"\n"),
+            "{out}"
+        );
+
+        let out = render(
+            Format::Toml,
+            "c.toml",
+            "c.toml",
+            "at = 2026-01-21T10:00:00Z\n",
+            "at = 2026-01-22T10:00:00Z\n",
+            false,
+        )
+        .unwrap();
+        assert!(
+            out.contains("~ at: \"2026-01-21T10:00:00Z\" → \"2026-01-22T10:00:00Z\""),
+            "{out}"
+        );
+
+        assert!(render(Format::Json, "a.json", "a.json", "{", "{}", false).is_none());
+        assert_eq!(Format::from_path("ci/deploy.YML"), Some(Format::Yaml));
+        assert_eq!(Format::from_path("plan.md"), None);
+    }
+}
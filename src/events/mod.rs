@@ -0,0 +1,690 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::config::{EventSinkConfig, TargetConfig};
+use crate::logging::stable_hash;
+use crate::shell::{self, ShellError};
+use crate::snapshot::SnapshotTrigger;
+
+/// Version of the event JSON schema; bumped on incompatible changes
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Events buffered per sink before new ones are dropped
+const SINK_BUFFER: usize = 1024;
+
+/// How often an HTTP sink retries its on-disk queue while idle
+const QUEUE_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Limit for a single HTTP delivery attempt
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An event emitted by the snapshot service.
+///
+/// Serialized as one JSON object with `schema_version`, `time` (RFC 3339,
+/// UTC), `type` and the type-specific fields documented in the README.
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub schema_version: u32,
+    pub time: String,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    SnapshotCreated {
+        target: TargetRef,
+        trigger: SnapshotTrigger,
+        commit: String,
+        files: Vec<String>,
+        duration_ms: u64,
+    },
+    SnapshotFailed {
+        target: TargetRef,
+        trigger: SnapshotTrigger,
+        error: String,
+    },
+    TargetAdded {
+        target: TargetRef,
+    },
+    TargetRemoved {
+        target: TargetRef,
+    },
+    ConfigReloaded {
+        targets: usize,
+    },
+    Anomaly {
+        target: TargetRef,
+        anomaly: String,
+        message: String,
+    },
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::SnapshotCreated { .. } => "snapshot_created",
+            EventKind::SnapshotFailed { .. } => "snapshot_failed",
+            EventKind::TargetAdded { .. } => "target_added",
+            EventKind::TargetRemoved { .. } => "target_removed",
+            EventKind::ConfigReloaded { .. } => "config_reloaded",
+            EventKind::Anomaly { .. } => "anomaly",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetRef {
+    pub name: String,
+    pub path: String,
+}
+
+impl From<&TargetConfig> for TargetRef {
+    fn from(target: &TargetConfig) -> Self {
+        Self {
+            name: target.name().to_string(),
+            path: target.path.to_string_lossy().to_string(),
+        }
+    }
+}
+
+enum BusMessage {
+    Event(Event),
+    Reconfigure(Vec<EventSinkConfig>),
+}
+
+/// Handle used to emit events; cheap to clone.
+///
+/// Emitting never blocks: events go through a bounded channel to a dispatcher
+/// task that fans them out to one task per sink.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    tx: mpsc::Sender<BusMessage>,
+}
+
+impl std::fmt::Debug for BusMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BusMessage::Event(event) => write!(f, "Event({})", event.kind.name()),
+            BusMessage::Reconfigure(sinks) => write!(f, "Reconfigure({} sinks)", sinks.len()),
+        }
+    }
+}
+
+/// Receiving side of the bus, started once the runtime is running
+#[derive(Debug)]
+pub struct EventDispatcher {
+    rx: mpsc::Receiver<BusMessage>,
+    sinks: Vec<EventSinkConfig>,
+}
+
+impl EventBus {
+    pub fn new(sinks: Vec<EventSinkConfig>) -> (Self, EventDispatcher) {
+        let (tx, rx) = mpsc::channel(SINK_BUFFER);
+        (Self { tx }, EventDispatcher { rx, sinks })
+    }
+
+    pub fn emit(&self, kind: EventKind) {
+        let event = Event {
+            schema_version: EVENT_SCHEMA_VERSION,
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            kind,
+        };
+        if self.tx.try_send(BusMessage::Event(event)).is_err() {
+            warn!("Event queue full or closed, dropping event");
+        }
+    }
+
+    /// Replace the active sinks (used on config reload)
+    pub async fn reconfigure(&self, sinks: Vec<EventSinkConfig>) {
+        let _ = self.tx.send(BusMessage::Reconfigure(sinks)).await;
+    }
+}
+
+impl EventDispatcher {
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(mut self) {
+        let mut running = start_sinks(&self.sinks);
+
+        while let Some(msg) = self.rx.recv().await {
+            match msg {
+                BusMessage::Event(event) => {
+                    let line = match serde_json::to_string(&event) {
+                        Ok(line) => line,
+                        Err(e) => {
+                            warn!(error = %e, "Failed to serialize event");
+                            continue;
+                        }
+                    };
+                    for sender in &running.senders {
+                        if sender
+                            .try_send(SinkItem::new(event.kind.name(), &line))
+                            .is_err()
+                        {
+                            warn!(
+                                event = event.kind.name(),
+                                "Event sink is backed up, dropping event"
+                            );
+                        }
+                    }
+                }
+                BusMessage::Reconfigure(sinks) => {
+                    // Let the old tasks drain before new ones touch the same queue files
+                    running.shutdown().await;
+                    running = start_sinks(&sinks);
+                    self.sinks = sinks;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SinkItem {
+    kind: &'static str,
+    json: String,
+}
+
+impl SinkItem {
+    fn new(kind: &'static str, json: &str) -> Self {
+        Self {
+            kind,
+            json: json.to_string(),
+        }
+    }
+}
+
+/// Sink tasks and the channels feeding them
+struct RunningSinks {
+    senders: Vec<mpsc::Sender<SinkItem>>,
+    handles: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl RunningSinks {
+    /// Close the channels and wait for every task to drain and exit
+    async fn shutdown(self) {
+        drop(self.senders);
+        for handle in self.handles {
+            let _ = handle.await;
+        }
+    }
+}
+
+fn start_sinks(sinks: &[EventSinkConfig]) -> RunningSinks {
+    let mut running = RunningSinks {
+        senders: Vec::with_capacity(sinks.len()),
+        handles: Vec::with_capacity(sinks.len()),
+    };
+    for sink in sinks {
+        let (tx, rx) = mpsc::channel(SINK_BUFFER);
+        running
+            .handles
+            .push(tokio::spawn(run_sink(sink.clone(), rx)));
+        running.senders.push(tx);
+    }
+    running
+}
+
+async fn run_sink(sink: EventSinkConfig, mut rx: mpsc::Receiver<SinkItem>) {
+    match sink {
+        EventSinkConfig::Jsonl { path } => {
+            while let Some(item) = rx.recv().await {
+                if let Err(e) = append_line(&path, &item.json).await {
+                    warn!(path = %path.display(), error = %e, "Failed to write event");
+                }
+            }
+        }
+        EventSinkConfig::Command {
+            command,
+            timeout_seconds,
+        } => {
+            let timeout = Duration::from_secs(timeout_seconds);
+            while let Some(item) = rx.recv().await {
+                if let Err(e) = run_command(&command, timeout, &item).await {
+                    warn!(command = %command, error = %e, "Event command failed");
+                }
+            }
+        }
+        EventSinkConfig::Http {
+            url,
+            retries,
+            queue_path,
+        } => {
+            let queue_path = queue_path.unwrap_or_else(|| default_queue_path(&url));
+            let sink = HttpSink {
+                url,
+                retries,
+                queue_path,
+            };
+            sink.run(rx).await;
+        }
+    }
+}
+
+/// Queue file named after the URL, so queued events stay with their endpoint
+fn default_queue_path(url: &str) -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("gsd")
+        .join("event-queue")
+        .join(format!(
+            "http-sink-{:016x}.jsonl",
+            stable_hash(url.as_bytes())
+        ))
+}
+
+async fn append_line(path: &Path, line: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(format!("{}\n", line).as_bytes()).await?;
+    file.flush().await
+}
+
+async fn run_command(command: &str, timeout: Duration, item: &SinkItem) -> std::io::Result<()> {
+    let mut cmd = shell::command(command);
+    cmd.env("GSD_EVENT", item.kind).stdout(Stdio::null());
+
+    let input = format!("{}\n", item.json);
+    let output = shell::run(
+        cmd,
+        input.as_bytes(),
+        timeout,
+        shell::MAX_LOGGED_OUTPUT_BYTES,
+    )
+    .await
+    .map_err(|e| match e {
+        ShellError::Io(e) => e,
+        ShellError::Timeout(_) => std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out"),
+    })?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// POSTs events to a local HTTP endpoint.
+///
+/// Deliveries that still fail after `retries` go to an on-disk JSONL queue,
+/// which is replayed in order before any newer event is sent.
+struct HttpSink {
+    url: String,
+    retries: u32,
+    queue_path: PathBuf,
+}
+
+impl HttpSink {
+    async fn run(&self, mut rx: mpsc::Receiver<SinkItem>) {
+        let mut retry_timer = tokio::time::interval(QUEUE_RETRY_INTERVAL);
+        retry_timer.tick().await;
+
+        loop {
+            tokio::select! {
+                item = rx.recv() => {
+                    let Some(item) = item else { break };
+                    self.deliver(&item.json).await;
+                }
+                _ = retry_timer.tick() => {
+                    self.flush_queue().await;
+                }
+            }
+        }
+    }
+
+    async fn deliver(&self, json: &str) {
+        if !self.flush_queue().await {
+            // Keep ordering: nothing new is sent while older events are queued
+            self.enqueue(json).await;
+            return;
+        }
+
+        if !self.post_with_retries(json).await {
+            self.enqueue(json).await;
+        }
+    }
+
+    async fn post_with_retries(&self, json: &str) -> bool {
+        let mut backoff = Duration::from_secs(1);
+        for attempt in 0..=self.retries {
+            match post_json(&self.url, json).await {
+                Ok(()) => return true,
+                Err(e) => {
+                    debug!(url = %self.url, attempt, error = %e, "Event delivery failed");
+                    if attempt < self.retries {
+                        tokio::time::sleep(backoff).await;
+                        backoff *= 2;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    async fn enqueue(&self, json: &str) {
+        match append_line(&self.queue_path, json).await {
+            Ok(()) => warn!(
+                url = %self.url,
+                queue = %self.queue_path.display(),
+                "Event delivery failed, queued on disk"
+            ),
+            Err(e) => warn!(
+                queue = %self.queue_path.display(),
+                error = %e,
+                "Failed to queue undelivered event, dropping it"
+            ),
+        }
+    }
+
+    /// Replay queued events; returns true once the queue is empty
+    async fn flush_queue(&self) -> bool {
+        let content = match fs::read_to_string(&self.queue_path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return true,
+            Err(e) => {
+                warn!(queue = %self.queue_path.display(), error = %e, "Failed to read event queue");
+                return false;
+            }
+        };
+
+        let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+        let mut sent = 0;
+        for line in &lines {
+            if post_json(&self.url, line).await.is_err() {
+                break;
+            }
+            sent += 1;
+        }
+
+        if sent == lines.len() {
+            let _ = fs::remove_file(&self.queue_path).await;
+            return true;
+        }
+
+        if sent > 0 {
+            let rest = format!("{}\n", lines[sent..].join("\n"));
+            if let Err(e) = fs::write(&self.queue_path, rest).await {
+                warn!(queue = %self.queue_path.display(), error = %e, "Failed to rewrite event queue");
+            }
+        }
+        false
+    }
+}
+
+/// Minimal HTTP/1.1 POST for `http://host[:port]/path` URLs
+async fn post_json(url: &str, json: &str) -> std::io::Result<()> {
+    let (host, port, path) = parse_http_url(url)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid URL"))?;
+
+    let request = async {
+        let mut stream = TcpStream::connect((host.as_str(), port)).await?;
+        let head = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nUser-Agent: gsd/{}\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            path,
+            host,
+            port,
+            env!("CARGO_PKG_VERSION"),
+            json.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(json.as_bytes()).await?;
+        stream.flush().await?;
+
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line).await?;
+        Ok::<_, std::io::Error>(status_line)
+    };
+
+    let status_line = tokio::time::timeout(HTTP_TIMEOUT, request)
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out"))??;
+
+    let code = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|c| c.parse::<u16>().ok())
+        .ok_or_else(|| std::io::Error::other(format!("bad response: {:?}", status_line)))?;
+    if !(200..300).contains(&code) {
+        return Err(std::io::Error::other(format!("HTTP status {}", code)));
+    }
+    Ok(())
+}
+
+/// Split an `http://` URL into host, port and path
+pub fn parse_http_url(url: &str) -> Option<(String, u16, String)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(idx) => (&rest[..idx], &rest[idx..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return None;
+    }
+    // A bracketed IPv6 host contains colons of its own
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !authority.ends_with(']') => (host, port.parse().ok()?),
+        _ => (authority, 80),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Some((host.to_string(), port, path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    fn target_ref() -> TargetRef {
+        TargetRef {
+            name: "notes".to_string(),
+            path: "/tmp/notes".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_event_schema() {
+        let event = Event {
+            schema_version: EVENT_SCHEMA_VERSION,
+            time: "2026-01-01T00:00:00.000Z".to_string(),
+            kind: EventKind::SnapshotCreated {
+                target: target_ref(),
+                trigger: SnapshotTrigger::Interval,
+                commit: "abc".to_string(),
+                files: vec!["a.md".to_string()],
+                duration_ms: 12,
+            },
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["schema_version"], 1);
+        assert_eq!(value["type"], "snapshot_created");
+        assert_eq!(value["target"]["name"], "notes");
+        assert_eq!(value["trigger"], "interval");
+        assert_eq!(value["files"][0], "a.md");
+    }
+
+    #[test]
+    fn test_parse_http_url() {
+        assert_eq!(
+            parse_http_url("http://127.0.0.1:8080/hooks/gsd"),
+            Some(("127.0.0.1".to_string(), 8080, "/hooks/gsd".to_string()))
+        );
+        assert_eq!(
+            parse_http_url("http://localhost"),
+            Some(("localhost".to_string(), 80, "/".to_string()))
+        );
+        assert_eq!(
+            parse_http_url("http://[::1]/hook"),
+            Some(("::1".to_string(), 80, "/hook".to_string()))
+        );
+        assert_eq!(
+            parse_http_url("http://[::1]:9000/hook"),
+            Some(("::1".to_string(), 9000, "/hook".to_string()))
+        );
+        assert_eq!(parse_http_url("https://example.com"), None);
+    }
+
+    #[test]
+    fn test_default_queue_path_follows_url() {
+        let a = default_queue_path("http://127.0.0.1:8080/a");
+        assert_eq!(a, default_queue_path("http://127.0.0.1:8080/a"));
+        assert_ne!(a, default_queue_path("http://127.0.0.1:8080/b"));
+    }
+
+    async fn accept_one(listener: &TcpListener) -> String {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 4096];
+        let mut received = Vec::new();
+        // Read until the body (after the blank line) has arrived
+        loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            received.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&received);
+            if n == 0 || text.contains("\r\n\r\n") && text.trim_end().ends_with('}') {
+                break;
+            }
+        }
+        socket
+            .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8_lossy(&received).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_http_sink_queues_and_replays_in_order() {
+        let temp = TempDir::new().unwrap();
+
+        // Reserve a port, then close it so the first delivery fails
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let sink = HttpSink {
+            url: format!("http://{}/events", addr),
+            retries: 0,
+            queue_path: temp.path().join("queue.jsonl"),
+        };
+
+        sink.deliver(r#"{"n":1}"#).await;
+        assert!(sink.queue_path.exists());
+
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let server = tokio::spawn(async move {
+            let first = accept_one(&listener).await;
+            let second = accept_one(&listener).await;
+            (first, second)
+        });
+
+        sink.deliver(r#"{"n":2}"#).await;
+        let (first, second) = server.await.unwrap();
+
+        assert!(first.starts_with("POST /events HTTP/1.1"));
+        assert!(first.ends_with(r#"{"n":1}"#));
+        assert!(second.ends_with(r#"{"n":2}"#));
+        assert!(!sink.queue_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_keeps_pending_queue_in_order() {
+        let temp = TempDir::new().unwrap();
+        let queue_path = temp.path().join("queue.jsonl");
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let sink = EventSinkConfig::Http {
+            url: format!("http://{}/events", addr),
+            retries: 0,
+            queue_path: Some(queue_path.clone()),
+        };
+        let (bus, dispatcher) = EventBus::new(vec![sink.clone()]);
+        let handle = dispatcher.spawn();
+
+        bus.emit(EventKind::ConfigReloaded { targets: 1 });
+        for _ in 0..50 {
+            if queue_path.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(queue_path.exists());
+
+        let listener = TcpListener::bind(addr).await.unwrap();
+        bus.reconfigure(vec![sink]).await;
+        bus.emit(EventKind::ConfigReloaded { targets: 2 });
+
+        let first = accept_one(&listener).await;
+        let second = accept_one(&listener).await;
+        assert!(first.ends_with(r#""targets":1}"#));
+        assert!(second.ends_with(r#""targets":2}"#));
+
+        drop(bus);
+        handle.await.unwrap();
+        // Each event went out exactly once and the queue was consumed
+        let extra = tokio::time::timeout(Duration::from_millis(200), listener.accept()).await;
+        assert!(extra.is_err());
+        assert!(!queue_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_sink_appends_events() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("events.jsonl");
+
+        let (bus, dispatcher) = EventBus::new(vec![EventSinkConfig::Jsonl { path: path.clone() }]);
+        let handle = dispatcher.spawn();
+
+        bus.emit(EventKind::TargetAdded {
+            target: target_ref(),
+        });
+        bus.emit(EventKind::ConfigReloaded { targets: 1 });
+        drop(bus);
+        handle.await.unwrap();
+
+        // Sink tasks finish writing shortly after the dispatcher exits
+        for _ in 0..50 {
+            if fs::read_to_string(&path)
+                .await
+                .map(|c| c.lines().count() == 2)
+                .unwrap_or(false)
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let content = fs::read_to_string(&path).await.unwrap();
+        let types: Vec<String> = content
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["type"].to_string()
+            })
+            .collect();
+        assert_eq!(types, vec!["\"target_added\"", "\"config_reloaded\""]);
+    }
+}
@@ -0,0 +1,398 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use regex::bytes::Regex;
+
+use crate::config::{FilterConfig, FilterKind, TargetConfig};
+use crate::git::{shell_quote, FilterDriver};
+use crate::shell::{self, ShellError};
+use crate::sqlite;
+
+/// Filter driver name used for content filters in .gsd/info/attributes
+pub const CONTENT_FILTER: &str = "gsd-content";
+
+/// How long an external command filter may run before it is killed
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum FilterError {
+    #[error("invalid filter spec: {0}")]
+    Spec(String),
+
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("command `{command}` failed: {message}")]
+    Command { command: String, message: String },
+
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// All filter drivers a target needs installed in its snapshot repo.
+///
+/// Git applies only the last `filter=` attribute matching a path, so the
+/// SQLite driver comes last and runs the content filters itself on files
+/// that turn out not to be databases. Databases are never content-filtered.
+pub fn target_drivers(target: &TargetConfig) -> io::Result<Vec<FilterDriver>> {
+    let spec = filter_spec(target)?;
+    let mut drivers = Vec::new();
+    drivers.extend(filter_driver(target)?);
+    drivers.extend(sqlite::filter_driver(target, spec.as_deref())?);
+    Ok(drivers)
+}
+
+/// A target's filter list as JSON, or `None` when it has no filters
+fn filter_spec(target: &TargetConfig) -> io::Result<Option<String>> {
+    if target.filters.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(&target.filters)
+        .map(Some)
+        .map_err(io::Error::other)
+}
+
+/// Build the filter driver that runs a target's content filters.
+///
+/// The filter list is passed to the clean command as JSON, so the driver is
+/// self-contained and needs no state besides `.gsd/config`. Returns `None`
+/// when the target has no filters.
+pub fn filter_driver(target: &TargetConfig) -> io::Result<Option<FilterDriver>> {
+    let Some(spec) = filter_spec(target)? else {
+        return Ok(None);
+    };
+    let exe = std::env::current_exe()?;
+
+    let mut patterns: Vec<String> = target.filters.iter().map(|f| f.glob.clone()).collect();
+    patterns.sort();
+    patterns.dedup();
+
+    Ok(Some(FilterDriver {
+        name: CONTENT_FILTER.to_string(),
+        clean: format!(
+            "{} filter content --spec {} %f",
+            shell_quote(&exe.to_string_lossy()),
+            shell_quote(&spec)
+        ),
+        smudge: None,
+        patterns,
+    }))
+}
+
+/// Clean filter entry point: apply every filter in `spec` matching `path`.
+///
+/// A failing filter never blocks a snapshot; the raw content is stored
+/// instead and the error is reported on stderr for git to surface.
+pub fn clean(
+    spec: &str,
+    path: &Path,
+    mut input: impl Read,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut raw = Vec::new();
+    input.read_to_end(&mut raw)?;
+
+    let result = serde_json::from_str::<Vec<FilterConfig>>(spec)
+        .map_err(|e| FilterError::Spec(e.to_string()))
+        .and_then(|filters| apply_filters(&filters, path, raw.clone()));
+
+    match result {
+        Ok(filtered) => output.write_all(&filtered),
+        Err(e) => {
+            eprintln!(
+                "gsd: filter for {} failed, storing raw file: {}",
+                path.display(),
+                e
+            );
+            output.write_all(&raw)
+        }
+    }
+}
+
+/// Apply all filters whose glob matches `path`, in configuration order
+pub fn apply_filters(
+    filters: &[FilterConfig],
+    path: &Path,
+    mut content: Vec<u8>,
+) -> Result<Vec<u8>, FilterError> {
+    for filter in filters {
+        if !glob_matcher(&filter.glob)?.matches(path) {
+            continue;
+        }
+        content = apply_filter(filter, path, content)?;
+    }
+    Ok(content)
+}
+
+fn apply_filter(
+    filter: &FilterConfig,
+    path: &Path,
+    content: Vec<u8>,
+) -> Result<Vec<u8>, FilterError> {
+    match filter.kind {
+        FilterKind::Json => normalize_json(&content),
+        FilterKind::TrailingWhitespace => Ok(strip_trailing_whitespace(&content)),
+        FilterKind::DropLines => {
+            let pattern = filter
+                .pattern
+                .as_deref()
+                .ok_or_else(|| FilterError::Spec("drop_lines requires pattern".to_string()))?;
+            let regex = Regex::new(pattern).map_err(|e| FilterError::Spec(e.to_string()))?;
+            Ok(drop_lines(&content, &regex))
+        }
+        FilterKind::Command => {
+            let command = filter
+                .command
+                .as_deref()
+                .ok_or_else(|| FilterError::Spec("command filter requires command".to_string()))?;
+            run_command_filter(command, path, content)
+        }
+    }
+}
+
+/// Match like gitattributes: patterns without '/' match the file name only
+struct PathGlob {
+    matcher: GlobMatcher,
+    basename_only: bool,
+}
+
+impl PathGlob {
+    fn matches(&self, path: &Path) -> bool {
+        if self.basename_only {
+            path.file_name()
+                .map(|name| self.matcher.is_match(name))
+                .unwrap_or(false)
+        } else {
+            self.matcher.is_match(path)
+        }
+    }
+}
+
+fn glob_matcher(pattern: &str) -> Result<PathGlob, FilterError> {
+    let trimmed = pattern.trim_start_matches('/');
+    let glob = if trimmed.contains('/') {
+        GlobBuilder::new(trimmed).literal_separator(true).build()
+    } else {
+        Glob::new(trimmed)
+    }
+    .map_err(|e| FilterError::Spec(e.to_string()))?;
+
+    Ok(PathGlob {
+        matcher: glob.compile_matcher(),
+        basename_only: !trimmed.contains('/'),
+    })
+}
+
+/// Sort object keys and pretty-print, so key order and formatting don't matter
+fn normalize_json(content: &[u8]) -> Result<Vec<u8>, FilterError> {
+    let value: serde_json::Value = serde_json::from_slice(content)?;
+    let mut out = serde_json::to_vec_pretty(&value)?;
+    out.push(b'\n');
+    Ok(out)
+}
+
+fn strip_trailing_whitespace(content: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(content.len());
+    for line in content.split_inclusive(|b| *b == b'\n') {
+        let (body, newline) = match line.strip_suffix(b"\n") {
+            Some(body) => (body, true),
+            None => (line, false),
+        };
+        let end = body
+            .iter()
+            .rposition(|b| *b != b' ' && *b != b'\t')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        out.extend_from_slice(&body[..end]);
+        if newline {
+            out.push(b'\n');
+        }
+    }
+    out
+}
+
+fn drop_lines(content: &[u8], regex: &Regex) -> Vec<u8> {
+    content
+        .split_inclusive(|b| *b == b'\n')
+        .filter(|line| !regex.is_match(line.strip_suffix(b"\n").unwrap_or(line)))
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// Run an external filter with the content on stdin and `GSD_FILE` set
+fn run_command_filter(
+    command: &str,
+    path: &Path,
+    content: Vec<u8>,
+) -> Result<Vec<u8>, FilterError> {
+    let failed = |message: String| FilterError::Command {
+        command: command.to_string(),
+        message,
+    };
+
+    let mut cmd = shell::command(command);
+    cmd.env("GSD_FILE", path).stderr(Stdio::inherit());
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let output = runtime
+        .block_on(shell::run(cmd, &content, COMMAND_TIMEOUT, usize::MAX))
+        .map_err(|e| match e {
+            ShellError::Io(e) => FilterError::Io(e),
+            ShellError::Timeout(_) => {
+                failed(format!("timed out after {}s", COMMAND_TIMEOUT.as_secs()))
+            }
+        })?;
+
+    if !output.status.success() {
+        return Err(failed(format!("exited with {}", output.status)));
+    }
+
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(glob: &str, kind: FilterKind) -> FilterConfig {
+        FilterConfig {
+            glob: glob.to_string(),
+            kind,
+            pattern: None,
+            command: None,
+        }
+    }
+
+    #[test]
+    fn test_json_filter_ignores_key_order_and_formatting() {
+        let filters = vec![filter("*.json", FilterKind::Json)];
+        let a = apply_filters(
+            &filters,
+            Path::new("state.json"),
+            br#"{"b":1,"a":[1,2]}"#.to_vec(),
+        )
+        .unwrap();
+        let b = apply_filters(
+            &filters,
+            Path::new("state.json"),
+            b"{\n  \"a\": [1, 2],\n    \"b\": 1\n}".to_vec(),
+        )
+        .unwrap();
+        assert_eq!(a, b);
+        assert!(String::from_utf8(a).unwrap().starts_with("{\n  \"a\""));
+    }
+
+    #[test]
+    fn test_filters_only_apply_to_matching_paths() {
+        let filters = vec![
+            filter("*.md", FilterKind::TrailingWhitespace),
+            filter("logs/*.txt", FilterKind::TrailingWhitespace),
+        ];
+        let content = b"one  \ntwo\t\n".to_vec();
+
+        let out = apply_filters(&filters, Path::new("docs/plan.md"), content.clone()).unwrap();
+        assert_eq!(out, b"one\ntwo\n");
+
+        let out = apply_filters(&filters, Path::new("logs/run.txt"), content.clone()).unwrap();
+        assert_eq!(out, b"one\ntwo\n");
+
+        let out =
+            apply_filters(&filters, Path::new("nested/logs/run.txt"), content.clone()).unwrap();
+        assert_eq!(out, content);
+    }
+
+    #[test]
+    fn test_drop_lines_filter() {
+        let mut drop = filter("*", FilterKind::DropLines);
+        drop.pattern = Some("^updated: ".to_string());
+        let content = b"title: plan\nupdated: 2026-01-01T00:00:00Z\nbody\n".to_vec();
+
+        let out = apply_filters(&[drop], Path::new("plan.yaml"), content).unwrap();
+        assert_eq!(out, b"title: plan\nbody\n");
+    }
+
+    #[test]
+    fn test_command_filter() {
+        let mut cmd = filter("*.txt", FilterKind::Command);
+        cmd.command = Some("tr a-z A-Z".to_string());
+
+        let out = apply_filters(&[cmd], Path::new("a.txt"), b"hello\n".to_vec()).unwrap();
+        assert_eq!(out, b"HELLO\n");
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_driver_wins_over_overlapping_content_filters() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        let mut target = TargetConfig::new(dir.to_path_buf(), 60);
+        target.sqlite_snapshots = true;
+        target.filters = vec![
+            filter("*", FilterKind::TrailingWhitespace),
+            filter("*", FilterKind::TrailingWhitespace),
+        ];
+
+        let drivers = target_drivers(&target).unwrap();
+        let names: Vec<&str> = drivers.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec![CONTENT_FILTER, sqlite::SQLITE_FILTER]);
+        assert_eq!(drivers[0].patterns, vec!["*"]);
+
+        // The last matching attribute applies, and it still runs the content filters
+        let sqlite_driver = &drivers[1];
+        assert!(sqlite_driver.patterns.iter().any(|p| p == "*.db"));
+        assert!(sqlite_driver.clean.contains("sqlite-clean --spec "));
+
+        crate::git::ensure_repo_initialized(dir, "Test", "test@test.com", &[], &drivers)
+            .await
+            .unwrap();
+        let attr = |path: &'static str| async move {
+            crate::git::run_snapshot_git(dir, &["check-attr", "filter", "--", path], None)
+                .await
+                .unwrap()
+                .stdout
+        };
+        assert_eq!(attr("app.db").await, "app.db: filter: gsd-sqlite\n");
+        assert_eq!(attr("notes.md").await, "notes.md: filter: gsd-content\n");
+
+        // Databases are backed up, other files still get the content filters
+        let spec = filter_spec(&target).unwrap().unwrap();
+        let db_path = dir.join("app.db");
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        conn.execute_batch("CREATE TABLE t (x TEXT); INSERT INTO t VALUES ('a  ');")
+            .unwrap();
+        drop(conn);
+        let raw = std::fs::read(&db_path).unwrap();
+        let mut output = Vec::new();
+        sqlite::clean(&db_path, Some(&spec), &raw[..], &mut output).unwrap();
+        assert_eq!(output, sqlite::backup_bytes(&db_path).unwrap());
+
+        let mut output = Vec::new();
+        sqlite::clean(
+            Path::new("old.db"),
+            Some(&spec),
+            &b"text  \n"[..],
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(output, b"text\n");
+    }
+
+    #[test]
+    fn test_clean_stores_raw_content_on_failure() {
+        let spec = serde_json::to_string(&[filter("*.json", FilterKind::Json)]).unwrap();
+        let mut output = Vec::new();
+        clean(
+            &spec,
+            Path::new("broken.json"),
+            &b"{not json"[..],
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(output, b"{not json");
+    }
+}
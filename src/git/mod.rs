@@ -1,9 +1,17 @@
+use std::ffi::OsStr;
+use std::future::Future;
+use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
+use std::process::{ExitStatus, Stdio};
+use std::sync::RwLock;
+use std::time::Duration;
 
 use tokio::fs;
-use tokio::io::{AsyncRead, AsyncReadExt};
-use tokio::process::Command;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tracing::warn;
+
+use crate::config::{GitConfig, GitTimeouts};
 
 const DEFAULT_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
 
@@ -13,6 +21,143 @@ pub const GSD_DIR: &str = ".gsd";
 /// Optional ignore file that users can create
 pub const GSD_IGNORE_FILE: &str = ".gsdignore";
 
+/// Limits applied to every git process this process spawns
+static TIMEOUTS: RwLock<GitTimeouts> = RwLock::new(GitTimeouts::DEFAULT);
+
+tokio::task_local! {
+    /// Index snapshot commands use instead of `.gsd/index`, see `with_index_file`
+    static INDEX_FILE: PathBuf;
+}
+
+/// How much of the user's git setup snapshot commands may see
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Isolation {
+    inherit_user_config: bool,
+    run_hooks: bool,
+}
+
+static ISOLATION: RwLock<Isolation> = RwLock::new(Isolation {
+    inherit_user_config: false,
+    run_hooks: false,
+});
+
+/// Settings gsd pins on every snapshot command, whatever config is inherited
+const PINNED_CONFIG: &[&str] = &[
+    "core.fsmonitor=false",
+    "commit.gpgSign=false",
+    "tag.gpgSign=false",
+    "init.defaultBranch=main",
+];
+
+/// Points git at a hooks directory that cannot exist, so no hook runs
+const NO_HOOKS: &str = "core.hooksPath=/dev/null";
+
+/// Variables that point git at other repositories, indexes or config
+const REPO_ENV: &[&str] = &[
+    "GIT_DIR",
+    "GIT_WORK_TREE",
+    "GIT_INDEX_FILE",
+    "GIT_OBJECT_DIRECTORY",
+    "GIT_ALTERNATE_OBJECT_DIRECTORIES",
+    "GIT_COMMON_DIR",
+    "GIT_NAMESPACE",
+    "GIT_CONFIG",
+    "GIT_CONFIG_PARAMETERS",
+    "GIT_CONFIG_COUNT",
+];
+
+/// Identity variables that would override the configured author
+const IDENTITY_ENV: &[&str] = &[
+    "GIT_AUTHOR_NAME",
+    "GIT_AUTHOR_EMAIL",
+    "GIT_AUTHOR_DATE",
+    "GIT_COMMITTER_NAME",
+    "GIT_COMMITTER_EMAIL",
+    "GIT_COMMITTER_DATE",
+];
+
+/// Apply the `[git]` settings that affect every git process, e.g. after
+/// loading or reloading config
+pub fn configure(config: &GitConfig) {
+    *TIMEOUTS.write().unwrap_or_else(|e| e.into_inner()) = config.timeouts;
+    *ISOLATION.write().unwrap_or_else(|e| e.into_inner()) = Isolation {
+        inherit_user_config: config.inherit_user_config,
+        run_hooks: config.run_hooks,
+    };
+}
+
+fn isolation() -> Isolation {
+    *ISOLATION.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `-c` value that disables hooks in `.gsd/hooks`, unless they are enabled
+pub fn hooks_override() -> Option<&'static str> {
+    (!isolation().run_hooks).then_some(NO_HOOKS)
+}
+
+/// Run snapshot commands with gsd's own settings instead of the user's
+fn isolate(cmd: &mut Command, isolation: Isolation) {
+    for var in REPO_ENV {
+        cmd.env_remove(var);
+    }
+    if !isolation.inherit_user_config {
+        cmd.env("GIT_CONFIG_NOSYSTEM", "1");
+        cmd.env("GIT_CONFIG_GLOBAL", "/dev/null");
+        for var in IDENTITY_ENV {
+            cmd.env_remove(var);
+        }
+    }
+    cmd.env("GIT_TERMINAL_PROMPT", "0");
+
+    for setting in PINNED_CONFIG {
+        cmd.arg("-c").arg(setting);
+    }
+    if !isolation.run_hooks {
+        cmd.arg("-c").arg(NO_HOOKS);
+    }
+}
+
+/// Global git options that take their value as the next argument
+const OPTIONS_WITH_VALUE: &[&str] = &[
+    "-c",
+    "-C",
+    "--git-dir",
+    "--work-tree",
+    "--namespace",
+    "--exec-path",
+    "--config-env",
+];
+
+/// Position of the subcommand in a git argument list, after any global options
+pub fn subcommand_index<S: AsRef<str>>(args: &[S]) -> Option<usize> {
+    let mut index = 0;
+    while let Some(arg) = args.get(index) {
+        let arg = arg.as_ref();
+        if OPTIONS_WITH_VALUE.contains(&arg) {
+            index += 2;
+        } else if arg.starts_with('-') {
+            index += 1;
+        } else {
+            return Some(index);
+        }
+    }
+    None
+}
+
+/// Timeout for a git invocation, chosen by its subcommand
+fn timeout_for(args: &[&str]) -> Option<Duration> {
+    let subcommand = subcommand_index(args).map(|i| args[i]);
+
+    let timeouts = *TIMEOUTS.read().unwrap_or_else(|e| e.into_inner());
+    let seconds = match subcommand {
+        Some("status" | "diff" | "ls-files" | "diff-index") => timeouts.status_seconds,
+        Some("add") => timeouts.add_seconds,
+        Some("commit") => timeouts.commit_seconds,
+        _ => timeouts.default_seconds,
+    };
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct GitCommandResult {
@@ -36,6 +181,9 @@ pub enum GitError {
 
     #[error("detached HEAD in {path}")]
     DetachedHead { path: PathBuf },
+
+    #[error("git {command} timed out after {limit:?} and was killed")]
+    Timeout { command: String, limit: Duration },
 }
 
 /// Run a git command (standard, not using our snapshot dir)
@@ -63,46 +211,230 @@ async fn run_git_with_options(
     max_output_bytes: Option<usize>,
     use_snapshot_dir: bool,
 ) -> Result<GitCommandResult, GitError> {
-    let max_bytes = max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES);
-
-    let mut cmd = Command::new("git");
+    run_git_with_limit(
+        cwd,
+        args,
+        max_output_bytes,
+        use_snapshot_dir.then(isolation),
+        timeout_for(args),
+        None,
+    )
+    .await
+}
 
-    if use_snapshot_dir {
-        // Use our custom git directory, separate from any existing .git
-        cmd.arg(format!("--git-dir={}", GSD_DIR));
-        cmd.arg("--work-tree=.");
-    }
+/// Run a snapshot git command with `input` on its stdin
+pub async fn run_snapshot_git_with_input(
+    cwd: &Path,
+    args: &[&str],
+    input: &[u8],
+    max_output_bytes: Option<usize>,
+) -> Result<GitCommandResult, GitError> {
+    run_git_with_limit(
+        cwd,
+        args,
+        max_output_bytes,
+        Some(isolation()),
+        timeout_for(args),
+        Some(input),
+    )
+    .await
+}
 
-    cmd.args(args);
-    cmd.current_dir(cwd);
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
+/// Run git; `isolation` is set for snapshot commands, which use `.gsd`
+async fn run_git_with_limit(
+    cwd: &Path,
+    args: &[&str],
+    max_output_bytes: Option<usize>,
+    isolation: Option<Isolation>,
+    limit: Option<Duration>,
+    input: Option<&[u8]>,
+) -> Result<GitCommandResult, GitError> {
+    let max_bytes = max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES);
 
+    let mut cmd = git_command(cwd, args, isolation);
+    if input.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
     let mut child = cmd.spawn()?;
+    crate::metrics::record_git_spawn();
 
+    let stdin_handle = child.stdin.take();
     let stdout_handle = child.stdout.take().expect("stdout piped");
     let stderr_handle = child.stderr.take().expect("stderr piped");
 
-    let stdout_read = read_with_cap(stdout_handle, max_bytes);
-    let stderr_read = read_with_cap(stderr_handle, max_bytes);
+    let output = async {
+        let stdin_write = async {
+            if let (Some(mut stdin), Some(input)) = (stdin_handle, input) {
+                match stdin.write_all(input).await {
+                    // git stopped reading; its exit status says why
+                    Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+                    result => result?,
+                }
+            }
+            Ok::<_, std::io::Error>(())
+        };
+        let stdout_read = read_with_cap(stdout_handle, max_bytes);
+        let stderr_read = read_with_cap(stderr_handle, max_bytes);
+
+        let (stdin_result, stdout_result, stderr_result) =
+            tokio::join!(stdin_write, stdout_read, stderr_read);
+        stdin_result?;
+        let (stdout_buf, stdout_truncated) = stdout_result?;
+        let (stderr_buf, stderr_truncated) = stderr_result?;
+        Ok((stdout_buf, stderr_buf, stdout_truncated || stderr_truncated))
+    };
+    let ((stdout_buf, stderr_buf, truncated), status) =
+        wait_with_limit(&mut child, cwd, args, limit, output).await?;
+
+    Ok(GitCommandResult {
+        stdout: String::from_utf8_lossy(&stdout_buf).to_string(),
+        stderr: String::from_utf8_lossy(&stderr_buf).to_string(),
+        exit_code: status.code().unwrap_or(-1),
+        truncated,
+    })
+}
 
-    let (stdout_result, stderr_result) = tokio::join!(stdout_read, stderr_read);
-    let (stdout_buf, stdout_truncated) = stdout_result?;
-    let (stderr_buf, stderr_truncated) = stderr_result?;
+/// Run a snapshot git command, passing each NUL-terminated record of its
+/// output to `on_record` as it arrives.
+///
+/// Output is not capped; the returned `stdout` is empty.
+async fn stream_snapshot_git<F>(
+    cwd: &Path,
+    args: &[&str],
+    mut on_record: F,
+) -> Result<GitCommandResult, GitError>
+where
+    F: FnMut(&[u8]),
+{
+    let mut child = git_command(cwd, args, Some(isolation())).spawn()?;
+    crate::metrics::record_git_spawn();
 
-    let truncated = stdout_truncated || stderr_truncated;
+    let stdout_handle = child.stdout.take().expect("stdout piped");
+    let stderr_handle = child.stderr.take().expect("stderr piped");
+
+    let output = async {
+        let stdout_read = async {
+            let mut reader = BufReader::new(stdout_handle);
+            let mut record = Vec::new();
+            loop {
+                record.clear();
+                if reader.read_until(0, &mut record).await? == 0 {
+                    return Ok::<_, std::io::Error>(());
+                }
+                if record.last() == Some(&0) {
+                    record.pop();
+                }
+                on_record(&record);
+            }
+        };
+        let stderr_read = read_with_cap(stderr_handle, DEFAULT_MAX_OUTPUT_BYTES);
 
-    let status = child.wait().await?;
+        let (stdout_result, stderr_result) = tokio::join!(stdout_read, stderr_read);
+        stdout_result?;
+        stderr_result
+    };
+    let ((stderr_buf, truncated), status) =
+        wait_with_limit(&mut child, cwd, args, timeout_for(args), output).await?;
 
     Ok(GitCommandResult {
-        stdout: String::from_utf8_lossy(&stdout_buf).to_string(),
+        stdout: String::new(),
         stderr: String::from_utf8_lossy(&stderr_buf).to_string(),
         exit_code: status.code().unwrap_or(-1),
         truncated,
     })
 }
 
-async fn read_with_cap<R: AsyncRead + Unpin>(
+/// Contents of `path` in snapshot `commit`, byte for byte
+pub async fn read_snapshot_file(dir: &Path, commit: &str, path: &str) -> Result<Vec<u8>, GitError> {
+    let spec = format!("{}:{}", commit, path);
+    let args = ["cat-file", "blob", spec.as_str()];
+    let mut child = git_command(dir, &args, Some(isolation())).spawn()?;
+    crate::metrics::record_git_spawn();
+
+    let stdout_handle = child.stdout.take().expect("stdout piped");
+    let stderr_handle = child.stderr.take().expect("stderr piped");
+
+    let output = async {
+        let (stdout_result, stderr_result) = tokio::join!(
+            read_with_cap(stdout_handle, usize::MAX),
+            read_with_cap(stderr_handle, DEFAULT_MAX_OUTPUT_BYTES)
+        );
+        Ok((stdout_result?.0, stderr_result?.0))
+    };
+    let ((contents, stderr), status) =
+        wait_with_limit(&mut child, dir, &args, timeout_for(&args), output).await?;
+    if !status.success() {
+        return Err(GitError::CommandFailed {
+            message: String::from_utf8_lossy(&stderr).trim().to_string(),
+        });
+    }
+    Ok(contents)
+}
+
+/// Run `future` with its snapshot git commands reading and writing `index`
+/// instead of `.gsd/index`
+pub async fn with_index_file<F: Future>(index: PathBuf, future: F) -> F::Output {
+    INDEX_FILE.scope(index, future).await
+}
+
+fn git_command(cwd: &Path, args: &[&str], isolation: Option<Isolation>) -> Command {
+    let mut cmd = Command::new("git");
+
+    if let Some(isolation) = isolation {
+        isolate(&mut cmd, isolation);
+        if let Ok(index) = INDEX_FILE.try_with(|index| index.clone()) {
+            cmd.env("GIT_INDEX_FILE", index);
+        }
+        // Use our custom git directory, separate from any existing .git
+        cmd.arg(format!("--git-dir={}", GSD_DIR));
+        cmd.arg("--work-tree=.");
+    }
+
+    cmd.args(args);
+    cmd.current_dir(cwd);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    // Own process group, so a timeout also kills helpers git started
+    cmd.process_group(0);
+    cmd
+}
+
+/// Drive `output` to completion and reap `child`, killing its process group
+/// if that takes longer than `limit`
+async fn wait_with_limit<T>(
+    child: &mut Child,
+    cwd: &Path,
+    args: &[&str],
+    limit: Option<Duration>,
+    output: impl Future<Output = Result<T, std::io::Error>>,
+) -> Result<(T, ExitStatus), GitError> {
+    let run = async {
+        let output = output.await?;
+        let status = child.wait().await?;
+        Ok::<_, std::io::Error>((output, status))
+    };
+
+    let result = match limit {
+        Some(limit) => match tokio::time::timeout(limit, run).await {
+            Ok(result) => result,
+            Err(_) => {
+                if let Some(pid) = child.id() {
+                    // SAFETY: kill(2) on our child's process group
+                    unsafe { libc::kill(-(pid as i32), libc::SIGKILL) };
+                }
+                let _ = child.wait().await;
+                crate::metrics::record_git_timeout();
+                let command = args.join(" ");
+                warn!(cwd = %cwd.display(), command = %command, "Killed hung git process");
+                return Err(GitError::Timeout { command, limit });
+            }
+        },
+        None => run.await,
+    };
+    Ok(result?)
+}
+
+pub async fn read_with_cap<R: AsyncRead + Unpin>(
     mut reader: R,
     max_bytes: usize,
 ) -> Result<(Vec<u8>, bool), std::io::Error> {
@@ -166,6 +498,71 @@ pub enum RepoOwnership {
     Ours,
 }
 
+/// A git filter driver installed into the snapshot repo.
+///
+/// The driver is registered in `.gsd/config` and bound to paths through
+/// `.gsd/info/attributes`, so it applies to snapshots without touching the
+/// target's own `.gitattributes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterDriver {
+    /// Driver name (`filter.<name>.*`)
+    pub name: String,
+    /// Command run when content is staged
+    pub clean: String,
+    /// Command run when content is checked out
+    pub smudge: Option<String>,
+    /// Attribute patterns the driver applies to
+    pub patterns: Vec<String>,
+}
+
+/// Quote a value for use in a shell command run by git (filters, hooks)
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+const ATTRIBUTES_HEADER: &str = "# Managed by gsd - regenerated on startup";
+
+/// Registers filter drivers and rewrites .gsd/info/attributes to match
+async fn ensure_filter_drivers(dir: &Path, drivers: &[FilterDriver]) -> Result<(), GitError> {
+    let attributes_path = dir.join(GSD_DIR).join("info").join("attributes");
+
+    if drivers.is_empty() {
+        match fs::remove_file(&attributes_path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(GitError::Io(e)),
+        }
+        return Ok(());
+    }
+
+    let mut lines = vec![ATTRIBUTES_HEADER.to_string()];
+    for driver in drivers {
+        let clean_key = format!("filter.{}.clean", driver.name);
+        run_snapshot_git(dir, &["config", &clean_key, &driver.clean], None).await?;
+
+        let smudge_key = format!("filter.{}.smudge", driver.name);
+        match &driver.smudge {
+            Some(smudge) => {
+                run_snapshot_git(dir, &["config", &smudge_key, smudge], None).await?;
+            }
+            None => {
+                // Exit code 5 means the key was already unset
+                run_snapshot_git(dir, &["config", "--unset", &smudge_key], None).await?;
+            }
+        }
+
+        for pattern in &driver.patterns {
+            lines.push(format!("{} filter={}", pattern, driver.name));
+        }
+    }
+
+    let info_dir = dir.join(GSD_DIR).join("info");
+    fs::create_dir_all(&info_dir).await?;
+    fs::write(&attributes_path, format!("{}\n", lines.join("\n"))).await?;
+
+    Ok(())
+}
+
 /// Reads .gsdignore if it exists and copies patterns to .gsd/info/exclude
 async fn setup_gsd_excludes(dir: &Path) -> Result<(), GitError> {
     let gitignore_path = dir.join(".gitignore");
@@ -297,6 +694,7 @@ pub async fn ensure_repo_initialized(
     author_name: &str,
     author_email: &str,
     ignore_patterns: &[String],
+    filters: &[FilterDriver],
 ) -> Result<(), GitError> {
     let mut all_patterns = vec![format!("{}/", GSD_DIR)];
     all_patterns.extend(ignore_patterns.iter().cloned());
@@ -311,6 +709,7 @@ pub async fn ensure_repo_initialized(
         ensure_local_git_config(dir, author_name, author_email).await?;
         ensure_gitignore(dir, &all_patterns).await?;
         setup_gsd_excludes(dir).await?;
+        ensure_filter_drivers(dir, filters).await?;
         return Ok(());
     }
 
@@ -331,6 +730,9 @@ pub async fn ensure_repo_initialized(
     // Set up .gsdignore -> .gsd/info/exclude
     setup_gsd_excludes(dir).await?;
 
+    // Install filters before the initial commit so it is stored filtered too
+    ensure_filter_drivers(dir, filters).await?;
+
     // Initial commit
     let add_result = run_snapshot_git(dir, &["add", "-A"], None).await?;
     if add_result.exit_code != 0 {
@@ -364,73 +766,178 @@ pub async fn is_detached_head(dir: &Path) -> Result<bool, GitError> {
     Ok(result.stdout.trim() == "HEAD")
 }
 
-pub async fn list_changed_files(dir: &Path) -> Result<Vec<String>, GitError> {
-    let result = run_snapshot_git(dir, &["status", "--porcelain", "-z"], None).await?;
-    if result.exit_code != 0 {
-        return Err(GitError::CommandFailed {
-            message: result.stderr.trim().to_string(),
-        });
+/// How a path differs from the last snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Modified,
+    Deleted,
+    Renamed { from: PathBuf },
+    Copied { from: PathBuf },
+}
+
+/// One path reported by `git status`, relative to the target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusEntry {
+    pub change: Change,
+    pub path: PathBuf,
+}
+
+impl StatusEntry {
+    /// The path for messages and JSON output; invalid UTF-8 is replaced
+    pub fn display_path(&self) -> String {
+        self.path.to_string_lossy().into_owned()
     }
+}
 
-    let entries: Vec<&str> = result
-        .stdout
-        .split('\0')
-        .filter(|s| !s.is_empty())
-        .collect();
-    let mut files = Vec::new();
-    let mut i = 0;
+/// Paths of `entries` for messages, events and hooks
+pub fn display_paths(entries: &[StatusEntry]) -> Vec<String> {
+    entries.iter().map(StatusEntry::display_path).collect()
+}
 
-    while i < entries.len() {
-        let entry = entries[i];
-        if entry.len() < 4 {
-            i += 1;
-            continue;
-        }
+/// Incremental parser for `git status --porcelain -z` records
+#[derive(Debug, Default)]
+struct StatusParser {
+    entries: Vec<StatusEntry>,
+    /// A rename or copy, waiting for the record holding its source path
+    pending: Option<(u8, PathBuf)>,
+}
 
-        let status = &entry[..2];
-        let path_value = &entry[3..];
+impl StatusParser {
+    fn push(&mut self, record: &[u8]) {
+        if let Some((code, path)) = self.pending.take() {
+            let from = path_from_bytes(record);
+            let change = match code {
+                b'R' => Change::Renamed { from },
+                _ => Change::Copied { from },
+            };
+            self.entries.push(StatusEntry { change, path });
+            return;
+        }
 
-        // Handle renames and copies which have an extra path entry
-        if status.starts_with('R') || status.starts_with('C') {
-            if let Some(next) = entries.get(i + 1) {
-                files.push(next.to_string());
-                i += 2;
-                continue;
-            }
+        if record.len() < 4 {
+            return;
         }
+        // Everything is staged first, so the index column is authoritative
+        let code = match (record[0], record[1]) {
+            (b' ', worktree) => worktree,
+            (index, _) => index,
+        };
+        let path = path_from_bytes(&record[3..]);
+        let change = match code {
+            b'R' | b'C' => {
+                self.pending = Some((code, path));
+                return;
+            }
+            b'A' | b'?' => Change::Added,
+            b'D' => Change::Deleted,
+            b'!' => return,
+            _ => Change::Modified,
+        };
+        self.entries.push(StatusEntry { change, path });
+    }
 
-        files.push(path_value.to_string());
-        i += 1;
+    fn finish(mut self) -> Vec<StatusEntry> {
+        self.entries.sort_by(|a, b| a.path.cmp(&b.path));
+        self.entries.dedup_by(|a, b| a.path == b.path);
+        self.entries
     }
+}
 
-    // Deduplicate and sort
-    files.sort();
-    files.dedup();
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
 
-    Ok(files)
+/// List files that differ from the last snapshot.
+///
+/// Call `stage_all` first when clean filters should decide what counts as a
+/// change: git's stat check alone reports a file whose size changed even when
+/// its filtered content is identical to what is stored. Status output is
+/// parsed as it streams, so there is no limit on the number of changes.
+pub async fn list_changed_files(dir: &Path) -> Result<Vec<StatusEntry>, GitError> {
+    let mut parser = StatusParser::default();
+    let result = stream_snapshot_git(dir, &["status", "--porcelain", "-z"], |record| {
+        parser.push(record)
+    })
+    .await?;
+    if result.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            message: result.stderr.trim().to_string(),
+        });
+    }
+
+    Ok(parser.finish())
+}
+
+/// Lines added and removed by the staged changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineStats {
+    pub insertions: u64,
+    pub deletions: u64,
+}
+
+/// Line counts for what is staged, as reported by `git diff --shortstat`
+pub async fn staged_line_stats(dir: &Path) -> Result<LineStats, GitError> {
+    let result = run_snapshot_git(dir, &["diff", "--cached", "--shortstat"], None).await?;
+    if result.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            message: result.stderr.trim().to_string(),
+        });
+    }
+    Ok(parse_shortstat(&result.stdout))
 }
 
-pub async fn has_changes(dir: &Path) -> Result<bool, GitError> {
-    let files = list_changed_files(dir).await?;
-    Ok(!files.is_empty())
+/// Parse e.g. " 3 files changed, 10 insertions(+), 2 deletions(-)"
+fn parse_shortstat(line: &str) -> LineStats {
+    let mut stats = LineStats::default();
+    for part in line.trim().split(", ") {
+        let Some((count, label)) = part.split_once(' ') else {
+            continue;
+        };
+        let Ok(count) = count.parse() else {
+            continue;
+        };
+        if label.starts_with("insertion") {
+            stats.insertions = count;
+        } else if label.starts_with("deletion") {
+            stats.deletions = count;
+        }
+    }
+    stats
 }
 
-pub async fn commit_all(dir: &Path, message: &str) -> Result<(), GitError> {
+/// Stage the whole working tree
+pub async fn stage_all(dir: &Path) -> Result<(), GitError> {
     let add_result = run_snapshot_git(dir, &["add", "-A"], None).await?;
     if add_result.exit_code != 0 {
         return Err(GitError::CommandFailed {
             message: add_result.stderr.trim().to_string(),
         });
     }
+    Ok(())
+}
+
+/// Stage everything and commit, returning the new commit ID
+pub async fn commit_all(dir: &Path, message: &str) -> Result<String, GitError> {
+    stage_all(dir).await?;
 
-    let commit_result = run_snapshot_git(dir, &["commit", "-m", message], None).await?;
+    // On stdin, since a long file list can exceed the argument size limit
+    let commit_result =
+        run_snapshot_git_with_input(dir, &["commit", "-F", "-"], message.as_bytes(), None).await?;
     if commit_result.exit_code != 0 {
         return Err(GitError::CommandFailed {
             message: commit_result.stderr.trim().to_string(),
         });
     }
 
-    Ok(())
+    let rev_result = run_snapshot_git(dir, &["rev-parse", "HEAD"], None).await?;
+    if rev_result.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            message: rev_result.stderr.trim().to_string(),
+        });
+    }
+
+    Ok(rev_result.stdout.trim().to_string())
 }
 
 #[cfg(test)]
@@ -443,6 +950,48 @@ mod tests {
         assert!(is_git_available().await);
     }
 
+    #[test]
+    fn test_timeout_chosen_by_subcommand() {
+        let defaults = GitTimeouts::default();
+        let secs = |args: &[&str]| timeout_for(args).map(|d| d.as_secs());
+        assert_eq!(secs(&["add", "-A"]), Some(defaults.add_seconds));
+        assert_eq!(
+            secs(&["-c", "core.quotepath=off", "status", "--porcelain"]),
+            Some(defaults.status_seconds)
+        );
+        assert_eq!(secs(&["commit", "-m", "x"]), Some(defaults.commit_seconds));
+        assert_eq!(
+            secs(&["--git-dir", ".gsd", "add", "-A"]),
+            Some(defaults.add_seconds)
+        );
+        assert_eq!(secs(&["rev-parse", "HEAD"]), Some(defaults.default_seconds));
+    }
+
+    #[tokio::test]
+    async fn test_hung_git_is_killed() {
+        let temp = TempDir::new().unwrap();
+        let started = std::time::Instant::now();
+
+        // The alias runs in a shell, so this also checks the group is killed
+        let result = run_git_with_limit(
+            temp.path(),
+            &["-c", "alias.hang=!sleep 30", "hang"],
+            None,
+            None,
+            Some(Duration::from_millis(200)),
+            None,
+        )
+        .await;
+
+        let err = result.unwrap_err();
+        assert!(matches!(err, GitError::Timeout { .. }));
+        assert!(
+            err.to_string().ends_with("after 200ms and was killed"),
+            "{err}"
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
     #[tokio::test]
     async fn test_repo_initialization() {
         let temp = TempDir::new().unwrap();
@@ -453,7 +1002,7 @@ mod tests {
         assert_eq!(ownership, RepoOwnership::NoRepo);
 
         // Initialize
-        ensure_repo_initialized(dir, "Test", "test@test.com", &["*.tmp".to_string()])
+        ensure_repo_initialized(dir, "Test", "test@test.com", &["*.tmp".to_string()], &[])
             .await
             .unwrap();
 
@@ -482,7 +1031,7 @@ mod tests {
             .unwrap();
 
         // Initialize
-        ensure_repo_initialized(dir, "Test", "test@test.com", &[])
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
             .await
             .unwrap();
 
@@ -503,7 +1052,7 @@ mod tests {
         assert!(dir.join(".git").exists());
 
         // Now initialize our snapshot repo - should work alongside
-        ensure_repo_initialized(dir, "Snapshot", "snapshot@local", &[])
+        ensure_repo_initialized(dir, "Snapshot", "snapshot@local", &[], &[])
             .await
             .unwrap();
 
@@ -521,27 +1070,160 @@ mod tests {
         let temp = TempDir::new().unwrap();
         let dir = temp.path();
 
-        ensure_repo_initialized(dir, "Test", "test@test.com", &[])
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
             .await
             .unwrap();
 
         // No changes initially
-        assert!(!has_changes(dir).await.unwrap());
+        assert!(list_changed_files(dir).await.unwrap().is_empty());
 
         // Create a file
         fs::write(dir.join("test.txt"), "hello").await.unwrap();
 
         // Should have changes now
-        assert!(has_changes(dir).await.unwrap());
-
         let files = list_changed_files(dir).await.unwrap();
-        assert!(files.contains(&"test.txt".to_string()));
+        assert_eq!(
+            files,
+            vec![StatusEntry {
+                change: Change::Added,
+                path: PathBuf::from("test.txt"),
+            }]
+        );
 
         // Commit
-        commit_all(dir, "Test commit").await.unwrap();
+        let commit_id = commit_all(dir, "Test commit").await.unwrap();
+        assert_eq!(commit_id.len(), 40);
 
         // No changes after commit
-        assert!(!has_changes(dir).await.unwrap());
+        assert!(list_changed_files(dir).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_commit_with_large_message() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        fs::write(dir.join("test.txt"), "hello").await.unwrap();
+
+        // A message far beyond the argument size limit still commits
+        let message = format!("Big\n\n{}", "M  some/long/path.txt\n".repeat(20_000));
+        commit_all(dir, &message).await.unwrap();
+        let body = run_snapshot_git(dir, &["log", "-1", "--format=%B"], Some(usize::MAX))
+            .await
+            .unwrap();
+        assert_eq!(body.stdout.trim_end(), message.trim_end());
+    }
+
+    #[test]
+    fn test_status_parser_records() {
+        let mut parser = StatusParser::default();
+        for record in [
+            &b"M  notes.md"[..],
+            b"R  new.md",
+            b"old.md",
+            b"D  gone.md",
+            b"!! ignored.log",
+            b"A  caf\xe9.txt",
+        ] {
+            parser.push(record);
+        }
+
+        let entries = parser.finish();
+        let change = |path: &[u8]| {
+            entries
+                .iter()
+                .find(|e| e.path.as_os_str().as_bytes() == path)
+                .map(|e| e.change.clone())
+        };
+        assert_eq!(entries.len(), 4);
+        assert_eq!(change(b"notes.md"), Some(Change::Modified));
+        assert_eq!(
+            change(b"new.md"),
+            Some(Change::Renamed {
+                from: PathBuf::from("old.md")
+            })
+        );
+        assert_eq!(change(b"gone.md"), Some(Change::Deleted));
+        assert_eq!(change(b"caf\xe9.txt"), Some(Change::Added));
+    }
+
+    #[test]
+    fn test_parse_shortstat() {
+        assert_eq!(
+            parse_shortstat(" 3 files changed, 10 insertions(+), 1 deletion(-)\n"),
+            LineStats {
+                insertions: 10,
+                deletions: 1
+            }
+        );
+        assert_eq!(
+            parse_shortstat(" 1 file changed, 2 deletions(-)"),
+            LineStats {
+                insertions: 0,
+                deletions: 2
+            }
+        );
+        assert_eq!(parse_shortstat(""), LineStats::default());
+    }
+
+    #[tokio::test]
+    async fn test_status_streams_large_change_sets() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+
+        // Enough long names that status output exceeds the default cap
+        let prefix = "x".repeat(200);
+        let count = DEFAULT_MAX_OUTPUT_BYTES / 200 + 100;
+        for i in 0..count {
+            std::fs::write(dir.join(format!("{}{:05}", prefix, i)), "").unwrap();
+        }
+        let raw = OsStr::from_bytes(b"raw\xff.bin");
+        std::fs::write(dir.join(raw), "data").unwrap();
+
+        let files = list_changed_files(dir).await.unwrap();
+        assert_eq!(files.len(), count + 1);
+        assert!(files.iter().any(|e| e.path.as_os_str() == raw));
+        assert!(files.iter().all(|e| e.change == Change::Added));
+    }
+
+    #[tokio::test]
+    async fn test_repo_hooks_only_run_when_enabled() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+
+        let hook = dir.join(GSD_DIR).join("hooks").join("pre-commit");
+        fs::create_dir_all(hook.parent().unwrap()).await.unwrap();
+        fs::write(&hook, "#!/bin/sh\nexit 1\n").await.unwrap();
+        fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))
+            .await
+            .unwrap();
+
+        let commit = |run_hooks| {
+            run_git_with_limit(
+                dir,
+                &["commit", "--allow-empty", "-m", "x"],
+                None,
+                Some(Isolation {
+                    inherit_user_config: false,
+                    run_hooks,
+                }),
+                None,
+                None,
+            )
+        };
+        assert_ne!(commit(true).await.unwrap().exit_code, 0);
+        assert_eq!(commit(false).await.unwrap().exit_code, 0);
     }
 
     #[tokio::test]
@@ -549,7 +1231,7 @@ mod tests {
         let temp = TempDir::new().unwrap();
         let dir = temp.path();
 
-        ensure_repo_initialized(dir, "Test", "test@test.com", &[])
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
             .await
             .unwrap();
 
@@ -0,0 +1,634 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::checkpoint::{self, CheckpointError};
+use crate::git::{read_snapshot_file, run_snapshot_git, run_snapshot_git_with_input, GitError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("no snapshot, checkpoint or time matches '{0}'")]
+    NotFound(String),
+
+    #[error("no snapshot at or before {0}")]
+    NothingBefore(String),
+
+    #[error("'{0}' is not in any snapshot")]
+    UnknownFile(String),
+
+    #[error("'{path}' did not exist at {reference}")]
+    Missing { path: String, reference: String },
+
+    #[error("no deleted files at or under '{0}'")]
+    NothingDeleted(String),
+
+    #[error(transparent)]
+    Checkpoint(#[from] CheckpointError),
+
+    #[error(transparent)]
+    Git(#[from] GitError),
+}
+
+/// Parse a point in time: a duration ago (`90s`, `15m`, `2h`, `7d`, `2w`),
+/// an RFC 3339 timestamp, a local date (`2026-01-21`), a local date and
+/// time (`2026-01-21 09:30`) or a local time today (`09:30`)
+pub fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Some(unit) = value.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        if let Ok(amount) = value[..value.len() - 1].parse::<i64>() {
+            let seconds = match unit {
+                's' => 1,
+                'm' => 60,
+                'h' => 60 * 60,
+                'd' => 24 * 60 * 60,
+                'w' => 7 * 24 * 60 * 60,
+                _ => return Err(format!("unknown duration unit '{}'", unit)),
+            };
+            return amount
+                .checked_mul(seconds)
+                .and_then(chrono::Duration::try_seconds)
+                .and_then(|ago| Utc::now().checked_sub_signed(ago))
+                .ok_or_else(|| format!("duration out of range: {}", value));
+        }
+    }
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let local = if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        date.and_hms_opt(0, 0, 0)
+    } else if let Ok(time) = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M") {
+        Some(time)
+    } else if let Ok(time) = NaiveTime::parse_from_str(value, "%H:%M") {
+        Some(Local::now().date_naive().and_time(time))
+    } else {
+        None
+    };
+    if let Some(time) = local.and_then(|t| Local.from_local_datetime(&t).earliest()) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    Err(format!(
+        "expected a duration like 2h or 7d, an RFC 3339 time, YYYY-MM-DD [HH:MM] or HH:MM: {}",
+        value
+    ))
+}
+
+/// Resolve a snapshot reference to a commit ID.
+///
+/// Checkpoint names and git revisions are tried first; anything else that
+/// parses as a time selects the last snapshot at or before it.
+pub async fn resolve(dir: &Path, reference: &str) -> Result<String, HistoryError> {
+    match checkpoint::resolve(dir, reference).await {
+        Ok(commit) => return Ok(commit),
+        Err(CheckpointError::NotFound(_)) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let time = parse_time(reference).map_err(|_| HistoryError::NotFound(reference.to_string()))?;
+    snapshot_at(dir, time)
+        .await?
+        .ok_or_else(|| HistoryError::NothingBefore(reference.to_string()))
+}
+
+/// The last snapshot committed at or before `time`
+pub async fn snapshot_at(dir: &Path, time: DateTime<Utc>) -> Result<Option<String>, GitError> {
+    let before = format!("--before=@{}", time.timestamp());
+    let result = run_snapshot_git(dir, &["rev-list", "-1", &before, "HEAD"], None).await?;
+    if result.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            message: result.stderr.trim().to_string(),
+        });
+    }
+    let commit = result.stdout.trim();
+    Ok((!commit.is_empty()).then(|| commit.to_string()))
+}
+
+/// One snapshot in which a file changed
+#[derive(Debug, Clone, Serialize)]
+pub struct FileVersion {
+    pub commit: String,
+    /// RFC 3339 time of the snapshot
+    pub time: String,
+    pub message: String,
+    /// The file's path in this snapshot
+    pub path: String,
+    /// Previous path, if the file was renamed in this snapshot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// added, modified, renamed, copied, deleted or type_changed
+    pub change: &'static str,
+    /// Size in bytes; `None` once deleted
+    pub size: Option<u64>,
+    /// Line counts; `None` for binary files
+    pub insertions: Option<u64>,
+    pub deletions: Option<u64>,
+    #[serde(skip)]
+    blob: String,
+}
+
+/// Every snapshot that changed `path`, newest first, following renames.
+/// `since` drops snapshots older than it.
+pub async fn file_history(
+    dir: &Path,
+    path: &str,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<FileVersion>, GitError> {
+    let since = since.map(|t| format!("--since=@{}", t.timestamp()));
+    let mut args = vec![
+        "log",
+        "--follow",
+        "-M",
+        "--no-abbrev",
+        "--raw",
+        "--numstat",
+        "-z",
+        "--format=%x01%H%x00%cI%x00%s",
+    ];
+    args.extend(since.as_deref());
+    args.extend(["--", path]);
+
+    let result = run_snapshot_git(dir, &args, Some(usize::MAX)).await?;
+    if result.exit_code != 0 {
+        // A repository without snapshots has no history for anything
+        if result.stderr.contains("does not have any commits") {
+            return Ok(Vec::new());
+        }
+        return Err(GitError::CommandFailed {
+            message: result.stderr.trim().to_string(),
+        });
+    }
+
+    let mut versions = parse_file_log(&result.stdout);
+    let blobs: String = versions
+        .iter()
+        .filter(|v| v.change != "deleted")
+        .map(|v| format!("{}\n", v.blob))
+        .collect();
+    if blobs.is_empty() {
+        return Ok(versions);
+    }
+
+    // Every size from one process; missing objects print "<id> missing"
+    let sizes = run_snapshot_git_with_input(
+        dir,
+        &["cat-file", "--batch-check=%(objectname) %(objectsize)"],
+        blobs.as_bytes(),
+        Some(usize::MAX),
+    )
+    .await?;
+    let sizes: HashMap<&str, u64> = sizes
+        .stdout
+        .lines()
+        .filter_map(|line| {
+            let (blob, size) = line.split_once(' ')?;
+            Some((blob, size.parse().ok()?))
+        })
+        .collect();
+    for version in &mut versions {
+        if version.change != "deleted" {
+            version.size = sizes.get(version.blob.as_str()).copied();
+        }
+    }
+    Ok(versions)
+}
+
+/// Parse `log --raw --numstat -z` output for a single file. Each record is
+/// `\x01` commit, time and subject, then a raw entry (`:modes blobs status`
+/// and one or two paths) and a numstat entry.
+fn parse_file_log(output: &str) -> Vec<FileVersion> {
+    let mut versions = Vec::new();
+    for record in output.split('\x01').filter(|r| !r.is_empty()) {
+        let mut fields = record.split('\0');
+        let (Some(commit), Some(time), Some(message)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let mut version: Option<FileVersion> = None;
+        let mut counts = (None, None);
+
+        while let Some(field) = fields.next() {
+            let field = field.trim_start_matches('\n');
+            if let Some(raw) = field.strip_prefix(':') {
+                let parts: Vec<&str> = raw.split(' ').collect();
+                let status = parts.last().copied().unwrap_or_default();
+                let blob = parts.get(3).copied().unwrap_or_default();
+                let (change, paths) = match status.chars().next() {
+                    Some('A') => ("added", 1),
+                    Some('D') => ("deleted", 1),
+                    Some('R') => ("renamed", 2),
+                    Some('C') => ("copied", 2),
+                    Some('T') => ("type_changed", 1),
+                    _ => ("modified", 1),
+                };
+                let from = if paths == 2 {
+                    fields.next().map(str::to_string)
+                } else {
+                    None
+                };
+                let path = fields.next().unwrap_or_default().to_string();
+                // Only the first entry describes the followed file
+                if version.is_none() {
+                    version = Some(FileVersion {
+                        commit: commit.to_string(),
+                        time: time.to_string(),
+                        message: message.to_string(),
+                        path,
+                        from,
+                        change,
+                        size: None,
+                        insertions: None,
+                        deletions: None,
+                        blob: blob.to_string(),
+                    });
+                }
+            } else if !field.is_empty() {
+                let mut parts = field.splitn(3, '\t');
+                let (insertions, deletions) = (parts.next(), parts.next());
+                if parts.next() == Some("") {
+                    // Renames put the old and new paths in their own fields
+                    fields.next();
+                    fields.next();
+                }
+                if counts == (None, None) {
+                    counts = (
+                        insertions.and_then(|n| n.parse().ok()),
+                        deletions.and_then(|n| n.parse().ok()),
+                    );
+                }
+            }
+        }
+
+        if let Some(mut version) = version {
+            (version.insertions, version.deletions) = counts;
+            versions.push(version);
+        }
+    }
+    versions
+}
+
+/// The version of `path` current at `commit`, following renames, and its
+/// contents
+pub async fn file_at(
+    dir: &Path,
+    path: &str,
+    commit: &str,
+    reference: &str,
+) -> Result<(FileVersion, Vec<u8>), HistoryError> {
+    let versions = file_history(dir, path, None).await?;
+    if versions.is_empty() {
+        return Err(HistoryError::UnknownFile(path.to_string()));
+    }
+
+    let ancestors = run_snapshot_git(dir, &["rev-list", commit], Some(usize::MAX)).await?;
+    let ancestors: HashSet<&str> = ancestors.stdout.lines().collect();
+    let missing = || HistoryError::Missing {
+        path: path.to_string(),
+        reference: reference.to_string(),
+    };
+    let version = versions
+        .into_iter()
+        .find(|v| ancestors.contains(v.commit.as_str()))
+        .filter(|v| v.change != "deleted")
+        .ok_or_else(missing)?;
+
+    let contents = read_snapshot_file(dir, commit, &version.path)
+        .await
+        .map_err(|_| missing())?;
+    Ok((version, contents))
+}
+
+/// A file that was in a snapshot but is no longer in the working tree
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletedFile {
+    pub path: String,
+    /// Snapshot that recorded the deletion, or `None` if the file was
+    /// deleted after the latest snapshot
+    pub commit: Option<String>,
+    /// Snapshot holding the last version, which `undelete` restores
+    pub source: String,
+    /// RFC 3339 time of the recording snapshot, or of the latest snapshot
+    /// for a deletion not recorded yet
+    pub time: String,
+}
+
+/// Files deleted in snapshot history or since the latest snapshot, and
+/// still missing, most recently deleted first. Renamed files are not
+/// deleted. `since` only looks at snapshots newer than it; deletions since
+/// the latest snapshot are always listed.
+pub async fn deleted_files(
+    dir: &Path,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<DeletedFile>, GitError> {
+    let since = since.map(|t| format!("--since=@{}", t.timestamp()));
+    let mut args = vec![
+        "log",
+        "-M",
+        "--diff-filter=D",
+        "--name-only",
+        "-z",
+        "--format=%x01%H%x00%P%x00%cI",
+    ];
+    args.extend(since.as_deref());
+
+    let result = run_snapshot_git(dir, &args, Some(usize::MAX)).await?;
+    if result.exit_code != 0 {
+        if result.stderr.contains("does not have any commits") {
+            return Ok(Vec::new());
+        }
+        return Err(GitError::CommandFailed {
+            message: result.stderr.trim().to_string(),
+        });
+    }
+
+    let mut seen = HashSet::new();
+    let mut deleted = missing_since_head(dir).await?;
+    seen.extend(deleted.iter().map(|f| f.path.clone()));
+    for record in result.stdout.split('\x01').filter(|r| !r.is_empty()) {
+        let mut fields = record.split('\0');
+        let (Some(commit), Some(parents), Some(time)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let parent = parents.split(' ').next().unwrap_or_default();
+        for path in fields.map(|f| f.trim_start_matches('\n')) {
+            // Only the latest deletion counts; skip files that are back
+            if path.is_empty()
+                || !seen.insert(path.to_string())
+                || dir.join(path).symlink_metadata().is_ok()
+            {
+                continue;
+            }
+            deleted.push(DeletedFile {
+                path: path.to_string(),
+                commit: Some(commit.to_string()),
+                source: parent.to_string(),
+                time: time.to_string(),
+            });
+        }
+    }
+    Ok(deleted)
+}
+
+/// Files in the latest snapshot that are missing from the working tree
+async fn missing_since_head(dir: &Path) -> Result<Vec<DeletedFile>, GitError> {
+    let head = run_snapshot_git(dir, &["log", "-1", "--format=%H%x00%cI"], None).await?;
+    if head.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            message: head.stderr.trim().to_string(),
+        });
+    }
+    let Some((commit, time)) = head.stdout.trim_end().split_once('\0') else {
+        return Ok(Vec::new());
+    };
+
+    let tree = run_snapshot_git(
+        dir,
+        &["ls-tree", "-r", "-z", "--name-only", "--full-tree", commit],
+        Some(usize::MAX),
+    )
+    .await?;
+    if tree.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            message: tree.stderr.trim().to_string(),
+        });
+    }
+
+    Ok(tree
+        .stdout
+        .split('\0')
+        .filter(|path| !path.is_empty() && dir.join(path).symlink_metadata().is_err())
+        .map(|path| DeletedFile {
+            path: path.to_string(),
+            commit: None,
+            source: commit.to_string(),
+            time: time.to_string(),
+        })
+        .collect())
+}
+
+/// Restore the last version of every deleted file at or under `path` (the
+/// whole target if empty) from the snapshot that last had it, returning the
+/// restored files
+pub async fn undelete(dir: &Path, path: &str) -> Result<Vec<DeletedFile>, HistoryError> {
+    let path = path.trim_end_matches('/');
+    let prefix = format!("{}/", path);
+    let files: Vec<DeletedFile> = deleted_files(dir, None)
+        .await?
+        .into_iter()
+        .filter(|f| path.is_empty() || f.path == path || f.path.starts_with(&prefix))
+        .collect();
+    if files.is_empty() {
+        let shown = if path.is_empty() { "." } else { path };
+        return Err(HistoryError::NothingDeleted(shown.to_string()));
+    }
+
+    let mut sources: Vec<&str> = files.iter().map(|f| f.source.as_str()).collect();
+    sources.sort();
+    sources.dedup();
+    for source in sources {
+        let source_arg = format!("--source={}", source);
+        let mut args = vec![
+            "--literal-pathspecs",
+            "restore",
+            &source_arg,
+            "--worktree",
+            "--",
+        ];
+        args.extend(
+            files
+                .iter()
+                .filter(|f| f.source == source)
+                .map(|f| f.path.as_str()),
+        );
+        let result = run_snapshot_git(dir, &args, None).await?;
+        if result.exit_code != 0 {
+            return Err(GitError::CommandFailed {
+                message: result.stderr.trim().to_string(),
+            }
+            .into());
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{commit_all, ensure_repo_initialized};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_time_forms() {
+        let now = Utc::now();
+        let two_hours = parse_time("2h").unwrap();
+        assert!(
+            (now - two_hours - chrono::Duration::hours(2))
+                .num_seconds()
+                .abs()
+                < 5
+        );
+
+        assert_eq!(
+            parse_time("2026-01-21T10:00:00Z").unwrap(),
+            "2026-01-21T10:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        let morning = parse_time("2026-01-21 09:30")
+            .unwrap()
+            .with_timezone(&Local);
+        assert_eq!(
+            morning.format("%Y-%m-%d %H:%M").to_string(),
+            "2026-01-21 09:30"
+        );
+        let today = parse_time("00:00").unwrap().with_timezone(&Local);
+        assert_eq!(today.date_naive(), Local::now().date_naive());
+
+        assert!(parse_time("soon").is_err());
+        assert!(parse_time("9999999999999w").is_err());
+        assert!(parse_time("99999999999999s").is_err());
+        assert!(parse_time("-99999999999999s").is_err());
+        assert!(parse_time("3y").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_times_and_names() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        std::fs::write(dir.join("plan.md"), "v1").unwrap();
+        let commit = commit_all(dir, "v1").await.unwrap();
+        checkpoint::create(dir, "v1", None, false).await.unwrap();
+
+        assert_eq!(resolve(dir, "v1").await.unwrap(), commit);
+        assert_eq!(resolve(dir, "HEAD").await.unwrap(), commit);
+        assert_eq!(resolve(dir, "0s").await.unwrap(), commit);
+        assert!(matches!(
+            resolve(dir, "2000-01-01").await,
+            Err(HistoryError::NothingBefore(_))
+        ));
+        assert!(matches!(
+            resolve(dir, "no-such-thing").await,
+            Err(HistoryError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_file_history_follows_renames() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        std::fs::write(dir.join("draft.md"), "one\ntwo\nthree\n").unwrap();
+        let first = commit_all(dir, "v1").await.unwrap();
+        std::fs::write(dir.join("draft.md"), "one\n2\nthree\nfour\n").unwrap();
+        let second = commit_all(dir, "v2").await.unwrap();
+        std::fs::rename(dir.join("draft.md"), dir.join("plan.md")).unwrap();
+        let third = commit_all(dir, "v3").await.unwrap();
+        std::fs::remove_file(dir.join("plan.md")).unwrap();
+        let fourth = commit_all(dir, "v4").await.unwrap();
+
+        let versions = file_history(dir, "plan.md", None).await.unwrap();
+        let commits: Vec<&str> = versions.iter().map(|v| v.commit.as_str()).collect();
+        assert_eq!(commits, vec![&fourth, &third, &second, &first]);
+        let changes: Vec<(&str, &str, Option<u64>)> = versions
+            .iter()
+            .map(|v| (v.change, v.path.as_str(), v.size))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("deleted", "plan.md", None),
+                ("renamed", "plan.md", Some(17)),
+                ("modified", "draft.md", Some(17)),
+                ("added", "draft.md", Some(14)),
+            ]
+        );
+        assert_eq!(
+            (versions[2].insertions, versions[2].deletions),
+            (Some(2), Some(1))
+        );
+        assert_eq!(versions[1].from.as_deref(), Some("draft.md"));
+
+        let (version, contents) = file_at(dir, "plan.md", &first, "v1").await.unwrap();
+        assert_eq!(version.path, "draft.md");
+        assert_eq!(contents, b"one\ntwo\nthree\n");
+        assert!(matches!(
+            file_at(dir, "plan.md", &fourth, "now").await,
+            Err(HistoryError::Missing { .. })
+        ));
+        assert!(matches!(
+            file_at(dir, "other.md", &fourth, "now").await,
+            Err(HistoryError::UnknownFile(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_deleted_and_undelete() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        std::fs::create_dir(dir.join("skills")).unwrap();
+        std::fs::write(dir.join("skills/a.md"), "a1").unwrap();
+        std::fs::write(dir.join("skills/b.md"), "b1").unwrap();
+        std::fs::write(dir.join("old.md"), "old").unwrap();
+        std::fs::write(dir.join("keep.md"), "keep").unwrap();
+        commit_all(dir, "v1").await.unwrap();
+
+        std::fs::write(dir.join("skills/a.md"), "a2").unwrap();
+        std::fs::remove_file(dir.join("skills/b.md")).unwrap();
+        std::fs::rename(dir.join("old.md"), dir.join("new.md")).unwrap();
+        let first_delete = commit_all(dir, "v2").await.unwrap();
+        std::fs::remove_dir_all(dir.join("skills")).unwrap();
+        std::fs::remove_file(dir.join("keep.md")).unwrap();
+        let second_delete = commit_all(dir, "v3").await.unwrap();
+        std::fs::write(dir.join("keep.md"), "back").unwrap();
+        // Deleted after the latest snapshot
+        std::fs::remove_file(dir.join("new.md")).unwrap();
+
+        let deleted: Vec<(String, Option<String>)> = deleted_files(dir, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.path, f.commit))
+            .collect();
+        assert_eq!(
+            deleted,
+            vec![
+                ("new.md".to_string(), None),
+                ("skills/a.md".to_string(), Some(second_delete.clone())),
+                ("skills/b.md".to_string(), Some(first_delete)),
+            ]
+        );
+
+        let restored = undelete(dir, "skills/").await.unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("skills/a.md")).unwrap(),
+            "a2"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("skills/b.md")).unwrap(),
+            "b1"
+        );
+        assert!(matches!(
+            undelete(dir, "skills").await,
+            Err(HistoryError::NothingDeleted(_))
+        ));
+
+        // An empty path restores everything missing in the target
+        let restored = undelete(dir, "").await.unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(std::fs::read_to_string(dir.join("new.md")).unwrap(), "old");
+        assert!(deleted_files(dir, None).await.unwrap().is_empty());
+    }
+}
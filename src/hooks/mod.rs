@@ -0,0 +1,273 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::config::{HooksConfig, TargetConfig};
+use crate::shell::{self, ShellError};
+use crate::snapshot::SnapshotTrigger;
+
+/// Changed-file lists longer than this are only passed on stdin, since
+/// a single environment variable is limited to 128 KiB on Linux.
+const MAX_FILES_ENV_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStage {
+    PreSnapshot,
+    PostSnapshot,
+}
+
+impl HookStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookStage::PreSnapshot => "pre_snapshot",
+            HookStage::PostSnapshot => "post_snapshot",
+        }
+    }
+
+    fn command<'a>(&self, hooks: &'a HooksConfig) -> Option<&'a str> {
+        match self {
+            HookStage::PreSnapshot => hooks.pre_snapshot.as_deref(),
+            HookStage::PostSnapshot => hooks.post_snapshot.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HookError {
+    #[error("{stage} hook `{command}` exited with {code}")]
+    Failed {
+        stage: &'static str,
+        command: String,
+        code: i32,
+    },
+
+    #[error("{stage} hook `{command}` timed out after {seconds}s")]
+    Timeout {
+        stage: &'static str,
+        command: String,
+        seconds: u64,
+    },
+
+    #[error("{stage} hook `{command}` could not run: {source}")]
+    Io {
+        stage: &'static str,
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// What a hook is told about the snapshot, as JSON on stdin
+#[derive(Debug, Serialize)]
+pub struct HookPayload<'a> {
+    pub stage: HookStage,
+    pub target_name: &'a str,
+    pub target_path: &'a str,
+    pub trigger: SnapshotTrigger,
+    pub commit: Option<&'a str>,
+    pub files: &'a [String],
+}
+
+/// Run the global hook for `stage`, then the target's own hook.
+///
+/// Stops at the first failure, so a failing global pre-hook vetoes the
+/// snapshot without running the target's hook.
+pub async fn run_stage(
+    stage: HookStage,
+    global: &HooksConfig,
+    target: &TargetConfig,
+    trigger: SnapshotTrigger,
+    commit: Option<&str>,
+    files: &[String],
+) -> Result<(), HookError> {
+    let target_path = target.path.to_string_lossy();
+    let payload = HookPayload {
+        stage,
+        target_name: target.name(),
+        target_path: &target_path,
+        trigger,
+        commit,
+        files,
+    };
+
+    for hooks in [global, &target.hooks] {
+        if let Some(command) = stage.command(hooks) {
+            let timeout = Duration::from_secs(hooks.timeout_seconds);
+            run_hook(command, timeout, &payload, target).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_hook(
+    command: &str,
+    timeout: Duration,
+    payload: &HookPayload<'_>,
+    target: &TargetConfig,
+) -> Result<(), HookError> {
+    let stage = payload.stage.as_str();
+    let io_error = |source| HookError::Io {
+        stage,
+        command: command.to_string(),
+        source,
+    };
+
+    let mut cmd = shell::command(command);
+    cmd.current_dir(&target.path)
+        .env("GSD_HOOK", stage)
+        .env("GSD_TARGET_NAME", payload.target_name)
+        .env("GSD_TARGET_PATH", payload.target_path)
+        .env("GSD_TRIGGER", payload.trigger.as_str())
+        .env("GSD_CHANGED_COUNT", payload.files.len().to_string());
+
+    if let Some(commit) = payload.commit {
+        cmd.env("GSD_COMMIT", commit);
+    }
+
+    let files = payload.files.join("\n");
+    if files.len() <= MAX_FILES_ENV_BYTES {
+        cmd.env("GSD_CHANGED_FILES", files);
+    }
+
+    let mut input = serde_json::to_vec(payload).expect("hook payload serializes");
+    input.push(b'\n');
+
+    let output = match shell::run(cmd, &input, timeout, shell::MAX_LOGGED_OUTPUT_BYTES).await {
+        Ok(output) => output,
+        Err(ShellError::Io(source)) => return Err(io_error(source)),
+        Err(ShellError::Timeout(_)) => {
+            return Err(HookError::Timeout {
+                stage,
+                command: command.to_string(),
+                seconds: timeout.as_secs(),
+            })
+        }
+    };
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        warn!(
+            target = %target.name(),
+            hook = stage,
+            stderr = %stderr.trim(),
+            "Hook failed"
+        );
+        return Err(HookError::Failed {
+            stage,
+            command: command.to_string(),
+            code: output.status.code().unwrap_or(-1),
+        });
+    }
+
+    debug!(
+        target = %target.name(),
+        hook = stage,
+        stdout = %String::from_utf8_lossy(&output.stdout).trim(),
+        stderr = %stderr.trim(),
+        "Hook completed"
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn target_with_hooks(dir: &std::path::Path, hooks: HooksConfig) -> TargetConfig {
+        let mut target = TargetConfig::new(dir.to_path_buf(), 60);
+        target.hooks = hooks;
+        target
+    }
+
+    #[tokio::test]
+    async fn test_hook_receives_env_and_stdin() {
+        let temp = TempDir::new().unwrap();
+        let hooks = HooksConfig {
+            post_snapshot: Some(
+                "cat > payload.json; echo \"$GSD_COMMIT $GSD_TRIGGER $GSD_CHANGED_FILES\" > env.txt"
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        let target = target_with_hooks(temp.path(), hooks);
+        let files = vec!["a.md".to_string()];
+
+        run_stage(
+            HookStage::PostSnapshot,
+            &HooksConfig::default(),
+            &target,
+            SnapshotTrigger::Interval,
+            Some("abc123"),
+            &files,
+        )
+        .await
+        .unwrap();
+
+        let env = std::fs::read_to_string(temp.path().join("env.txt")).unwrap();
+        assert_eq!(env.trim(), "abc123 interval a.md");
+
+        let payload = std::fs::read_to_string(temp.path().join("payload.json")).unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(payload["stage"], "post_snapshot");
+        assert_eq!(payload["files"][0], "a.md");
+    }
+
+    #[tokio::test]
+    async fn test_failing_global_hook_stops_stage() {
+        let temp = TempDir::new().unwrap();
+        let global = HooksConfig {
+            pre_snapshot: Some("exit 3".to_string()),
+            ..Default::default()
+        };
+        let target = target_with_hooks(
+            temp.path(),
+            HooksConfig {
+                pre_snapshot: Some("touch ran".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let result = run_stage(
+            HookStage::PreSnapshot,
+            &global,
+            &target,
+            SnapshotTrigger::Interval,
+            None,
+            &[],
+        )
+        .await;
+
+        assert!(matches!(result, Err(HookError::Failed { code: 3, .. })));
+        assert!(!temp.path().join("ran").exists());
+    }
+
+    #[tokio::test]
+    async fn test_hook_timeout() {
+        let temp = TempDir::new().unwrap();
+        let target = target_with_hooks(
+            temp.path(),
+            HooksConfig {
+                pre_snapshot: Some("sleep 5".to_string()),
+                timeout_seconds: 1,
+                ..Default::default()
+            },
+        );
+
+        let result = run_stage(
+            HookStage::PreSnapshot,
+            &HooksConfig::default(),
+            &target,
+            SnapshotTrigger::Startup,
+            None,
+            &[],
+        )
+        .await;
+
+        assert!(matches!(result, Err(HookError::Timeout { seconds: 1, .. })));
+    }
+}
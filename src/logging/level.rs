@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tracing::Level;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use super::{LoggingError, TARGET_SPAN, TARGET_SPAN_MODULE};
+
+static LEVEL_CONTROL: OnceLock<LevelControl> = OnceLock::new();
+
+/// Key for a boost that applies to the whole daemon
+const ALL_TARGETS: &str = "*";
+
+/// Longest temporary level boost, in minutes (7 days)
+pub const MAX_BOOST_MINUTES: u64 = 7 * 24 * 60;
+
+/// Runtime control over the installed `EnvFilter`.
+///
+/// The filter is rebuilt from a base level plus temporary per-target boosts,
+/// which match events inside that target's span. Boosts only ever make
+/// logging more verbose than the base.
+pub struct LevelControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    state: Mutex<LevelState>,
+}
+
+struct LevelState {
+    base: String,
+    /// RUST_LOG took precedence at startup; config level changes are ignored
+    from_env: bool,
+    /// Keyed by target path, or `ALL_TARGETS`
+    boosts: HashMap<String, Boost>,
+}
+
+#[derive(Clone, Copy)]
+struct Boost {
+    level: Level,
+    until: Instant,
+}
+
+impl LevelControl {
+    pub(super) fn new(
+        handle: reload::Handle<EnvFilter, Registry>,
+        base: String,
+        from_env: bool,
+    ) -> Self {
+        Self {
+            handle,
+            state: Mutex::new(LevelState {
+                base,
+                from_env,
+                boosts: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Make this the control used by `set_base_level` and `boost`
+    pub(super) fn install(self) {
+        let _ = LEVEL_CONTROL.set(self);
+    }
+
+    fn directives(state: &LevelState, now: Instant) -> String {
+        let base_level = max_level(&state.base);
+        let active = state
+            .boosts
+            .iter()
+            .filter(|(_, b)| b.until > now && LevelFilter::from_level(b.level) > base_level);
+
+        let mut base = state.base.clone();
+        let mut spans = Vec::new();
+        for (path, boost) in active {
+            if path == ALL_TARGETS {
+                base = boost.level.as_str().to_ascii_lowercase();
+            } else {
+                spans.push(format!(
+                    "[{}{{target_path={}}}]={}",
+                    TARGET_SPAN,
+                    regex::escape(path),
+                    boost.level.as_str().to_ascii_lowercase()
+                ));
+            }
+        }
+
+        if spans.is_empty() {
+            return base;
+        }
+        // Target spans are INFO; keep them enabled so boosts can match them
+        format!("{},{}=info,{}", base, TARGET_SPAN_MODULE, spans.join(","))
+    }
+
+    fn apply(&self, state: &LevelState) -> Result<(), LoggingError> {
+        let directives = Self::directives(state, Instant::now());
+        let filter = EnvFilter::try_new(&directives).map_err(|e| LoggingError::InvalidConfig {
+            field: "level",
+            message: e.to_string(),
+        })?;
+        self.handle
+            .reload(filter)
+            .map_err(|e| LoggingError::InitFailed(Box::new(e)))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LevelState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn set_base_level(&self, level: Level) -> Result<bool, LoggingError> {
+        let mut state = self.lock();
+        if state.from_env {
+            return Ok(false);
+        }
+        let base = level.as_str().to_ascii_lowercase();
+        if state.base == base {
+            return Ok(false);
+        }
+        state.base = base;
+        self.apply(&state)?;
+        Ok(true)
+    }
+
+    fn boost(
+        &self,
+        target: Option<&Path>,
+        level: Level,
+        duration: Duration,
+    ) -> Result<(), LoggingError> {
+        let key = match target {
+            Some(path) => {
+                let path = path.display().to_string();
+                validate_target_path(&path)?;
+                path
+            }
+            None => ALL_TARGETS.to_string(),
+        };
+
+        let until =
+            Instant::now()
+                .checked_add(duration)
+                .ok_or_else(|| LoggingError::InvalidConfig {
+                    field: "minutes",
+                    message: "boost duration is too long".to_string(),
+                })?;
+        let mut state = self.lock();
+        let base_level = max_level(&state.base);
+        if LevelFilter::from_level(level) <= base_level {
+            return Err(LoggingError::InvalidConfig {
+                field: "level",
+                message: format!(
+                    "already logging at {} or more verbose",
+                    level.as_str().to_ascii_lowercase()
+                ),
+            });
+        }
+        state.boosts.insert(key, Boost { level, until });
+        self.apply(&state)
+    }
+
+    fn expire(&self) -> Result<(), LoggingError> {
+        let mut state = self.lock();
+        let now = Instant::now();
+        let before = state.boosts.len();
+        state.boosts.retain(|_, boost| boost.until > now);
+        if state.boosts.len() != before {
+            self.apply(&state)?;
+        }
+        Ok(())
+    }
+}
+
+/// Most verbose level the base directives enable anywhere
+fn max_level(base: &str) -> LevelFilter {
+    EnvFilter::new(base)
+        .max_level_hint()
+        .unwrap_or(LevelFilter::TRACE)
+}
+
+/// Target paths end up inside a filter directive, so paths containing the
+/// directive separators can't be matched
+fn validate_target_path(path: &str) -> Result<(), LoggingError> {
+    let usable = !path.is_empty() && !path.contains([',', ']', '}', '=']) && path.trim() == path;
+    if usable {
+        Ok(())
+    } else {
+        Err(LoggingError::InvalidConfig {
+            field: "target",
+            message: format!("target path '{}' cannot be used in a log filter", path),
+        })
+    }
+}
+
+fn control() -> Result<&'static LevelControl, LoggingError> {
+    LEVEL_CONTROL
+        .get()
+        .ok_or_else(|| LoggingError::InvalidConfig {
+            field: "level",
+            message: "logging is not initialized".to_string(),
+        })
+}
+
+/// Apply a new `logging.level`; returns false if nothing changed or RUST_LOG
+/// overrides it
+pub fn set_base_level(level: Level) -> Result<bool, LoggingError> {
+    control()?.set_base_level(level)
+}
+
+/// Log at `level` for the target at one path (or all, if `None`) for
+/// `duration`; `level` must be more verbose than the base level.
+///
+/// Call `expire_boosts` once the duration has passed to restore the filter.
+pub fn boost_level(
+    target: Option<&Path>,
+    level: Level,
+    duration: Duration,
+) -> Result<(), LoggingError> {
+    control()?.boost(target, level, duration)
+}
+
+/// Drop boosts that have run out and rebuild the filter
+pub fn expire_boosts() -> Result<(), LoggingError> {
+    control()?.expire()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::target_span;
+    use std::sync::{Arc, Mutex as StdMutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<StdMutex<Vec<String>>>);
+
+    impl std::io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0
+                .lock()
+                .unwrap()
+                .push(String::from_utf8_lossy(buf).to_string());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_boost_raises_level_for_one_target() {
+        let (filter, handle) = reload::Layer::new(EnvFilter::new("warn"));
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::registry().with(filter).with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || writer.clone()),
+        );
+        let control = LevelControl::new(handle, "warn".to_string(), false);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let notes = Path::new("/home/u/notes");
+            let log_all = || {
+                target_span("notes", notes).in_scope(|| tracing::debug!("notes detail"));
+                // Same name, different path
+                target_span("notes", Path::new("/home/u/notes.old"))
+                    .in_scope(|| tracing::debug!("other detail"));
+            };
+
+            log_all();
+            control
+                .boost(Some(notes), Level::DEBUG, Duration::from_secs(60))
+                .unwrap();
+            log_all();
+
+            // Expired boosts are removed
+            control
+                .boost(Some(notes), Level::DEBUG, Duration::ZERO)
+                .unwrap();
+            control.expire().unwrap();
+            log_all();
+        });
+
+        let lines = capture.0.lock().unwrap().join("");
+        assert_eq!(lines.matches("notes detail").count(), 1);
+        assert!(!lines.contains("other detail"));
+    }
+
+    #[test]
+    fn test_boost_never_lowers_the_base_level() {
+        let (_, handle) = reload::Layer::new(EnvFilter::new("debug"));
+        let control = LevelControl::new(handle, "debug".to_string(), false);
+        let notes = Path::new("/home/u/notes");
+        assert!(control
+            .boost(Some(notes), Level::WARN, Duration::from_secs(60))
+            .is_err());
+        assert!(control
+            .boost(None, Level::DEBUG, Duration::from_secs(60))
+            .is_err());
+
+        // A boost that the base has since caught up with is dropped
+        let mut state = LevelState {
+            base: "warn".to_string(),
+            from_env: false,
+            boosts: HashMap::new(),
+        };
+        let until = Instant::now() + Duration::from_secs(60);
+        let boost = Boost {
+            level: Level::INFO,
+            until,
+        };
+        state.boosts.insert(ALL_TARGETS.to_string(), boost);
+        assert_eq!(LevelControl::directives(&state, Instant::now()), "info");
+        state.base = "trace".to_string();
+        assert_eq!(LevelControl::directives(&state, Instant::now()), "trace");
+    }
+
+    #[test]
+    fn test_boost_rejects_unusable_target_paths() {
+        assert!(validate_target_path("/home/u/my notes.d").is_ok());
+        assert!(validate_target_path("/home/u/a,b").is_err());
+        assert!(validate_target_path("/home/u/x=y").is_err());
+    }
+}
@@ -1,14 +1,52 @@
+use std::collections::HashMap;
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::thread;
+use std::time::{Duration, SystemTime};
 
-use tracing::Level;
-use tracing_subscriber::fmt::format::FmtSpan;
-use tracing_subscriber::EnvFilter;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::{self, FmtSpan, FormatEvent, FormatFields};
+use tracing_subscriber::fmt::{FmtContext, MakeWriter};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{filter, reload, EnvFilter, Layer};
 
-use crate::config::LoggingConfig;
+use crate::config::{LogFormat, LogRotation, LoggingConfig};
+
+mod level;
+mod query;
+
+use level::LevelControl;
+pub use level::{boost_level, expire_boosts, set_base_level, MAX_BOOST_MINUTES};
+pub use query::{follow_log, print_logs, LogFilter, TargetFilter};
+
+/// Name of the daemon's log file inside `logging.directory`
+pub const LOG_FILE_NAME: &str = "gsd.log";
+
+/// Subdirectory of `logging.directory` holding per-target log files
+pub const TARGET_LOG_DIR: &str = "targets";
+
+/// Name of the span that attributes nested events to a target
+const TARGET_SPAN: &str = "target";
+
+/// Tracing target of the spans made by `target_span`
+const TARGET_SPAN_MODULE: &str = module_path!();
+
+/// Span for work done on behalf of one target.
+///
+/// Events inside it are also written to that target's log file when
+/// `logging.per_target_files` is enabled.
+pub fn target_span(name: &str, path: &Path) -> tracing::Span {
+    tracing::info_span!("target", target_name = %name, target_path = %path.display())
+}
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -18,6 +56,11 @@ pub struct LoggingSettings {
     pub max_bytes: u64,
     pub max_files: usize,
     pub console: bool,
+    pub format: LogFormat,
+    pub per_target_files: bool,
+    pub rotation: LogRotation,
+    pub compress: bool,
+    pub max_age: Option<Duration>,
 }
 
 pub struct LoggingGuards {
@@ -59,7 +102,7 @@ pub enum LoggingError {
     InitFailed(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
 
-fn parse_level(value: &str) -> Result<Level, LoggingError> {
+pub(crate) fn parse_level(value: &str) -> Result<Level, LoggingError> {
     match value.trim().to_ascii_lowercase().as_str() {
         "trace" => Ok(Level::TRACE),
         "debug" => Ok(Level::DEBUG),
@@ -90,6 +133,17 @@ impl LoggingSettings {
                     message: "must be > 0 when logging.directory is set".to_string(),
                 });
             }
+            if cfg.max_age_days == Some(0) {
+                return Err(LoggingError::InvalidConfig {
+                    field: "max_age_days",
+                    message: "must be > 0".to_string(),
+                });
+            }
+        } else if cfg.per_target_files {
+            return Err(LoggingError::InvalidConfig {
+                field: "per_target_files",
+                message: "requires logging.directory".to_string(),
+            });
         }
 
         Ok(Self {
@@ -98,31 +152,73 @@ impl LoggingSettings {
             max_bytes: cfg.max_bytes,
             max_files: cfg.max_files,
             console: cfg.console,
+            format: cfg.format,
+            per_target_files: cfg.per_target_files,
+            rotation: cfg.rotation,
+            compress: cfg.compress,
+            max_age: cfg
+                .max_age_days
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
         })
     }
 
+    fn rotation_policy(&self) -> RotationPolicy {
+        RotationPolicy {
+            rotation: self.rotation,
+            max_bytes: self.max_bytes,
+            max_files: self.max_files,
+            max_age: self.max_age,
+            compress: self.compress,
+        }
+    }
+
     pub fn init_tracing(&self) -> Result<LoggingGuards, LoggingError> {
-        let filter = EnvFilter::try_from_default_env()
-            .unwrap_or_else(|_| EnvFilter::new(self.level.as_str()));
+        let env_filter = std::env::var(EnvFilter::DEFAULT_ENV)
+            .ok()
+            .filter(|directives| EnvFilter::try_new(directives).is_ok());
+        let from_env = env_filter.is_some();
+        let base = env_filter.unwrap_or_else(|| self.level.as_str().to_ascii_lowercase());
+        let (filter, handle) = reload::Layer::new(EnvFilter::new(&base));
 
-        let (writer, guards) = self.build_writer()?;
+        let (writer, sender, guards) = self.build_writer()?;
         let ansi = self.console && self.directory.is_none();
 
-        let subscriber = tracing_subscriber::fmt()
-            .with_env_filter(filter)
+        let fmt_layer = tracing_subscriber::fmt::layer()
             .with_span_events(FmtSpan::CLOSE)
             .with_target(true)
             .with_ansi(ansi)
             .with_writer(writer);
+        let fmt_layer = match self.format {
+            LogFormat::Text => fmt_layer.boxed(),
+            LogFormat::Json => fmt_layer.json().boxed(),
+        };
 
-        subscriber.try_init().map_err(LoggingError::InitFailed)?;
+        let target_layer = self
+            .per_target_files
+            .then(|| target_file_layer(sender, self.format));
+
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt_layer)
+            .with(target_layer)
+            .try_init()
+            .map_err(|e| LoggingError::InitFailed(Box::new(e)))?;
+        LevelControl::new(handle, base, from_env).install();
 
         Ok(guards)
     }
 
+    #[allow(clippy::type_complexity)]
     fn build_writer(
         &self,
-    ) -> Result<(impl Fn() -> NonBlockingWriter, LoggingGuards), LoggingError> {
+    ) -> Result<
+        (
+            impl Fn() -> NonBlockingWriter,
+            mpsc::Sender<LogMessage>,
+            LoggingGuards,
+        ),
+        LoggingError,
+    > {
         let (tx, rx) = mpsc::channel::<LogMessage>();
         let mut file_writer = None;
 
@@ -131,21 +227,35 @@ impl LoggingSettings {
                 path: directory.clone(),
                 source,
             })?;
-            let path = directory.join("gsd.log");
+            let path = directory.join(LOG_FILE_NAME);
             file_writer = Some(
-                RotatingFileWriter::new(path, self.max_bytes, self.max_files)
+                RotatingFileWriter::new(path, self.rotation_policy())
                     .map_err(|e| LoggingError::InitFailed(Box::new(e)))?,
             );
         }
 
         let console = self.console;
+        let target_files = match &self.directory {
+            Some(directory) if self.per_target_files => Some(TargetFiles {
+                directory: directory.join(TARGET_LOG_DIR),
+                policy: self.rotation_policy(),
+                writers: HashMap::new(),
+            }),
+            _ => None,
+        };
         let worker = thread::spawn(move || {
             let mut writer = LogWriter::new(file_writer, console);
+            let mut target_files = target_files;
             while let Ok(msg) = rx.recv() {
                 match msg {
                     LogMessage::Data(data) => {
                         let _ = writer.write_all(&data);
                     }
+                    LogMessage::Target(path, data) => {
+                        if let Some(files) = &mut target_files {
+                            let _ = files.write(&path, &data);
+                        }
+                    }
                     LogMessage::Flush => {
                         let _ = writer.flush();
                     }
@@ -153,6 +263,9 @@ impl LoggingSettings {
                 }
             }
             let _ = writer.flush();
+            if let Some(files) = &mut target_files {
+                files.flush();
+            }
         });
 
         let writer = NonBlockingWriter { sender: tx.clone() };
@@ -160,6 +273,7 @@ impl LoggingSettings {
 
         Ok((
             make_writer,
+            tx.clone(),
             LoggingGuards {
                 shutdown_tx: Some(tx),
                 worker: Some(worker),
@@ -191,10 +305,213 @@ impl Write for NonBlockingWriter {
 
 enum LogMessage {
     Data(Vec<u8>),
+    /// A formatted event for one target's own log file, keyed by target path
+    Target(String, Vec<u8>),
     Flush,
     Shutdown,
 }
 
+/// Target path stored in the extensions of a target span
+struct TargetPath(String);
+
+/// Records the target path of each target span, for `target_path_in`
+struct TargetSpanLayer;
+
+impl<S> Layer<S> for TargetSpanLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != TARGET_SPAN {
+            return;
+        }
+        let mut visitor = TargetPathVisitor::default();
+        attrs.record(&mut visitor);
+        if let (Some(path), Some(span)) = (visitor.path, ctx.span(id)) {
+            span.extensions_mut().insert(TargetPath(path));
+        }
+    }
+}
+
+#[derive(Default)]
+struct TargetPathVisitor {
+    path: Option<String>,
+}
+
+impl Visit for TargetPathVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "target_path" {
+            self.path = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "target_path" {
+            self.path = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// Path of the innermost target span enclosing `span`
+fn target_path_in<'a, R: LookupSpan<'a>>(span: SpanRef<'a, R>) -> Option<String> {
+    span.scope()
+        .find_map(|span| span.extensions().get::<TargetPath>().map(|t| t.0.clone()))
+}
+
+/// Copies events inside a target span to the logging worker, formatted by
+/// the same fmt layer as the main log
+fn target_file_layer<S>(sender: mpsc::Sender<LogMessage>, format: LogFormat) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_target(true)
+        .with_ansi(false)
+        .with_writer(TargetMakeWriter { sender });
+    let fmt_layer = match format {
+        LogFormat::Text => fmt_layer.map_event_format(TargetFormat).boxed(),
+        LogFormat::Json => fmt_layer.json().map_event_format(TargetFormat).boxed(),
+    };
+    // Skip formatting events that no target file would receive. Spans stay
+    // enabled, since the filter's context only sees spans it enabled.
+    let in_target = filter::dynamic_filter_fn(|metadata, ctx: &Context<'_, S>| {
+        metadata.is_span() || ctx.lookup_current().and_then(target_path_in).is_some()
+    });
+    TargetSpanLayer.and_then(fmt_layer.with_filter(in_target))
+}
+
+/// Prefixes each formatted event with its target path and a NUL, which
+/// `TargetWriter` strips to pick the file
+struct TargetFormat<E>(E);
+
+impl<S, N, E> FormatEvent<S, N> for TargetFormat<E>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+    E: FormatEvent<S, N>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: format::Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let Some(path) = ctx
+            .event_scope()
+            .and_then(|mut scope| scope.find_map(|span| target_path_in(span)))
+        else {
+            return Ok(());
+        };
+        write!(writer, "{}\0", path)?;
+        self.0.format_event(ctx, writer, event)
+    }
+}
+
+struct TargetMakeWriter {
+    sender: mpsc::Sender<LogMessage>,
+}
+
+impl<'a> MakeWriter<'a> for TargetMakeWriter {
+    type Writer = TargetWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        TargetWriter {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+/// Sends each event formatted by `TargetFormat` to its target's file
+struct TargetWriter {
+    sender: mpsc::Sender<LogMessage>,
+}
+
+impl Write for TargetWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(split) = buf.iter().position(|&b| b == 0) else {
+            return Ok(buf.len());
+        };
+        let path = String::from_utf8_lossy(&buf[..split]).into_owned();
+        self.sender
+            .send(LogMessage::Target(path, buf[split + 1..].to_vec()))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "logging worker stopped"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Rotating log files under `<directory>/targets`, opened on first use
+struct TargetFiles {
+    directory: PathBuf,
+    policy: RotationPolicy,
+    writers: HashMap<String, RotatingFileWriter>,
+}
+
+impl TargetFiles {
+    fn write(&mut self, target: &str, data: &[u8]) -> io::Result<()> {
+        if !self.writers.contains_key(target) {
+            fs::create_dir_all(&self.directory)?;
+            let path = target_log_path(&self.directory, Path::new(target));
+            let writer = RotatingFileWriter::new(path, self.policy.clone())?;
+            self.writers.insert(target.to_string(), writer);
+        }
+        let writer = self.writers.get_mut(target).expect("writer inserted");
+        writer.write_all(data)?;
+        writer.flush()
+    }
+
+    fn flush(&mut self) {
+        for writer in self.writers.values_mut() {
+            let _ = writer.flush();
+        }
+    }
+}
+
+/// Log file for a target, named after its full path
+pub(crate) fn target_log_path(directory: &Path, target: &Path) -> PathBuf {
+    directory.join(format!("{}.log", target_file_name(target)))
+}
+
+/// File name stem unique to a target path: the sanitized path plus a hash
+/// of the original, since sanitizing alone maps different paths together
+pub(crate) fn target_file_name(target: &Path) -> String {
+    let path = target.to_string_lossy();
+    format!(
+        "{}-{:016x}",
+        safe_file_name(path.trim_matches('/'), "root"),
+        stable_hash(path.as_bytes())
+    )
+}
+
+/// `name` with characters unsafe in file names replaced and leading dots
+/// removed, or `fallback` if nothing is left
+pub(crate) fn safe_file_name(name: &str, fallback: &str) -> String {
+    let safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match safe.trim_start_matches('.') {
+        "" => fallback.to_string(),
+        safe => safe.to_string(),
+    }
+}
+
+/// FNV-1a hash, stable across runs and builds for naming files on disk
+pub(crate) fn stable_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 struct LogWriter {
     file: Option<RotatingFileWriter>,
     console: bool,
@@ -234,41 +551,95 @@ impl Write for LogWriter {
     }
 }
 
-struct RotatingFileWriter {
+/// How and when a `RotatingFileWriter` rotates and prunes its files
+#[derive(Debug, Clone)]
+pub(crate) struct RotationPolicy {
+    pub rotation: LogRotation,
+    pub max_bytes: u64,
+    pub max_files: usize,
+    pub max_age: Option<Duration>,
+    pub compress: bool,
+}
+
+impl LogRotation {
+    /// Period a timestamp falls in, or `None` for size-only rotation
+    fn period(&self, time: DateTime<Utc>) -> Option<String> {
+        match self {
+            LogRotation::Size => None,
+            LogRotation::Hourly => Some(time.format("%Y-%m-%dT%H").to_string()),
+            LogRotation::Daily => Some(time.format("%Y-%m-%d").to_string()),
+        }
+    }
+}
+
+/// Log file writer rotating by size and, optionally, by hour or day.
+///
+/// Size rotation keeps `<name>.1` (newest) to `<name>.<max_files>`. Time-based
+/// rotation names files after the period they cover (UTC), e.g.
+/// `gsd.log.2026-01-21`, adding `.N` if a period rotates on size more than once.
+pub(crate) struct RotatingFileWriter {
     base_path: PathBuf,
-    max_bytes: u64,
-    max_files: usize,
+    policy: RotationPolicy,
     file: File,
     size: u64,
+    /// Period the current file's contents belong to (time-based mode)
+    period: Option<String>,
+    /// Background compression of the last rotated file, then pruning
+    compressor: Option<thread::JoinHandle<()>>,
 }
 
 impl RotatingFileWriter {
-    fn new(base_path: PathBuf, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+    pub(crate) fn new(base_path: PathBuf, policy: RotationPolicy) -> io::Result<Self> {
         let file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(&base_path)?;
-        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let metadata = file.metadata()?;
+        let size = metadata.len();
+
+        // An existing file belongs to the period it was last written in
+        let modified = metadata
+            .modified()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+        let period = policy.rotation.period(modified);
 
         let mut writer = Self {
             base_path,
-            max_bytes,
-            max_files,
+            policy,
             file,
             size,
+            period,
+            compressor: None,
         };
 
-        if writer.max_bytes > 0 && writer.size >= writer.max_bytes {
+        let now = Utc::now();
+        if writer.policy.max_bytes > 0 && writer.size >= writer.policy.max_bytes
+            || writer.period_changed(now)
+        {
             writer.rotate()?;
         }
+        writer.period = writer.policy.rotation.period(now);
 
         Ok(writer)
     }
 
+    /// Flush file contents to disk, for records that must survive a crash
+    pub(crate) fn sync(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.sync_data()
+    }
+
+    fn period_changed(&self, now: DateTime<Utc>) -> bool {
+        self.size > 0 && self.period != self.policy.rotation.period(now)
+    }
+
     fn rotate(&mut self) -> io::Result<()> {
         self.file.flush()?;
+        // Renaming rotated files must not race the previous compression
+        self.wait_for_compressor();
 
-        if self.max_files == 0 {
+        if self.policy.max_files == 0 {
             self.file = OpenOptions::new()
                 .create(true)
                 .write(true)
@@ -278,20 +649,15 @@ impl RotatingFileWriter {
             return Ok(());
         }
 
-        let oldest = self.rotated_path(self.max_files);
-        let _ = fs::remove_file(&oldest);
-
-        for idx in (1..self.max_files).rev() {
-            let from = self.rotated_path(idx);
-            let to = self.rotated_path(idx + 1);
-            if from.exists() {
-                let _ = fs::rename(&from, &to);
+        let rotated = match self.period.clone() {
+            Some(period) => self.timestamped_path(&period),
+            None => {
+                self.shift_numbered();
+                self.rotated_path(1)
             }
-        }
+        };
 
-        if self.base_path.exists() {
-            let _ = fs::rename(&self.base_path, self.rotated_path(1));
-        }
+        let renamed = self.base_path.exists() && fs::rename(&self.base_path, &rotated).is_ok();
 
         self.file = OpenOptions::new()
             .create(true)
@@ -299,41 +665,218 @@ impl RotatingFileWriter {
             .truncate(true)
             .open(&self.base_path)?;
         self.size = 0;
+
+        if renamed && self.policy.compress {
+            // Compress off the logging path, pruning once the .gz is in place
+            let base_path = self.base_path.clone();
+            let policy = self.policy.clone();
+            self.compressor = Some(thread::spawn(move || {
+                // Keep the plain file if compression fails
+                let _ = compress_file(&rotated);
+                prune(&base_path, &policy);
+            }));
+        } else {
+            prune(&self.base_path, &self.policy);
+        }
         Ok(())
     }
 
-    fn rotated_path(&self, index: usize) -> PathBuf {
-        let name = self
-            .base_path
+    fn wait_for_compressor(&mut self) {
+        if let Some(compressor) = self.compressor.take() {
+            let _ = compressor.join();
+        }
+    }
+
+    /// Move `<name>.N` (and `<name>.N.gz`) up by one, dropping the oldest
+    fn shift_numbered(&self) {
+        let max_files = self.policy.max_files;
+        for path in [
+            self.rotated_path(max_files),
+            gz_path(&self.rotated_path(max_files)),
+        ] {
+            let _ = fs::remove_file(path);
+        }
+
+        for idx in (1..max_files).rev() {
+            let from = self.rotated_path(idx);
+            let to = self.rotated_path(idx + 1);
+            if from.exists() {
+                let _ = fs::rename(&from, &to);
+            }
+            if gz_path(&from).exists() {
+                let _ = fs::rename(gz_path(&from), gz_path(&to));
+            }
+        }
+    }
+
+    fn base_name(&self) -> String {
+        self.base_path
             .file_name()
             .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| "gsd.log".to_string());
-        let file_name = format!("{}.{}", name, index);
+            .unwrap_or_else(|| LOG_FILE_NAME.to_string())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let file_name = format!("{}.{}", self.base_name(), index);
         let mut path = self.base_path.clone();
         path.set_file_name(file_name);
         path
     }
-}
 
-impl Write for RotatingFileWriter {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        if self.max_bytes > 0 && self.size.saturating_add(buf.len() as u64) > self.max_bytes {
+    /// First unused `<name>.<period>[.N]` path
+    fn timestamped_path(&self, period: &str) -> PathBuf {
+        let base = format!("{}.{}", self.base_name(), period);
+        let mut path = self.base_path.with_file_name(&base);
+        let mut n = 1;
+        while path.exists() || gz_path(&path).exists() {
+            path = self.base_path.with_file_name(format!("{}.{}", base, n));
+            n += 1;
+        }
+        path
+    }
+
+    fn write_at(&mut self, buf: &[u8], now: DateTime<Utc>) -> io::Result<usize> {
+        let over_size = self.policy.max_bytes > 0
+            && self.size.saturating_add(buf.len() as u64) > self.policy.max_bytes;
+        if over_size || self.period_changed(now) {
             self.rotate()?;
         }
+        self.period = self.policy.rotation.period(now);
 
         let written = self.file.write(buf)?;
         self.size = self.size.saturating_add(written as u64);
         Ok(written)
     }
+}
+
+impl Drop for RotatingFileWriter {
+    fn drop(&mut self) {
+        self.wait_for_compressor();
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_at(buf, Utc::now())
+    }
 
     fn flush(&mut self) -> io::Result<()> {
         self.file.flush()
     }
 }
 
+/// Rotated files next to `base_path`, with their modification times
+fn rotated_files(base_path: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let Some(name) = base_path.file_name() else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", name.to_string_lossy());
+    let Some(dir) = base_path.parent() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_prefix(&prefix).is_some_and(is_rotation_suffix)
+        })
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((entry.path(), modified))
+        })
+        .collect()
+}
+
+/// Remove rotated files beyond `max_files` or older than `max_age`
+fn prune(base_path: &Path, policy: &RotationPolicy) {
+    let mut rotated = rotated_files(base_path);
+    // Newest first
+    rotated.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+
+    let now = SystemTime::now();
+    for (idx, (path, modified)) in rotated.iter().enumerate() {
+        let too_many = idx >= policy.max_files;
+        let too_old = policy
+            .max_age
+            .map(|max_age| now.duration_since(*modified).unwrap_or_default() > max_age)
+            .unwrap_or(false);
+        if too_many || too_old {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Whether `suffix` is one `RotatingFileWriter` adds when rotating: `N`,
+/// `<period>` or `<period>.N`, each optionally followed by `.gz`
+fn is_rotation_suffix(suffix: &str) -> bool {
+    let suffix = suffix.strip_suffix(".gz").unwrap_or(suffix);
+    let is_index = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let is_period = |s: &str| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok()
+            || NaiveDateTime::parse_from_str(&format!("{}:00", s), "%Y-%m-%dT%H:%M").is_ok()
+    };
+    match suffix.split_once('.') {
+        Some((period, index)) => is_period(period) && is_index(index),
+        None => is_index(suffix) || is_period(suffix),
+    }
+}
+
+/// A log file and its rotated predecessors, oldest first
+pub(crate) fn log_file_set(base_path: &Path) -> Vec<PathBuf> {
+    let mut rotated = rotated_files(base_path);
+    // Numbered files rotated within the same clock tick: higher is older
+    let index = |path: &Path| {
+        let name = path.file_name()?.to_string_lossy().to_string();
+        let name = name.strip_suffix(".gz").unwrap_or(&name);
+        name.rsplit('.').next()?.parse::<usize>().ok()
+    };
+    rotated.sort_by_key(|(path, modified)| (*modified, std::cmp::Reverse(index(path))));
+    let mut files: Vec<PathBuf> = rotated.into_iter().map(|(path, _)| path).collect();
+    if base_path.exists() {
+        files.push(base_path.to_path_buf());
+    }
+    files
+}
+
+/// Open a current or rotated log file, decompressing `.gz` files
+pub(crate) fn open_log_file(path: &Path) -> io::Result<Box<dyn io::BufRead>> {
+    let file = File::open(path)?;
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        Ok(Box::new(io::BufReader::new(flate2::read::GzDecoder::new(
+            file,
+        ))))
+    } else {
+        Ok(Box::new(io::BufReader::new(file)))
+    }
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".gz");
+    PathBuf::from(name)
+}
+
+/// Replace `path` with a gzipped `<path>.gz`
+fn compress_file(path: &Path) -> io::Result<()> {
+    let target = gz_path(path);
+    let mut input = File::open(path)?;
+    let output = File::create(&target)?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    if let Err(e) = io::copy(&mut input, &mut encoder).and_then(|_| encoder.finish()) {
+        let _ = fs::remove_file(&target);
+        return Err(e);
+    }
+    fs::remove_file(path)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Read;
     use tempfile::TempDir;
 
     #[test]
@@ -345,6 +888,7 @@ mod tests {
             max_bytes: 0,
             max_files: 1,
             console: true,
+            ..Default::default()
         };
 
         assert!(matches!(
@@ -365,6 +909,7 @@ mod tests {
             max_bytes: 10,
             max_files: 0,
             console: true,
+            ..Default::default()
         };
 
         assert!(matches!(
@@ -376,12 +921,30 @@ mod tests {
         ));
     }
 
+    fn size_policy(max_bytes: u64, max_files: usize) -> RotationPolicy {
+        RotationPolicy {
+            rotation: LogRotation::Size,
+            max_bytes,
+            max_files,
+            max_age: None,
+            compress: false,
+        }
+    }
+
+    fn read_gz(path: &Path) -> String {
+        let mut out = String::new();
+        flate2::read::GzDecoder::new(File::open(path).unwrap())
+            .read_to_string(&mut out)
+            .unwrap();
+        out
+    }
+
     #[test]
     fn test_rotating_file_writer_rotates_on_size() {
         let temp = TempDir::new().unwrap();
         let base_path = temp.path().join("gsd.log");
 
-        let mut writer = RotatingFileWriter::new(base_path.clone(), 5, 2).unwrap();
+        let mut writer = RotatingFileWriter::new(base_path.clone(), size_policy(5, 2)).unwrap();
         writer.write_all(b"hello").unwrap();
         writer.write_all(b"!").unwrap();
         writer.flush().unwrap();
@@ -394,4 +957,145 @@ mod tests {
         assert_eq!(rotated, "hello");
         assert!(!temp.path().join("gsd.log.2").exists());
     }
+
+    #[test]
+    fn test_daily_rotation_uses_period_names() {
+        let temp = TempDir::new().unwrap();
+        let base_path = temp.path().join("gsd.log");
+        let policy = RotationPolicy {
+            rotation: LogRotation::Daily,
+            ..size_policy(1024, 5)
+        };
+        let day1: DateTime<Utc> = "2026-01-21T23:59:00Z".parse().unwrap();
+        let day2: DateTime<Utc> = "2026-01-22T00:01:00Z".parse().unwrap();
+
+        let mut writer = RotatingFileWriter::new(base_path.clone(), policy).unwrap();
+        writer.write_at(b"first", day1).unwrap();
+        writer.write_at(b" day", day1).unwrap();
+        writer.write_at(b"second", day2).unwrap();
+        writer.flush().unwrap();
+
+        let rotated = fs::read_to_string(temp.path().join("gsd.log.2026-01-21")).unwrap();
+        assert_eq!(rotated, "first day");
+        assert_eq!(fs::read_to_string(&base_path).unwrap(), "second");
+    }
+
+    #[test]
+    fn test_rotated_files_are_compressed_and_shifted() {
+        let temp = TempDir::new().unwrap();
+        let base_path = temp.path().join("gsd.log");
+        let policy = RotationPolicy {
+            compress: true,
+            ..size_policy(5, 2)
+        };
+
+        let mut writer = RotatingFileWriter::new(base_path.clone(), policy).unwrap();
+        writer.write_all(b"hello").unwrap();
+        writer.write_all(b"world").unwrap();
+        writer.write_all(b"!").unwrap();
+        // Dropping the writer waits for background compression
+        drop(writer);
+
+        assert_eq!(read_gz(&temp.path().join("gsd.log.1.gz")), "world");
+        assert_eq!(read_gz(&temp.path().join("gsd.log.2.gz")), "hello");
+        assert!(!temp.path().join("gsd.log.1").exists());
+        assert_eq!(fs::read_to_string(&base_path).unwrap(), "!");
+    }
+
+    #[test]
+    fn test_rotation_prunes_files_older_than_max_age() {
+        let temp = TempDir::new().unwrap();
+        let base_path = temp.path().join("gsd.log");
+        let stale = temp.path().join("gsd.log.2025-01-01.gz");
+        let old = File::create(&stale).unwrap();
+        old.set_modified(SystemTime::now() - Duration::from_secs(10 * 24 * 60 * 60))
+            .unwrap();
+        drop(old);
+
+        let policy = RotationPolicy {
+            max_age: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+            ..size_policy(5, 5)
+        };
+        let mut writer = RotatingFileWriter::new(base_path, policy).unwrap();
+        writer.write_all(b"hello").unwrap();
+        writer.write_all(b"!").unwrap();
+
+        assert!(!stale.exists());
+        assert!(temp.path().join("gsd.log.1").exists());
+    }
+
+    #[test]
+    fn test_target_layer_routes_events_in_target_span() {
+        let (tx, rx) = mpsc::channel();
+        let subscriber =
+            tracing_subscriber::registry().with(target_file_layer(tx, LogFormat::Json));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("outside any target");
+            let _span = target_span("notes", Path::new("/home/u/notes")).entered();
+            tracing::info!(files = 2, "Committing changes");
+        });
+
+        let messages: Vec<LogMessage> = rx.try_iter().collect();
+        assert_eq!(messages.len(), 1);
+        let LogMessage::Target(path, data) = &messages[0] else {
+            panic!("expected a target message");
+        };
+        assert_eq!(path, "/home/u/notes");
+        let line: serde_json::Value = serde_json::from_slice(data).unwrap();
+        assert_eq!(line["fields"]["message"], "Committing changes");
+        assert_eq!(line["fields"]["files"], 2);
+        // Formatted like the main log, span context included
+        assert_eq!(line["span"]["target_name"], "notes");
+    }
+
+    #[test]
+    fn test_target_log_path_is_unique_per_path() {
+        let dir = Path::new("/logs/targets");
+        let a = target_log_path(dir, Path::new("/home/u/a/notes"));
+        let b = target_log_path(dir, Path::new("/home/u/b/notes"));
+        assert_ne!(a, b);
+        assert_eq!(a, target_log_path(dir, Path::new("/home/u/a/notes")));
+        assert!(a
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("home_u_a_notes-"));
+        assert_ne!(
+            target_log_path(dir, Path::new("/srv/a_b/c")),
+            target_log_path(dir, Path::new("/srv/a/b_c"))
+        );
+    }
+
+    #[test]
+    fn test_rotated_files_skip_other_logs_sharing_the_prefix() {
+        let temp = TempDir::new().unwrap();
+        for name in [
+            "a.log",
+            "a.log.1",
+            "a.log.2.gz",
+            "a.log.2026-01-21",
+            "a.log.2026-01-21T05.1.gz",
+            "a.log.log",
+            "a.log.log.1",
+            "a.log.old",
+        ] {
+            File::create(temp.path().join(name)).unwrap();
+        }
+
+        let mut names: Vec<String> = rotated_files(&temp.path().join("a.log"))
+            .into_iter()
+            .map(|(path, _)| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "a.log.1",
+                "a.log.2.gz",
+                "a.log.2026-01-21",
+                "a.log.2026-01-21T05.1.gz"
+            ]
+        );
+    }
 }
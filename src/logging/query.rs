@@ -0,0 +1,250 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tracing::Level;
+
+use super::{log_file_set, open_log_file};
+
+/// How often `follow_log` checks the file for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Selects log lines by level, time and target.
+///
+/// JSON lines are matched on their structured fields. Text lines are matched
+/// on the timestamp and level columns and the `target{target_name=..}` span
+/// or `target=` field.
+#[derive(Debug, Default)]
+pub struct LogFilter {
+    /// Least severe level to show
+    pub level: Option<Level>,
+    pub since: Option<DateTime<Utc>>,
+    pub target: Option<TargetFilter>,
+}
+
+#[derive(Debug)]
+pub struct TargetFilter {
+    pub name: String,
+    pub path: String,
+}
+
+impl TargetFilter {
+    fn matches_value(&self, value: &str) -> bool {
+        value == self.name || value == self.path
+    }
+}
+
+impl LogFilter {
+    pub fn matches(&self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return false;
+        }
+        match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(value) if value.is_object() => self.matches_json(&value),
+            _ => self.matches_text(line),
+        }
+    }
+
+    fn matches_json(&self, value: &serde_json::Value) -> bool {
+        let level = value["level"].as_str();
+        let time = value["timestamp"].as_str();
+        if !self.matches_level(level) || !self.matches_time(time) {
+            return false;
+        }
+
+        let Some(target) = &self.target else {
+            return true;
+        };
+        let in_span = value["spans"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .chain(std::iter::once(&value["span"]))
+            .filter_map(|span| span["target_name"].as_str())
+            .any(|name| target.matches_value(name));
+        let in_fields = value["fields"]["target"]
+            .as_str()
+            .is_some_and(|v| target.matches_value(v));
+        in_span || in_fields
+    }
+
+    fn matches_text(&self, line: &str) -> bool {
+        let mut columns = line.split_whitespace();
+        let time = columns.next();
+        let level = columns.next();
+        if !self.matches_level(level) || !self.matches_time(time) {
+            return false;
+        }
+
+        let Some(target) = &self.target else {
+            return true;
+        };
+        let patterns = [
+            format!("target{{target_name={}}}", target.name),
+            format!("target{{target_name={} ", target.name),
+            format!(" target={} ", target.name),
+            format!(" target={} ", target.path),
+        ];
+        // Pad so a field at the end of the line matches like one in the middle
+        let padded = format!("{} ", line);
+        patterns.iter().any(|p| padded.contains(p.as_str()))
+    }
+
+    /// Lines without a recognizable level are kept only when no level is set
+    fn matches_level(&self, level: Option<&str>) -> bool {
+        let Some(min) = self.level else {
+            return true;
+        };
+        match level.and_then(|l| l.parse::<Level>().ok()) {
+            // More verbose levels compare greater
+            Some(level) => level <= min,
+            None => false,
+        }
+    }
+
+    fn matches_time(&self, time: Option<&str>) -> bool {
+        let Some(since) = self.since else {
+            return true;
+        };
+        match time.and_then(|t| DateTime::parse_from_rfc3339(t).ok()) {
+            Some(time) => time >= since,
+            None => false,
+        }
+    }
+}
+
+/// Print matching lines from a log file and its rotated predecessors, oldest first
+pub fn print_logs(base_path: &Path, filter: &LogFilter, out: &mut impl Write) -> io::Result<()> {
+    for file in log_file_set(base_path) {
+        let reader = match open_log_file(&file) {
+            Ok(reader) => reader,
+            // Rotated away between listing and opening
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        for line in reader.split(b'\n') {
+            let line = String::from_utf8_lossy(&line?).into_owned();
+            if filter.matches(&line) {
+                writeln!(out, "{}", line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print matching lines as they are appended, following rotation. Runs until
+/// interrupted or writing fails.
+pub fn follow_log(base_path: &Path, filter: &LogFilter, out: &mut impl Write) -> io::Result<()> {
+    let mut file = File::open(base_path)?;
+    let mut inode = file.metadata()?.ino();
+    let mut position = file.seek(SeekFrom::End(0))?;
+    let mut pending = Vec::new();
+
+    loop {
+        thread::sleep(FOLLOW_INTERVAL);
+
+        let Ok(metadata) = fs::metadata(base_path) else {
+            continue; // Between rotation and the new file being created
+        };
+        if metadata.ino() != inode || metadata.len() < position {
+            file = File::open(base_path)?;
+            inode = file.metadata()?.ino();
+            position = 0;
+            pending.clear();
+        }
+
+        let mut chunk = Vec::new();
+        file.seek(SeekFrom::Start(position))?;
+        position += file.read_to_end(&mut chunk)? as u64;
+        pending.extend_from_slice(&chunk);
+
+        // Only complete lines are printed; a partial write waits for the next tick
+        while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]);
+            if filter.matches(&line) {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        out.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tempfile::TempDir;
+
+    const TEXT: &str = "2026-01-21T10:00:00.000000Z  WARN target{target_name=notes}: gsd::snapshot: Failed to commit target=/home/u/notes";
+    const JSON: &str = r#"{"timestamp":"2026-01-21T12:00:00.000000Z","level":"INFO","fields":{"message":"Committing changes"},"target":"gsd::snapshot","span":{"target_name":"notes","name":"target"}}"#;
+
+    fn notes() -> Option<TargetFilter> {
+        Some(TargetFilter {
+            name: "notes".to_string(),
+            path: "/home/u/notes".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_filter_text_and_json_lines() {
+        let warn = LogFilter {
+            level: Some(Level::WARN),
+            ..Default::default()
+        };
+        assert!(warn.matches(TEXT));
+        assert!(!warn.matches(JSON));
+
+        let since = LogFilter {
+            since: Some("2026-01-21T11:00:00Z".parse().unwrap()),
+            ..Default::default()
+        };
+        assert!(!since.matches(TEXT));
+        assert!(since.matches(JSON));
+
+        let target = LogFilter {
+            target: notes(),
+            ..Default::default()
+        };
+        assert!(target.matches(TEXT));
+        assert!(target.matches(JSON));
+        assert!(target.matches(
+            "2026-01-21T10:00:00.000000Z  INFO target{target_name=notes target_path=/home/u/notes}: gsd::snapshot: Committing changes"
+        ));
+        assert!(!target
+            .matches("2026-01-21T10:00:00.000000Z  INFO gsd::snapshot: Added target target=other"));
+    }
+
+    #[test]
+    fn test_print_logs_reads_rotated_files_in_order() {
+        let temp = TempDir::new().unwrap();
+        let base = temp.path().join("gsd.log");
+
+        let mut gz = GzEncoder::new(
+            File::create(temp.path().join("gsd.log.2.gz")).unwrap(),
+            Compression::default(),
+        );
+        gz.write_all(format!("{}\n", TEXT).as_bytes()).unwrap();
+        gz.finish().unwrap();
+        thread::sleep(Duration::from_millis(10));
+        fs::write(temp.path().join("gsd.log.1"), format!("{}\n", JSON)).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        fs::write(&base, "2026-01-21T13:00:00.000000Z DEBUG gsd: noise\n").unwrap();
+
+        let filter = LogFilter {
+            level: Some(Level::INFO),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        print_logs(&base, &filter, &mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines, vec![TEXT, JSON]);
+    }
+}
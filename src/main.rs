@@ -1,15 +1,36 @@
+mod audit;
+mod checkpoint;
 mod config;
+mod control;
+mod diff;
+mod events;
+mod filter;
 mod git;
+mod history;
+mod hooks;
 mod logging;
+mod message;
+mod metrics;
+mod schedule;
+mod search;
+mod shell;
 mod snapshot;
+mod sqlite;
+mod throttle;
 
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
+use chrono::{DateTime, Local, Utc};
 use clap::{Parser, Subcommand};
 use tracing::{error, info};
 
-use config::{Config, ConfigError, ConfigPathKind, TargetConfig, DEFAULT_INTERVAL_SECONDS};
+use audit::{AuditAction, AuditEntry};
+use config::{
+    AuditConfig, Config, ConfigError, ConfigPathKind, TargetConfig, DEFAULT_INTERVAL_SECONDS,
+};
+use control::ControlRequest;
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use ignore::Match;
 use logging::LoggingSettings;
@@ -47,6 +68,10 @@ enum Command {
         #[arg(short, long)]
         interval: Option<u64>,
 
+        /// Commit consistent copies of SQLite databases (via online backup)
+        #[arg(long)]
+        sqlite: bool,
+
         /// Skip confirmation prompt
         #[arg(short = 'y', long)]
         yes: bool,
@@ -84,6 +109,162 @@ enum Command {
         message: Option<String>,
     },
 
+    /// Snapshot a directory if needed and name the snapshot
+    Checkpoint {
+        /// Checkpoint name, e.g. "before refactor"
+        name: String,
+
+        /// Directory path (defaults to current directory)
+        #[arg(short = 'C', long)]
+        path: Option<PathBuf>,
+
+        /// Description stored with the checkpoint (defaults to the name)
+        #[arg(short, long)]
+        message: Option<String>,
+
+        /// Move an existing checkpoint with this name
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// List a directory's checkpoints
+    Checkpoints {
+        /// Directory path (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Print JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show changes between snapshots, checkpoints, times or the working tree
+    Diff {
+        /// Directory path (defaults to current directory)
+        #[arg(short = 'C', long)]
+        path: Option<PathBuf>,
+
+        /// Snapshot, checkpoint or time to compare from (defaults to the last snapshot)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Snapshot, checkpoint or time to compare to (defaults to the working tree)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Show a diffstat instead of the patch
+        #[arg(long, conflicts_with_all = ["name_only", "json"])]
+        stat: bool,
+
+        /// Show only the names of changed files
+        #[arg(long, conflicts_with = "json")]
+        name_only: bool,
+
+        /// Show line diffs for markdown, text and data files too
+        #[arg(long, conflicts_with_all = ["stat", "name_only", "json"])]
+        lines: bool,
+
+        /// Print changed files with line counts as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Limit the diff to these paths, relative to the directory
+        paths: Vec<String>,
+    },
+
+    /// List the snapshots in which a file changed, following renames
+    History {
+        /// File path
+        file: PathBuf,
+
+        /// Directory the file is snapshotted in (defaults to the nearest
+        /// directory with a .gsd above the file)
+        #[arg(short = 'C', long)]
+        path: Option<PathBuf>,
+
+        /// Only snapshots newer than this: a duration ago (30m, 2h, 7d) or a date/time
+        #[arg(long, value_parser = history::parse_time)]
+        since: Option<DateTime<Utc>>,
+
+        /// Print one JSON object per version
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a file as it was in a snapshot
+    Cat {
+        /// File path
+        file: PathBuf,
+
+        /// Snapshot, checkpoint or time (defaults to the last snapshot)
+        #[arg(long)]
+        at: Option<String>,
+
+        /// Directory the file is snapshotted in (defaults to the nearest
+        /// directory with a .gsd above the file)
+        #[arg(short = 'C', long)]
+        path: Option<PathBuf>,
+    },
+
+    /// List files deleted from a directory that are still in its snapshots
+    Deleted {
+        /// Directory path (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Only deletions newer than this: a duration ago (30m, 2h, 7d) or a date/time
+        #[arg(long, value_parser = history::parse_time)]
+        since: Option<DateTime<Utc>>,
+
+        /// Print one JSON object per file
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Restore a deleted file or directory from the last snapshot that had it
+    Undelete {
+        /// File or directory path
+        file: PathBuf,
+
+        /// Directory the file is snapshotted in (defaults to the nearest
+        /// directory with a .gsd above the file)
+        #[arg(short = 'C', long)]
+        path: Option<PathBuf>,
+    },
+
+    /// Search snapshot history for text
+    Search {
+        /// Text to search for
+        pattern: String,
+
+        /// Directory path (defaults to current directory)
+        #[arg(short = 'C', long, conflicts_with = "all_targets")]
+        path: Option<PathBuf>,
+
+        /// Search every configured target
+        #[arg(long)]
+        all_targets: bool,
+
+        /// Treat the pattern as a POSIX extended regular expression (git's dialect)
+        #[arg(short = 'E', long)]
+        regex: bool,
+
+        /// List every match in every snapshot, instead of the snapshots
+        /// where the text appeared or was removed
+        #[arg(long)]
+        each_snapshot: bool,
+
+        /// Only snapshots newer than this: a duration ago (30m, 2h, 7d) or a date/time
+        #[arg(long, value_parser = history::parse_time)]
+        since: Option<DateTime<Utc>>,
+
+        /// Only files matching this glob (e.g. '*.md' or 'skills/**')
+        #[arg(long)]
+        file_glob: Option<String>,
+
+        /// Print one JSON object per match
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Run git commands against the .gsd repository
     #[command(trailing_var_arg = true)]
     Git {
@@ -105,11 +286,97 @@ enum Command {
     /// Check target directories
     Check,
 
+    /// Show daemon logs, including rotated files
+    Logs {
+        /// Keep printing new lines as they are written
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Only events for this target directory
+        #[arg(long)]
+        target: Option<PathBuf>,
+
+        /// Least severe level to show (error, warn, info, debug, trace)
+        #[arg(long, value_parser = parse_log_level)]
+        level: Option<tracing::Level>,
+
+        /// Only lines newer than this: a duration ago (30m, 2h, 7d) or a date/time
+        #[arg(long, value_parser = history::parse_time)]
+        since: Option<DateTime<Utc>>,
+    },
+
+    /// Show the audit journal of a directory
+    Audit {
+        /// Directory path (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Only entries newer than this: a duration ago (30m, 2h, 7d) or a date/time
+        #[arg(long, value_parser = history::parse_time)]
+        since: Option<DateTime<Utc>>,
+
+        /// Print raw JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the running daemon's targets and their next scheduled snapshot
+    Status,
+
+    /// Temporarily change the running daemon's log level
+    LogLevel {
+        /// Level to log at (error, warn, info, debug, trace)
+        level: String,
+
+        /// Only for this target directory (defaults to all targets)
+        #[arg(long)]
+        target: Option<PathBuf>,
+
+        /// How long before the configured level is restored (at most 7 days)
+        #[arg(long, default_value_t = 15, value_parser = parse_boost_minutes)]
+        minutes: u64,
+    },
+
     /// Configuration management
     Config {
         #[command(subcommand)]
         command: ConfigCommand,
     },
+
+    /// Git filter drivers (invoked by git)
+    #[command(hide = true)]
+    Filter {
+        #[command(subcommand)]
+        command: FilterCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum FilterCommand {
+    /// Replace an SQLite database with a consistent backup
+    SqliteClean {
+        /// Content filter list as JSON, for files that are not databases
+        #[arg(long)]
+        spec: Option<String>,
+
+        /// Path of the file being staged, relative to the target
+        path: PathBuf,
+    },
+
+    /// Remove stale SQLite sidecar files before a restore
+    SqliteSmudge {
+        /// Path of the file being checked out, relative to the target
+        path: PathBuf,
+    },
+
+    /// Apply configured content filters
+    Content {
+        /// Filter list as JSON
+        #[arg(long)]
+        spec: String,
+
+        /// Path of the file being staged, relative to the target
+        path: PathBuf,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -141,6 +408,21 @@ enum CliError {
     #[error(transparent)]
     Git(#[from] git::GitError),
 
+    #[error(transparent)]
+    Audit(#[from] audit::AuditError),
+
+    #[error(transparent)]
+    Control(#[from] control::ControlError),
+
+    #[error(transparent)]
+    Checkpoint(#[from] checkpoint::CheckpointError),
+
+    #[error(transparent)]
+    History(#[from] history::HistoryError),
+
+    #[error(transparent)]
+    Search(#[from] search::SearchError),
+
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -163,23 +445,104 @@ fn run(cli: Cli) -> Result<ExitCode, CliError> {
         Command::Add {
             path,
             interval,
+            sqlite,
             yes,
-        } => add_target(path, interval, yes, cli.config.as_deref()),
+        } => add_target(path, interval, sqlite, yes, cli.config.as_deref()),
         Command::Remove { path, yes } => remove_target(path, yes, cli.config.as_deref()),
         Command::Enable { path } => set_target_enabled(path, true, cli.config.as_deref()),
         Command::Disable { path } => set_target_enabled(path, false, cli.config.as_deref()),
-        Command::Snapshot { path, message } => take_snapshot(path, message),
-        Command::Git { path, args } => run_git_command(path, args),
+        Command::Snapshot { path, message } => take_snapshot(path, message, cli.config.as_deref()),
+        Command::Checkpoint {
+            name,
+            path,
+            message,
+            force,
+        } => create_checkpoint(name, path, message, force, cli.config.as_deref()),
+        Command::Checkpoints { path, json } => list_checkpoints(path, json, cli.config.as_deref()),
+        Command::Diff {
+            path,
+            from,
+            to,
+            stat,
+            name_only,
+            lines,
+            json,
+            paths,
+        } => {
+            let mode = if stat {
+                diff::DiffMode::Stat
+            } else if name_only {
+                diff::DiffMode::NameOnly
+            } else if lines {
+                diff::DiffMode::Lines
+            } else {
+                diff::DiffMode::Patch
+            };
+            show_diff(path, from, to, paths, mode, json, cli.config.as_deref())
+        }
+        Command::History {
+            file,
+            path,
+            since,
+            json,
+        } => show_file_history(file, path, since, json, cli.config.as_deref()),
+        Command::Cat { file, at, path } => cat_file(file, at, path, cli.config.as_deref()),
+        Command::Deleted { path, since, json } => {
+            list_deleted(path, since, json, cli.config.as_deref())
+        }
+        Command::Undelete { file, path } => undelete_file(file, path, cli.config.as_deref()),
+        Command::Search {
+            pattern,
+            path,
+            all_targets,
+            regex,
+            each_snapshot,
+            since,
+            file_glob,
+            json,
+        } => {
+            let options = search::SearchOptions {
+                pattern,
+                regex,
+                since,
+                file_glob,
+            };
+            search_targets(
+                path,
+                all_targets,
+                &options,
+                each_snapshot,
+                json,
+                cli.config.as_deref(),
+            )
+        }
+        Command::Git { path, args } => run_git_command(path, args, cli.config.as_deref()),
         Command::Preview { path } => {
             let path = resolve_target_path(path)?;
             preview_path(&path, cli.config.as_deref())
         }
         Command::Check => check_targets(cli.config.as_deref()),
+        Command::Logs {
+            follow,
+            target,
+            level,
+            since,
+        } => show_logs(follow, target, level, since, cli.config.as_deref()),
+        Command::Audit { path, since, json } => {
+            show_audit(path, since, json, cli.config.as_deref())
+        }
+        Command::Status => show_status(cli.config.as_deref()),
+        Command::LogLevel {
+            level,
+            target,
+            minutes,
+        } => set_log_level(level, target, minutes, cli.config.as_deref()),
         Command::Config { command } => match command {
             ConfigCommand::Validate => validate_config(cli.config.as_deref()),
             ConfigCommand::Init { path } => init_config(path, cli.config.as_deref()),
             ConfigCommand::Path => show_config_path(cli.config.as_deref()),
         },
+        Command::Filter { command } => run_filter(command),
     }
 }
 
@@ -194,6 +557,39 @@ fn resolve_target_path(path: Option<PathBuf>) -> Result<PathBuf, CliError> {
     })
 }
 
+fn parse_log_level(value: &str) -> Result<tracing::Level, String> {
+    logging::parse_level(value).map_err(|e| e.to_string())
+}
+
+fn parse_boost_minutes(value: &str) -> Result<u64, String> {
+    let minutes: u64 = value.parse().map_err(|e| format!("{}", e))?;
+    if minutes == 0 || minutes > logging::MAX_BOOST_MINUTES {
+        return Err(format!(
+            "must be between 1 and {} (7 days)",
+            logging::MAX_BOOST_MINUTES
+        ));
+    }
+    Ok(minutes)
+}
+
+/// Audit settings from the config file, or defaults if it can't be loaded
+fn audit_config(config_path: Option<&Path>) -> AuditConfig {
+    match load_config_or_default(config_path) {
+        Ok(config) => config.audit,
+        Err(e) => {
+            eprintln!("Warning: using default audit settings: {}", e);
+            AuditConfig::default()
+        }
+    }
+}
+
+/// Record an audit entry from the CLI; failures are reported but not fatal
+fn record_audit(config: &AuditConfig, entry: AuditEntry) {
+    if let Err(e) = audit::record(config, &entry) {
+        eprintln!("Warning: failed to write audit journal: {}", e);
+    }
+}
+
 /// Prompt for confirmation
 fn confirm(prompt: &str) -> bool {
     use std::io::{self, Write};
@@ -222,6 +618,7 @@ fn prompt_with_default(prompt: &str, default: u64) -> u64 {
 fn add_target(
     path: Option<PathBuf>,
     interval: Option<u64>,
+    sqlite: bool,
     yes: bool,
     config_path: Option<&Path>,
 ) -> Result<ExitCode, CliError> {
@@ -229,6 +626,7 @@ fn add_target(
 
     // Load or create config
     let (mut config, config_file) = Config::load_or_create(config_path)?;
+    git::configure(&config.git);
 
     // Check if already exists
     if config.find_target(&path).is_some() {
@@ -255,6 +653,9 @@ fn add_target(
     // Confirm
     if !yes {
         println!("  interval: {}s", interval);
+        if sqlite {
+            println!("  sqlite snapshots: enabled");
+        }
         if !confirm("Proceed?") {
             println!("Cancelled.");
             return Ok(ExitCode::SUCCESS);
@@ -267,23 +668,22 @@ fn add_target(
         .build()
         .map_err(|e| CliError::Io(std::io::Error::other(e)))?;
 
+    let mut target = TargetConfig::new(path.clone(), interval);
+    target.sqlite_snapshots = sqlite;
+    let filters = filter::target_drivers(&target)?;
+
     runtime.block_on(async {
         git::ensure_repo_initialized(
             &path,
             &config.git.author_name,
             &config.git.author_email,
             &config.git.default_ignore_patterns,
+            &filters,
         )
         .await
     })?;
 
     // Add to config
-    let target = TargetConfig {
-        path: path.clone(),
-        interval_seconds: interval,
-        ignore_patterns: Vec::new(),
-        enabled: true,
-    };
     config.add_target(target)?;
     config.save(&config_file)?;
 
@@ -318,6 +718,7 @@ fn remove_target(
         let delete_gsd = yes || confirm("Delete .gsd directory (snapshot history)?");
         if delete_gsd {
             std::fs::remove_dir_all(&gsd_dir)?;
+            record_audit(&config.audit, AuditEntry::new(&path, AuditAction::Purge));
             println!("Deleted: {}", gsd_dir.display());
         } else {
             println!("Kept: {}", gsd_dir.display());
@@ -377,64 +778,795 @@ fn set_target_enabled(
     Ok(ExitCode::SUCCESS)
 }
 
-fn take_snapshot(path: Option<PathBuf>, message: Option<String>) -> Result<ExitCode, CliError> {
+fn take_snapshot(
+    path: Option<PathBuf>,
+    message: Option<String>,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
     let path = resolve_target_path(path)?;
+    // Optional here: manual snapshots work without a config file
+    let config = load_config_or_default(config_path)?;
+    git::configure(&config.git);
 
-    // Check if .gsd exists
+    if !has_snapshot_repo(&path) {
+        return Ok(ExitCode::from(1));
+    }
+
+    let runtime = cli_runtime()?;
+    runtime.block_on(async {
+        match snapshot_changes(&path, &config, message).await? {
+            Some((_, changed_files)) => {
+                println!("Snapshot created: {} file(s)", changed_files.len());
+                for f in &changed_files {
+                    println!("  {}", f);
+                }
+            }
+            None => println!("No changes to snapshot."),
+        }
+        Ok(ExitCode::SUCCESS)
+    })
+}
+
+/// An RFC 3339 time in local time, as printed in listings
+fn local_time(time: &str) -> String {
+    DateTime::parse_from_rfc3339(time)
+        .map(|t| {
+            t.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|_| time.to_string())
+}
+
+/// Check that `path` has a `.gsd` repo, explaining how to create one if not
+fn has_snapshot_repo(path: &Path) -> bool {
     let gsd_dir = path.join(git::GSD_DIR);
     if !gsd_dir.exists() {
         eprintln!(
             "Error: No .gsd directory found in {}. Run 'gsd add' first.",
             path.display()
         );
-        return Ok(ExitCode::from(1));
+        return false;
     }
+    true
+}
 
-    let runtime = tokio::runtime::Builder::new_current_thread()
+fn cli_runtime() -> Result<tokio::runtime::Runtime, CliError> {
+    tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
-        .map_err(|e| CliError::Io(std::io::Error::other(e)))?;
+        .map_err(|e| CliError::Io(std::io::Error::other(e)))
+}
+
+/// Commit pending changes as a manual snapshot, returning the commit and
+/// changed files, or `None` if nothing changed
+async fn snapshot_changes(
+    path: &Path,
+    config: &Config,
+    message: Option<String>,
+) -> Result<Option<(String, Vec<String>)>, CliError> {
+    let started = std::time::Instant::now();
+
+    // Pick up database writes that only reached the WAL
+    sqlite::refresh_databases(path, &mut sqlite::RefreshState::default()).await?;
+
+    // Stage everything so clean filters decide what counts as a change,
+    // keeping the file list for the auto-message
+    git::stage_all(path).await?;
+    let entries = git::list_changed_files(path).await?;
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    let changed_files = git::display_paths(&entries);
+
+    // Generate or use provided message
+    let commit_message = match message {
+        Some(message) => message,
+        None => {
+            let target = config
+                .targets
+                .iter()
+                .find(|t| t.path == path)
+                .cloned()
+                .unwrap_or_else(|| TargetConfig::new(path.to_path_buf(), 0));
+            message::generate(&config.message, &target, &entries).await?
+        }
+    };
+
+    // Commit
+    let commit = git::commit_all(path, &commit_message).await?;
+    record_audit(
+        &config.audit,
+        AuditEntry::new(
+            path,
+            AuditAction::Snapshot {
+                commit: commit.clone(),
+                files: changed_files.clone(),
+                trigger: snapshot::SnapshotTrigger::Manual,
+                duration_ms: started.elapsed().as_millis() as u64,
+            },
+        ),
+    );
+
+    Ok(Some((commit, changed_files)))
+}
+
+fn create_checkpoint(
+    name: String,
+    path: Option<PathBuf>,
+    message: Option<String>,
+    force: bool,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let path = resolve_target_path(path)?;
+    let config = load_config_or_default(config_path)?;
+    git::configure(&config.git);
 
+    if !has_snapshot_repo(&path) {
+        return Ok(ExitCode::from(1));
+    }
+    // Fail on a bad name before committing anything
+    checkpoint::normalize_name(&name)?;
+
+    let runtime = cli_runtime()?;
     runtime.block_on(async {
-        // Check for changes
-        let has_changes = git::has_changes(&path).await?;
-        if !has_changes {
-            println!("No changes to snapshot.");
-            return Ok(ExitCode::SUCCESS);
+        let description = message.as_deref().unwrap_or(&name).to_string();
+        if let Some((_, changed_files)) =
+            snapshot_changes(&path, &config, Some(description)).await?
+        {
+            println!("Snapshot created: {} file(s)", changed_files.len());
         }
 
-        // Get changed files for auto-message
-        let changed_files = git::list_changed_files(&path).await?;
+        let created = checkpoint::create(&path, &name, message.as_deref(), force).await?;
+        println!(
+            "Checkpoint {} at {}",
+            created.name,
+            &created.commit[..created.commit.len().min(10)]
+        );
+        Ok(ExitCode::SUCCESS)
+    })
+}
 
-        // Generate or use provided message
-        let commit_message = message.unwrap_or_else(|| {
-            let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
-            if changed_files.len() <= 3 {
-                format!("Snapshot {}: {}", timestamp, changed_files.join(", "))
-            } else {
-                format!(
-                    "Snapshot {}: {} and {} more",
-                    timestamp,
-                    changed_files[..2].join(", "),
-                    changed_files.len() - 2
-                )
-            }
-        });
+fn list_checkpoints(
+    path: Option<PathBuf>,
+    json: bool,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let path = resolve_target_path(path)?;
+    let config = load_config_or_default(config_path)?;
+    git::configure(&config.git);
+
+    if !has_snapshot_repo(&path) {
+        return Ok(ExitCode::from(1));
+    }
+
+    let runtime = cli_runtime()?;
+    let checkpoints = runtime.block_on(checkpoint::list(&path))?;
+    if checkpoints.is_empty() && !json {
+        println!("No checkpoints in {}", path.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for checkpoint in &checkpoints {
+        if json {
+            println!("{}", serde_json::to_string(checkpoint).unwrap_or_default());
+            continue;
+        }
+        let time = local_time(&checkpoint.time);
+        let message = if checkpoint.message == checkpoint.name {
+            ""
+        } else {
+            &checkpoint.message
+        };
+        println!(
+            "{}  {}  {:<24}  {}",
+            time,
+            &checkpoint.commit[..checkpoint.commit.len().min(10)],
+            checkpoint.name,
+            message
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn show_logs(
+    follow: bool,
+    target: Option<PathBuf>,
+    level: Option<tracing::Level>,
+    since: Option<DateTime<Utc>>,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let (config, _) = load_config_with_path(config_path)?;
+    let Some(directory) = &config.logging.directory else {
+        eprintln!("Error: logging.directory is not set; the daemon only logs to the console.");
+        return Ok(ExitCode::from(1));
+    };
+
+    let mut filter = logging::LogFilter {
+        level,
+        since,
+        target: None,
+    };
+
+    let mut log_path = directory.join(logging::LOG_FILE_NAME);
+    if let Some(target) = target {
+        let path = resolve_target_path(Some(target))?;
+        let name = config
+            .find_target(&path)
+            .map(|t| t.name().to_string())
+            .or_else(|| path.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_default();
+
+        // A target's own file holds only its events, so no target matching is needed
+        let own_file = logging::target_log_path(&directory.join(logging::TARGET_LOG_DIR), &path);
+        if config.logging.per_target_files && own_file.exists() {
+            log_path = own_file;
+        } else {
+            filter.target = Some(logging::TargetFilter {
+                name,
+                path: path.to_string_lossy().to_string(),
+            });
+        }
+    }
+
+    if !log_path.exists() {
+        eprintln!("Error: log file not found: {}", log_path.display());
+        return Ok(ExitCode::from(1));
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    let result = logging::print_logs(&log_path, &filter, &mut stdout).and_then(|()| {
+        if follow {
+            logging::follow_log(&log_path, &filter, &mut stdout)
+        } else {
+            Ok(())
+        }
+    });
+
+    match result {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        // The reader went away (e.g. piped into head)
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(ExitCode::SUCCESS),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn show_audit(
+    path: Option<PathBuf>,
+    since: Option<DateTime<Utc>>,
+    json: bool,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let path = resolve_target_path(path)?;
+    let config = audit_config(config_path);
+
+    let entries = audit::read_entries(&config, &path, since)?;
+    if entries.is_empty() && !json {
+        println!("No audit entries for {}", path.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for entry in &entries {
+        if json {
+            println!("{}", serde_json::to_string(entry).unwrap_or_default());
+            continue;
+        }
 
-        // Commit
-        git::commit_all(&path, &commit_message).await?;
+        let time = entry
+            .timestamp()
+            .map(|t| {
+                t.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| entry.time.clone());
+        match &entry.action {
+            AuditAction::Snapshot {
+                commit,
+                files,
+                trigger,
+                duration_ms,
+            } => println!(
+                "{}  snapshot  {}  {:<8}  {} file(s)  {}ms  {}",
+                time,
+                &commit[..commit.len().min(10)],
+                trigger.as_str(),
+                files.len(),
+                duration_ms,
+                entry.user
+            ),
+            AuditAction::Purge => println!("{}  purge     {}", time, entry.user),
+            AuditAction::Restore { files } => println!(
+                "{}  restore   {} file(s)  {}",
+                time,
+                files.len(),
+                entry.user
+            ),
+            AuditAction::Git {
+                args, exit_code, ..
+            } => println!(
+                "{}  git       {}  exit {}  {}",
+                time,
+                args.join(" "),
+                exit_code.map_or_else(|| "signal".to_string(), |c| c.to_string()),
+                entry.user
+            ),
+        }
+    }
 
-        println!("Snapshot created: {} file(s)", changed_files.len());
-        for f in &changed_files {
-            println!("  {}", f);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn show_status(config_path: Option<&Path>) -> Result<ExitCode, CliError> {
+    let config = load_config(config_path)?;
+    let socket = config
+        .control
+        .socket_path()
+        .ok_or(control::ControlError::NoSocket)?;
+
+    let response = match control::send(&socket, &ControlRequest::Status) {
+        Ok(response) => response,
+        Err(control::ControlError::NotRunning(_)) => {
+            println!("Daemon: not running");
+            for target in config.targets.iter().filter(|t| t.enabled) {
+                let schedule = schedule::Schedule::from_target(target)
+                    .map(|s| s.describe())
+                    .unwrap_or_else(|e| e.to_string());
+                println!(
+                    "{}: {} - {}",
+                    target.name(),
+                    target.path.display(),
+                    schedule
+                );
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    println!("Daemon: running ({})", response.message);
+    for target in &response.targets {
+        let next = target
+            .next_run
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| {
+                t.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "none".to_string());
+        println!(
+            "{}: {} - {}, next run {}, {}",
+            target.name,
+            target.path.display(),
+            target.schedule,
+            next,
+            target.health
+        );
+        if let Some(error) = &target.last_error {
+            println!(
+                "  {} consecutive failure(s), {} timed out; last: {}",
+                target.consecutive_failures, target.timeouts, error
+            );
         }
+    }
+    Ok(ExitCode::SUCCESS)
+}
 
+fn set_log_level(
+    level: String,
+    target: Option<PathBuf>,
+    minutes: u64,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let target = target
+        .map(|path| resolve_target_path(Some(path)))
+        .transpose()?;
+    let socket = Config::load_from_sources(config_path)
+        .map(|config| config.control)
+        .unwrap_or_default()
+        .socket_path()
+        .ok_or(control::ControlError::NoSocket)?;
+
+    let response = control::send(
+        &socket,
+        &ControlRequest::LogLevel {
+            target,
+            level,
+            minutes,
+        },
+    )?;
+    if response.ok {
+        println!("{}", response.message);
         Ok(ExitCode::SUCCESS)
+    } else {
+        eprintln!("Error: {}", response.message);
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+fn show_diff(
+    path: Option<PathBuf>,
+    from: Option<String>,
+    to: Option<String>,
+    paths: Vec<String>,
+    mode: diff::DiffMode,
+    json: bool,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let path = resolve_target_path(path)?;
+    let config = load_config_or_default(config_path)?;
+    git::configure(&config.git);
+
+    if !has_snapshot_repo(&path) {
+        return Ok(ExitCode::from(1));
+    }
+
+    let runtime = cli_runtime()?;
+    runtime.block_on(async {
+        let from = history::resolve(&path, from.as_deref().unwrap_or("HEAD")).await?;
+        let to = match &to {
+            Some(to) => Some(history::resolve(&path, to).await?),
+            None => None,
+        };
+
+        let print_diff = async {
+            if json {
+                let summary = diff::summarize(&path, &from, to.as_deref(), &paths).await?;
+                println!("{}", serde_json::to_string(&summary).unwrap_or_default());
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let color = std::io::IsTerminal::is_terminal(&std::io::stdout());
+            let output = diff::text(&path, &from, to.as_deref(), &paths, mode, color).await?;
+            print!("{}", output);
+            Ok(ExitCode::SUCCESS)
+        };
+        match to {
+            Some(_) => print_diff.await,
+            None => diff::with_working_tree(&path, print_diff).await,
+        }
     })
 }
 
-fn run_git_command(path: Option<PathBuf>, args: Vec<String>) -> Result<ExitCode, CliError> {
+/// The snapshotted directory containing `file`, and the file's path
+/// relative to it. Without `-C`, the nearest directory above the file with a
+/// `.gsd` is used; the file itself need not exist any more.
+fn locate_file(file: &Path, path: Option<PathBuf>) -> Result<(PathBuf, String), CliError> {
+    let (target, relative) = locate_path(file, path)?;
+    if relative.is_empty() {
+        return Err(not_found(format!("'{}' is not a file", file.display())));
+    }
+    Ok((target, relative))
+}
+
+fn not_found(message: String) -> CliError {
+    CliError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, message))
+}
+
+/// Like `locate_file`, but `path` may also name the target itself, which
+/// gives an empty relative path
+fn locate_path(file: &Path, path: Option<PathBuf>) -> Result<(PathBuf, String), CliError> {
+    let (target, absolute) = match path {
+        Some(path) => {
+            let target = resolve_target_path(Some(path))?;
+            let absolute = target.join(file);
+            (target, absolute)
+        }
+        None => {
+            // Resolve `.` and `..` first, so `.` in a target finds that target
+            let mut absolute = PathBuf::new();
+            for component in std::env::current_dir()?.join(file).components() {
+                match component {
+                    std::path::Component::ParentDir => {
+                        absolute.pop();
+                    }
+                    std::path::Component::CurDir => {}
+                    other => absolute.push(other),
+                }
+            }
+            let target = absolute
+                .ancestors()
+                .find(|dir| dir.join(git::GSD_DIR).is_dir())
+                .ok_or_else(|| {
+                    not_found(format!(
+                        "no .gsd directory found above '{}'. Run 'gsd add' first.",
+                        file.display()
+                    ))
+                })?
+                .to_path_buf();
+            (target, absolute)
+        }
+    };
+
+    // Normalize `.` and `..` without requiring the file to exist
+    let mut relative = PathBuf::new();
+    for component in absolute
+        .strip_prefix(&target)
+        .unwrap_or(&absolute)
+        .components()
+    {
+        match component {
+            std::path::Component::Normal(part) => relative.push(part),
+            std::path::Component::ParentDir if !relative.pop() => {
+                return Err(not_found(format!(
+                    "'{}' is outside {}",
+                    file.display(),
+                    target.display()
+                )));
+            }
+            _ => {}
+        }
+    }
+    Ok((target, relative.to_string_lossy().into_owned()))
+}
+
+fn show_file_history(
+    file: PathBuf,
+    path: Option<PathBuf>,
+    since: Option<DateTime<Utc>>,
+    json: bool,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let (path, file) = locate_file(&file, path)?;
+    let config = load_config_or_default(config_path)?;
+    git::configure(&config.git);
+
+    if !has_snapshot_repo(&path) {
+        return Ok(ExitCode::from(1));
+    }
+
+    let runtime = cli_runtime()?;
+    let versions = runtime.block_on(history::file_history(&path, &file, since))?;
+    if versions.is_empty() && !json {
+        println!("No snapshots of {}", file);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for version in &versions {
+        if json {
+            println!("{}", serde_json::to_string(version).unwrap_or_default());
+            continue;
+        }
+        let time = local_time(&version.time);
+        let lines = match (version.insertions, version.deletions) {
+            (Some(insertions), Some(deletions)) => format!("+{} -{}", insertions, deletions),
+            _ => "binary".to_string(),
+        };
+        let size = version
+            .size
+            .map(|size| format!("{} B", size))
+            .unwrap_or_else(|| "-".to_string());
+        let change = match &version.from {
+            Some(from) => format!("{} from {}", version.change, from),
+            None => version.change.to_string(),
+        };
+        println!(
+            "{}  {}  {:>12}  {:>10}  {}  {}",
+            time,
+            &version.commit[..version.commit.len().min(10)],
+            lines,
+            size,
+            version.path,
+            change
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn cat_file(
+    file: PathBuf,
+    at: Option<String>,
+    path: Option<PathBuf>,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let (path, file) = locate_file(&file, path)?;
+    let config = load_config_or_default(config_path)?;
+    git::configure(&config.git);
+
+    if !has_snapshot_repo(&path) {
+        return Ok(ExitCode::from(1));
+    }
+
+    let reference = at.unwrap_or_else(|| "HEAD".to_string());
+    let runtime = cli_runtime()?;
+    let (_, contents) = runtime.block_on(async {
+        let commit = history::resolve(&path, &reference).await?;
+        history::file_at(&path, &file, &commit, &reference).await
+    })?;
+
+    match std::io::stdout().write_all(&contents) {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(ExitCode::SUCCESS),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn list_deleted(
+    path: Option<PathBuf>,
+    since: Option<DateTime<Utc>>,
+    json: bool,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let path = resolve_target_path(path)?;
+    let config = load_config_or_default(config_path)?;
+    git::configure(&config.git);
+
+    if !has_snapshot_repo(&path) {
+        return Ok(ExitCode::from(1));
+    }
+
+    let runtime = cli_runtime()?;
+    let files = runtime.block_on(history::deleted_files(&path, since))?;
+    if files.is_empty() && !json {
+        println!("No deleted files in {}", path.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for file in &files {
+        if json {
+            println!("{}", serde_json::to_string(file).unwrap_or_default());
+            continue;
+        }
+        let commit = file.commit.as_deref().unwrap_or("unrecorded");
+        println!(
+            "{}  {:<10}  {}",
+            local_time(&file.time),
+            &commit[..commit.len().min(10)],
+            file.path
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn undelete_file(
+    file: PathBuf,
+    path: Option<PathBuf>,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let (path, file) = locate_path(&file, path)?;
+    let config = load_config_or_default(config_path)?;
+    git::configure(&config.git);
+
+    if !has_snapshot_repo(&path) {
+        return Ok(ExitCode::from(1));
+    }
+
+    let runtime = cli_runtime()?;
+    let restored = runtime.block_on(history::undelete(&path, &file))?;
+    for file in &restored {
+        match &file.commit {
+            Some(commit) => println!(
+                "Restored {} (deleted {} in {})",
+                file.path,
+                local_time(&file.time),
+                &commit[..commit.len().min(10)]
+            ),
+            None => println!(
+                "Restored {} (from the snapshot of {})",
+                file.path,
+                local_time(&file.time)
+            ),
+        }
+    }
+    record_audit(
+        &config.audit,
+        AuditEntry::new(
+            &path,
+            AuditAction::Restore {
+                files: restored.into_iter().map(|f| f.path).collect(),
+            },
+        ),
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn search_targets(
+    path: Option<PathBuf>,
+    all_targets: bool,
+    options: &search::SearchOptions,
+    each_snapshot: bool,
+    json: bool,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
+    let config = if all_targets {
+        load_config(config_path)?
+    } else {
+        load_config_or_default(config_path)?
+    };
+    git::configure(&config.git);
+    let targets = if all_targets {
+        search::searchable_targets(&config)
+    } else {
+        let path = resolve_target_path(path)?;
+        if !has_snapshot_repo(&path) {
+            return Ok(ExitCode::from(1));
+        }
+        vec![path]
+    };
+    search_history(&targets, options, each_snapshot, json)
+}
+
+fn search_history(
+    targets: &[PathBuf],
+    options: &search::SearchOptions,
+    each_snapshot: bool,
+    json: bool,
+) -> Result<ExitCode, CliError> {
+    let runtime = cli_runtime()?;
+    let mut stdout = std::io::stdout().lock();
+    let mut found = false;
+    for target in targets {
+        let matches = runtime.block_on(async {
+            if each_snapshot {
+                search::each_snapshot(target, options).await
+            } else {
+                search::changes(target, options).await
+            }
+        })?;
+        found |= !matches.is_empty();
+
+        match print_matches(&mut stdout, &matches, targets.len() > 1, json) {
+            Ok(()) => {}
+            // The reader went away (e.g. piped into head)
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => return Ok(ExitCode::SUCCESS),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    if !found && !json {
+        println!("No matches");
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_matches(
+    out: &mut impl Write,
+    matches: &[search::SearchMatch],
+    with_target: bool,
+    json: bool,
+) -> std::io::Result<()> {
+    for m in matches {
+        if json {
+            writeln!(out, "{}", serde_json::to_string(m).unwrap_or_default())?;
+            continue;
+        }
+        let path = if with_target {
+            format!("{}/{}", m.target, m.path)
+        } else {
+            m.path.clone()
+        };
+        let location = match (m.change, m.line) {
+            (Some("added"), _) => format!("+ {}", path),
+            (Some(_), _) => format!("- {}", path),
+            (None, Some(line)) => format!("{}:{}", path, line),
+            (None, None) => path,
+        };
+        writeln!(
+            out,
+            "{}  {}  {}: {}",
+            local_time(&m.time),
+            &m.commit[..m.commit.len().min(10)],
+            location,
+            m.text
+        )?;
+    }
+    Ok(())
+}
+
+fn run_git_command(
+    path: Option<PathBuf>,
+    args: Vec<String>,
+    config_path: Option<&Path>,
+) -> Result<ExitCode, CliError> {
     let path = resolve_target_path(path)?;
+    let config = load_config_or_default(config_path)?;
+    git::configure(&config.git);
 
     // Check if .gsd exists
     let gsd_dir = path.join(git::GSD_DIR);
@@ -463,8 +1595,13 @@ fn run_git_command(path: Option<PathBuf>, args: Vec<String>) -> Result<ExitCode,
         }
     }
 
-    // Run git with --git-dir and --work-tree
-    let status = std::process::Command::new("git")
+    // Run git with --git-dir and --work-tree. Your git config applies here,
+    // but hooks in .gsd/hooks only run if enabled.
+    let mut command = std::process::Command::new("git");
+    if let Some(setting) = git::hooks_override() {
+        command.arg("-c").arg(setting);
+    }
+    let status = command
         .arg("--git-dir")
         .arg(&gsd_dir)
         .arg("--work-tree")
@@ -472,9 +1609,33 @@ fn run_git_command(path: Option<PathBuf>, args: Vec<String>) -> Result<ExitCode,
         .args(&args)
         .status()?;
 
+    if let Some(subcommand) = audit::history_changing_subcommand(&args) {
+        let action = AuditAction::Git {
+            subcommand: subcommand.to_string(),
+            args: args.clone(),
+            exit_code: status.code(),
+        };
+        record_audit(&config.audit, AuditEntry::new(&path, action));
+    }
+
     Ok(ExitCode::from(status.code().unwrap_or(1) as u8))
 }
 
+fn run_filter(command: FilterCommand) -> Result<ExitCode, CliError> {
+    let stdin = std::io::stdin().lock();
+    let stdout = std::io::stdout().lock();
+
+    match command {
+        FilterCommand::SqliteClean { spec, path } => {
+            sqlite::clean(&path, spec.as_deref(), stdin, stdout)?
+        }
+        FilterCommand::SqliteSmudge { path } => sqlite::smudge(&path, stdin, stdout)?,
+        FilterCommand::Content { spec, path } => filter::clean(&spec, &path, stdin, stdout)?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
 fn show_config_path(config_path: Option<&Path>) -> Result<ExitCode, CliError> {
     let (path, kind) = Config::resolve_path(config_path);
     let exists = path.exists();
@@ -585,6 +1746,7 @@ fn init_config(path: Option<PathBuf>, config_path: Option<&Path>) -> Result<Exit
 
 fn check_targets(config_path: Option<&std::path::Path>) -> Result<ExitCode, CliError> {
     let config = load_config(config_path)?;
+    git::configure(&config.git);
 
     let runtime = tokio::runtime::Builder::new_current_thread()
         .enable_all()
@@ -949,6 +2111,23 @@ fn load_config(path: Option<&std::path::Path>) -> Result<Config, CliError> {
     load_config_with_path(path).map(|(cfg, _)| cfg)
 }
 
+/// Config for commands that also work without a config file. Only a missing
+/// default config falls back to defaults; a file that fails to parse is an error.
+fn load_config_or_default(path: Option<&std::path::Path>) -> Result<Config, CliError> {
+    let (_, kind) = Config::resolve_path(path);
+
+    match Config::load_from_sources(path) {
+        Ok(cfg) => Ok(cfg),
+        Err(ConfigError::Io { ref source, .. })
+            if source.kind() == std::io::ErrorKind::NotFound
+                && matches!(kind, ConfigPathKind::Default) =>
+        {
+            Ok(Config::default())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
 fn load_config_with_path(path: Option<&std::path::Path>) -> Result<(Config, PathBuf), CliError> {
     let (resolved_path, kind) = Config::resolve_path(path);
 
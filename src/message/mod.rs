@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+use std::path::Component;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use tracing::{debug, warn};
+
+use crate::config::{MessageConfig, TargetConfig};
+use crate::git::{self, Change, GitError, LineStats, StatusEntry};
+use crate::shell::{self, ShellError};
+
+/// Placeholders available in `message.subject`
+pub const PLACEHOLDERS: &[&str] = &[
+    "timestamp",
+    "target",
+    "files",
+    "count",
+    "added",
+    "modified",
+    "deleted",
+    "renamed",
+    "dirs",
+    "insertions",
+    "deletions",
+];
+
+/// Directories named by `{dirs}`
+const MAX_DIRS: usize = 3;
+
+/// Longest message accepted from `message_command`
+const MAX_COMMAND_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// Longest `GSD_DEFAULT_MESSAGE`; the kernel rejects much larger variables
+const MAX_ENV_MESSAGE_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MessageError {
+    #[error("unknown placeholder {{{0}}}")]
+    UnknownPlaceholder(String),
+
+    #[error("unclosed '{{' in template")]
+    Unclosed,
+
+    #[error("template is empty")]
+    Empty,
+
+    #[error("message_command `{command}` exited with {code}: {stderr}")]
+    CommandFailed {
+        command: String,
+        code: i32,
+        stderr: String,
+    },
+
+    #[error("message_command `{command}` timed out after {seconds}s")]
+    CommandTimeout { command: String, seconds: u64 },
+
+    #[error("message_command `{command}` printed no message")]
+    CommandEmpty { command: String },
+
+    #[error("message_command `{command}` could not run: {source}")]
+    CommandIo {
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("{0}")]
+    Git(#[from] GitError),
+}
+
+/// Check that a subject template only uses known placeholders
+pub fn validate(template: &str) -> Result<(), MessageError> {
+    if template.trim().is_empty() {
+        return Err(MessageError::Empty);
+    }
+    render(template, |name| {
+        PLACEHOLDERS.contains(&name).then(String::new)
+    })
+    .map(|_| ())
+}
+
+/// Replace each `{name}` in `template` with `lookup(name)`
+fn render(template: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, MessageError> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find('}').ok_or(MessageError::Unclosed)?;
+        let name = &after[..end];
+        let value =
+            lookup(name).ok_or_else(|| MessageError::UnknownPlaceholder(name.to_string()))?;
+        out.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// What a snapshot is about to commit
+#[derive(Debug)]
+pub struct Summary<'a> {
+    pub target: &'a str,
+    pub entries: &'a [StatusEntry],
+    /// Only read when the template uses `{insertions}` or `{deletions}`
+    pub lines: Option<LineStats>,
+    pub time: DateTime<Local>,
+}
+
+/// Whether formatting with `config` needs staged line counts
+pub fn needs_line_stats(config: &MessageConfig) -> bool {
+    config.subject.contains("{insertions}") || config.subject.contains("{deletions}")
+}
+
+/// Commit message for the staged changes in `target`.
+///
+/// Uses the target's `message_command` when set, falling back to the
+/// built-in message if it fails.
+pub async fn generate(
+    config: &MessageConfig,
+    target: &TargetConfig,
+    entries: &[StatusEntry],
+) -> Result<String, GitError> {
+    let dir = target.path.as_path();
+    let lines = if needs_line_stats(config) {
+        Some(git::staged_line_stats(dir).await?)
+    } else {
+        None
+    };
+    let built_in = format(
+        config,
+        &Summary {
+            target: target.name(),
+            entries,
+            lines,
+            time: Local::now(),
+        },
+    );
+
+    let Some(command) = target.message_command.as_deref() else {
+        return Ok(built_in);
+    };
+    match run_command(command, target, entries, &built_in).await {
+        Ok(message) => Ok(message),
+        Err(e) => {
+            warn!(target = %target.name(), error = %e, "Using built-in commit message");
+            Ok(built_in)
+        }
+    }
+}
+
+/// Run `message_command` with the staged diff on stdin and return what it
+/// prints, trimmed
+async fn run_command(
+    command: &str,
+    target: &TargetConfig,
+    entries: &[StatusEntry],
+    built_in: &str,
+) -> Result<String, MessageError> {
+    let diff = git::run_snapshot_git(&target.path, &["diff", "--cached"], None).await?;
+    if diff.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            message: diff.stderr.trim().to_string(),
+        }
+        .into());
+    }
+
+    let io_error = |source| MessageError::CommandIo {
+        command: command.to_string(),
+        source,
+    };
+    let mut cmd = shell::command(command);
+    cmd.current_dir(&target.path)
+        .env("GSD_TARGET_NAME", target.name())
+        .env("GSD_TARGET_PATH", &target.path)
+        .env("GSD_CHANGED_COUNT", entries.len().to_string())
+        .env(
+            "GSD_DEFAULT_MESSAGE",
+            truncate_lines(built_in, MAX_ENV_MESSAGE_BYTES),
+        )
+        .env("GSD_DIFF_TRUNCATED", if diff.truncated { "1" } else { "0" });
+
+    let timeout = Duration::from_secs(target.message_timeout_seconds);
+    let output = match shell::run(
+        cmd,
+        diff.stdout.as_bytes(),
+        timeout,
+        MAX_COMMAND_OUTPUT_BYTES,
+    )
+    .await
+    {
+        Ok(output) => output,
+        Err(ShellError::Io(source)) => return Err(io_error(source)),
+        Err(ShellError::Timeout(_)) => {
+            return Err(MessageError::CommandTimeout {
+                command: command.to_string(),
+                seconds: timeout.as_secs(),
+            })
+        }
+    };
+
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if !output.status.success() {
+        return Err(MessageError::CommandFailed {
+            command: command.to_string(),
+            code: output.status.code().unwrap_or(-1),
+            stderr,
+        });
+    }
+
+    let message = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if message.is_empty() {
+        return Err(MessageError::CommandEmpty {
+            command: command.to_string(),
+        });
+    }
+    debug!(target = %target.name(), stderr = %stderr, "message_command completed");
+    Ok(message)
+}
+
+/// Whole lines of `message` up to `max_bytes`, then "+N more" for the
+/// lines left out
+fn truncate_lines(message: &str, max_bytes: usize) -> String {
+    if message.len() <= max_bytes {
+        return message.to_string();
+    }
+    let mut out = String::new();
+    let mut lines = message.lines();
+    for line in lines.by_ref() {
+        if out.len() + line.len() + 1 > max_bytes {
+            let remaining = 1 + lines.count();
+            return format!("{}+{} more", out, remaining);
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Build a message from `config`'s subject template and optional file list
+pub fn format(config: &MessageConfig, summary: &Summary<'_>) -> String {
+    let entries = summary.entries;
+    let count_of = |matches: fn(&Change) -> bool| {
+        entries
+            .iter()
+            .filter(|e| matches(&e.change))
+            .count()
+            .to_string()
+    };
+    let lines = summary.lines.unwrap_or_default();
+
+    let subject = render(&config.subject, |name| {
+        Some(match name {
+            "timestamp" => summary.time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            "target" => summary.target.to_string(),
+            "files" => file_list(entries, config.max_files),
+            "count" => entries.len().to_string(),
+            "added" => count_of(|c| matches!(c, Change::Added | Change::Copied { .. })),
+            "modified" => count_of(|c| matches!(c, Change::Modified)),
+            "deleted" => count_of(|c| matches!(c, Change::Deleted)),
+            "renamed" => count_of(|c| matches!(c, Change::Renamed { .. })),
+            "dirs" => top_dirs(entries, MAX_DIRS),
+            "insertions" => lines.insertions.to_string(),
+            "deletions" => lines.deletions.to_string(),
+            _ => return None,
+        })
+    })
+    // Validated with the config; fall back to the plain file list otherwise
+    .unwrap_or_else(|_| file_list(entries, config.max_files));
+
+    let subject = subject.trim();
+    let subject = if subject.is_empty() {
+        "Snapshot"
+    } else {
+        subject
+    };
+    if !config.body {
+        return subject.to_string();
+    }
+
+    let body: Vec<String> = entries.iter().map(body_line).collect();
+    format!("{}\n\n{}", subject, body.join("\n"))
+}
+
+/// Up to `max_files` names, then "+N more"
+fn file_list(entries: &[StatusEntry], max_files: usize) -> String {
+    let visible: Vec<String> = entries
+        .iter()
+        .take(max_files)
+        .map(StatusEntry::display_path)
+        .collect();
+    let remaining = entries.len().saturating_sub(max_files);
+
+    if remaining > 0 {
+        format!("{} +{} more", visible.join(", "), remaining)
+    } else {
+        visible.join(", ")
+    }
+}
+
+/// Top-level directories with the most changes; "." for files at the root
+fn top_dirs(entries: &[StatusEntry], max_dirs: usize) -> String {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for entry in entries {
+        let mut components = entry.path.components();
+        let first = components.next();
+        let dir = match (first, components.next()) {
+            (Some(Component::Normal(dir)), Some(_)) => dir.to_string_lossy().into_owned(),
+            _ => ".".to_string(),
+        };
+        *counts.entry(dir).or_default() += 1;
+    }
+
+    let mut dirs: Vec<(String, usize)> = counts.into_iter().collect();
+    dirs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    dirs.into_iter()
+        .take(max_dirs)
+        .map(|(dir, _)| dir)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// One body line, using git's status letters
+fn body_line(entry: &StatusEntry) -> String {
+    let path = entry.display_path();
+    match &entry.change {
+        Change::Added => format!("A  {}", path),
+        Change::Modified => format!("M  {}", path),
+        Change::Deleted => format!("D  {}", path),
+        Change::Renamed { from } => format!("R  {} -> {}", from.to_string_lossy(), path),
+        Change::Copied { from } => format!("C  {} -> {}", from.to_string_lossy(), path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::path::PathBuf;
+
+    fn entry(change: Change, path: &str) -> StatusEntry {
+        StatusEntry {
+            change,
+            path: PathBuf::from(path),
+        }
+    }
+
+    fn summary(entries: &[StatusEntry]) -> Summary<'_> {
+        Summary {
+            target: "notes",
+            entries,
+            lines: Some(LineStats {
+                insertions: 12,
+                deletions: 3,
+            }),
+            time: Local.with_ymd_and_hms(2026, 3, 4, 5, 6, 7).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_default_subject_lists_files() {
+        let config = MessageConfig::default();
+        let files = vec![
+            entry(Change::Added, "a.txt"),
+            entry(Change::Modified, "b.txt"),
+        ];
+        assert_eq!(format(&config, &summary(&files)), "a.txt, b.txt");
+
+        let files: Vec<StatusEntry> = (0..15)
+            .map(|i| entry(Change::Modified, &format!("file{}.txt", i)))
+            .collect();
+        assert!(format(&config, &summary(&files)).ends_with("+5 more"));
+    }
+
+    #[test]
+    fn test_placeholders_and_body() {
+        let config = MessageConfig {
+            subject: "{target} {timestamp}: +{added} ~{modified} -{deleted} >{renamed} in {dirs} ({insertions}+/{deletions}-)".to_string(),
+            body: true,
+            ..Default::default()
+        };
+        let files = vec![
+            entry(Change::Added, "plans/a.md"),
+            entry(Change::Modified, "plans/b.md"),
+            entry(Change::Deleted, "notes/c.md"),
+            entry(
+                Change::Renamed {
+                    from: PathBuf::from("old.md"),
+                },
+                "new.md",
+            ),
+        ];
+
+        assert_eq!(
+            format(&config, &summary(&files)),
+            "notes 2026-03-04 05:06:07: +1 ~1 -1 >1 in plans, ., notes (12+/3-)\n\n\
+             A  plans/a.md\n\
+             M  plans/b.md\n\
+             D  notes/c.md\n\
+             R  old.md -> new.md"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_message_command_with_fallback() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        git::ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        std::fs::write(dir.join("plan.md"), "step one\nstep two\n").unwrap();
+        git::stage_all(dir).await.unwrap();
+        let entries = git::list_changed_files(dir).await.unwrap();
+
+        let message_for = |command: &str| {
+            let mut target = TargetConfig::new(dir.to_path_buf(), 60);
+            target.message_command = Some(command.to_string());
+            target.message_timeout_seconds = 1;
+            let entries = entries.clone();
+            async move {
+                generate(&MessageConfig::default(), &target, &entries)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        // The diff arrives on stdin
+        let message = message_for("echo \"$GSD_TARGET_NAME: $(grep -c '^+step')\" steps").await;
+        assert!(message.ends_with(": 2 steps"), "{message}");
+
+        // Failures, empty output and timeouts fall back to the built-in message
+        assert_eq!(message_for("exit 3").await, "plan.md");
+        assert_eq!(message_for("true").await, "plan.md");
+        let started = std::time::Instant::now();
+        assert_eq!(message_for("sleep 30").await, "plan.md");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_message_command_sees_long_default_message() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        git::ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        for i in 0..5000 {
+            std::fs::write(dir.join(format!("a-rather-long-file-name-{i:04}.md")), "x").unwrap();
+        }
+        git::stage_all(dir).await.unwrap();
+        let entries = git::list_changed_files(dir).await.unwrap();
+        let config = MessageConfig {
+            body: true,
+            ..Default::default()
+        };
+        let mut target = TargetConfig::new(dir.to_path_buf(), 60);
+        target.message_command =
+            Some("printf '%s' \"$GSD_DEFAULT_MESSAGE\" | tail -n 1".to_string());
+
+        let message = generate(&config, &target, &entries).await.unwrap();
+        assert!(
+            message.starts_with('+') && message.ends_with(" more"),
+            "{message}"
+        );
+        assert_eq!(truncate_lines("a\nbb\nccc", 5), "a\nbb\n+1 more");
+        assert_eq!(truncate_lines("a\nbb", 5), "a\nbb");
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_placeholders() {
+        assert!(validate("{count} files in {dirs}").is_ok());
+        assert!(matches!(
+            validate("{files} {author}"),
+            Err(MessageError::UnknownPlaceholder(name)) if name == "author"
+        ));
+        assert!(matches!(validate("{files"), Err(MessageError::Unclosed)));
+        assert!(matches!(validate("  "), Err(MessageError::Empty)));
+    }
+}
@@ -0,0 +1,479 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+use crate::config::TargetConfig;
+
+/// Upper bounds (seconds) of the commit duration histogram
+const DURATION_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Minimum time between walks of a target's `.gsd` directory for the size gauge
+const REPO_SIZE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Upper bounds of the changed-files-per-snapshot histogram
+const FILES_BUCKETS: &[f64] = &[1.0, 5.0, 10.0, 50.0, 100.0, 500.0, 1000.0];
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::default);
+
+/// Process-wide metric values, rendered in the Prometheus text format
+#[derive(Debug, Default)]
+struct Registry {
+    git_subprocesses: AtomicU64,
+    git_timeouts: AtomicU64,
+    /// Keyed by target path, since names need not be unique
+    targets: Mutex<BTreeMap<PathBuf, TargetMetrics>>,
+}
+
+#[derive(Debug, Default)]
+struct TargetMetrics {
+    name: String,
+    snapshots: u64,
+    failures: u64,
+    throttled: BTreeMap<&'static str, u64>,
+    last_success: Option<f64>,
+    duration: Histogram,
+    changed_files: Histogram,
+    repo_size_bytes: Option<u64>,
+    repo_size_checked: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, buckets: &[f64], value: f64) {
+        if self.counts.is_empty() {
+            self.counts = vec![0; buckets.len()];
+        }
+        for (bound, count) in buckets.iter().zip(self.counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+fn with_target(target: &TargetConfig, f: impl FnOnce(&mut TargetMetrics)) {
+    let mut targets = REGISTRY.targets.lock().unwrap_or_else(|e| e.into_inner());
+    let metrics = targets
+        .entry(target.path.clone())
+        .or_insert_with(|| TargetMetrics {
+            name: target.name().to_string(),
+            ..Default::default()
+        });
+    f(metrics);
+}
+
+/// Count one spawned git process
+pub fn record_git_spawn() {
+    REGISTRY.git_subprocesses.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count one git process killed for running too long
+pub fn record_git_timeout() {
+    REGISTRY.git_timeouts.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Record a committed snapshot for `target`
+pub fn record_snapshot(target: &TargetConfig, duration: Duration, files: usize) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    with_target(target, |m| {
+        m.snapshots += 1;
+        m.last_success = Some(now);
+        m.duration.observe(DURATION_BUCKETS, duration.as_secs_f64());
+        m.changed_files.observe(FILES_BUCKETS, files as f64);
+    });
+}
+
+/// Record a snapshot attempt that failed
+pub fn record_failure(target: &TargetConfig) {
+    with_target(target, |m| m.failures += 1);
+}
+
+/// Record a scheduled snapshot held back by throttling
+pub fn record_throttled(target: &TargetConfig, reason: &'static str) {
+    with_target(target, |m| *m.throttled.entry(reason).or_default() += 1);
+}
+
+/// Whether the `.gsd` size gauge for `target` is old enough to measure again;
+/// claims the measurement, so concurrent snapshots walk the directory once
+pub fn repo_size_due(target: &TargetConfig) -> bool {
+    let mut due = false;
+    with_target(target, |m| {
+        due = m
+            .repo_size_checked
+            .is_none_or(|checked| checked.elapsed() >= REPO_SIZE_INTERVAL);
+        if due {
+            m.repo_size_checked = Some(Instant::now());
+        }
+    });
+    due
+}
+
+/// Update the `.gsd` size gauge for `target`
+pub fn set_repo_size(target: &TargetConfig, bytes: u64) {
+    with_target(target, |m| m.repo_size_bytes = Some(bytes));
+}
+
+/// Stop exporting series for a target that was removed
+pub fn forget_target(target: &TargetConfig) {
+    let mut targets = REGISTRY.targets.lock().unwrap_or_else(|e| e.into_inner());
+    targets.remove(&target.path);
+}
+
+/// Total size of all files under `dir`, for the repo size gauge
+pub fn dir_size(dir: &Path) -> u64 {
+    ignore::WalkBuilder::new(dir)
+        .standard_filters(false)
+        .build()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn write_histogram(out: &mut String, name: &str, labels: &str, buckets: &[f64], h: &Histogram) {
+    for (i, bound) in buckets.iter().enumerate() {
+        let count = h.counts.get(i).copied().unwrap_or(0);
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"{}\"}} {}",
+            name, labels, bound, count
+        );
+    }
+    let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, h.count);
+    let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, h.sum);
+    let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, h.count);
+}
+
+/// Render all metrics in the Prometheus text exposition format
+pub fn render() -> String {
+    let mut out = String::new();
+
+    write_header(
+        &mut out,
+        "gsd_git_subprocesses_total",
+        "counter",
+        "Git processes spawned by gsd.",
+    );
+    let _ = writeln!(
+        out,
+        "gsd_git_subprocesses_total {}",
+        REGISTRY.git_subprocesses.load(Ordering::Relaxed)
+    );
+
+    write_header(
+        &mut out,
+        "gsd_git_timeouts_total",
+        "counter",
+        "Git processes killed after exceeding their timeout.",
+    );
+    let _ = writeln!(
+        out,
+        "gsd_git_timeouts_total {}",
+        REGISTRY.git_timeouts.load(Ordering::Relaxed)
+    );
+
+    let targets = REGISTRY.targets.lock().unwrap_or_else(|e| e.into_inner());
+    let labeled: Vec<(String, &TargetMetrics)> = targets
+        .iter()
+        .map(|(path, m)| {
+            let labels = format!(
+                "target=\"{}\",path=\"{}\"",
+                escape_label(&m.name),
+                escape_label(&path.to_string_lossy())
+            );
+            (labels, m)
+        })
+        .collect();
+
+    write_header(
+        &mut out,
+        "gsd_snapshots_total",
+        "counter",
+        "Snapshots committed.",
+    );
+    for (label, m) in &labeled {
+        let _ = writeln!(out, "gsd_snapshots_total{{{}}} {}", label, m.snapshots);
+    }
+
+    write_header(
+        &mut out,
+        "gsd_snapshot_failures_total",
+        "counter",
+        "Snapshot attempts that failed.",
+    );
+    for (label, m) in &labeled {
+        let _ = writeln!(
+            out,
+            "gsd_snapshot_failures_total{{{}}} {}",
+            label, m.failures
+        );
+    }
+
+    write_header(
+        &mut out,
+        "gsd_snapshots_throttled_total",
+        "counter",
+        "Scheduled snapshots skipped by load, disk or battery throttling.",
+    );
+    for (label, m) in &labeled {
+        for (reason, count) in &m.throttled {
+            let _ = writeln!(
+                out,
+                "gsd_snapshots_throttled_total{{{},reason=\"{}\"}} {}",
+                label, reason, count
+            );
+        }
+    }
+
+    write_header(
+        &mut out,
+        "gsd_last_success_timestamp_seconds",
+        "gauge",
+        "Unix time of the last committed snapshot.",
+    );
+    for (label, m) in &labeled {
+        if let Some(ts) = m.last_success {
+            let _ = writeln!(
+                out,
+                "gsd_last_success_timestamp_seconds{{{}}} {:.3}",
+                label, ts
+            );
+        }
+    }
+
+    write_header(
+        &mut out,
+        "gsd_repo_size_bytes",
+        "gauge",
+        "Size of the target's .gsd directory.",
+    );
+    for (label, m) in &labeled {
+        if let Some(bytes) = m.repo_size_bytes {
+            let _ = writeln!(out, "gsd_repo_size_bytes{{{}}} {}", label, bytes);
+        }
+    }
+
+    write_header(
+        &mut out,
+        "gsd_commit_duration_seconds",
+        "histogram",
+        "Time taken by snapshots that created a commit.",
+    );
+    for (label, m) in &labeled {
+        write_histogram(
+            &mut out,
+            "gsd_commit_duration_seconds",
+            label,
+            DURATION_BUCKETS,
+            &m.duration,
+        );
+    }
+
+    write_header(
+        &mut out,
+        "gsd_changed_files",
+        "histogram",
+        "Changed files per committed snapshot.",
+    );
+    for (label, m) in &labeled {
+        write_histogram(
+            &mut out,
+            "gsd_changed_files",
+            label,
+            FILES_BUCKETS,
+            &m.changed_files,
+        );
+    }
+
+    out
+}
+
+/// Delay after a failed accept, doubled on each consecutive failure
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(100);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+/// Serve `GET /metrics` on `addr` until the task is dropped
+pub async fn serve(addr: SocketAddr) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!(address = %addr, "Metrics listener started");
+
+    let mut backoff = ACCEPT_BACKOFF_MIN;
+    loop {
+        // Errors such as running out of file descriptors are transient, so
+        // keep serving once they clear
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => {
+                backoff = ACCEPT_BACKOFF_MIN;
+                accepted
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to accept metrics connection");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream).await {
+                debug!(peer = %peer, error = %e, "Metrics request failed");
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream) -> std::io::Result<()> {
+    // Only the request line matters; read until the end of the headers
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    let read = async {
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 16 * 1024 {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        Ok::<_, std::io::Error>(())
+    };
+    if tokio::time::timeout(Duration::from_secs(5), read)
+        .await
+        .is_err()
+    {
+        return Ok(());
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => ("200 OK", "text/plain; version=0.0.4", render()),
+        ("GET", _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "method not allowed\n".to_string(),
+        ),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Start the listener in the background, logging instead of failing the daemon
+pub fn spawn(addr: SocketAddr) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = serve(addr).await {
+            warn!(address = %addr, error = %e, "Metrics listener stopped");
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_target_series() {
+        let target = TargetConfig::new(PathBuf::from("/metrics-test/a/notes"), 60);
+        let same_name = TargetConfig::new(PathBuf::from("/metrics-test/b/notes"), 60);
+        record_snapshot(&target, Duration::from_millis(200), 3);
+        record_failure(&target);
+        set_repo_size(&target, 4096);
+        record_failure(&same_name);
+
+        let a = r#"target="notes",path="/metrics-test/a/notes""#;
+        let b = r#"target="notes",path="/metrics-test/b/notes""#;
+        let text = render();
+        assert!(text.contains(&format!("gsd_snapshots_total{{{a}}} 1")));
+        assert!(text.contains(&format!("gsd_snapshot_failures_total{{{a}}} 1")));
+        assert!(text.contains(&format!("gsd_snapshot_failures_total{{{b}}} 1")));
+        assert!(text.contains(&format!("gsd_repo_size_bytes{{{a}}} 4096")));
+        assert!(text.contains(&format!(
+            "gsd_commit_duration_seconds_bucket{{{a},le=\"0.1\"}} 0"
+        )));
+        assert!(text.contains(&format!(
+            "gsd_commit_duration_seconds_bucket{{{a},le=\"0.25\"}} 1"
+        )));
+        assert!(text.contains(&format!("gsd_changed_files_bucket{{{a},le=\"5\"}} 1")));
+        assert!(text.contains("# TYPE gsd_git_subprocesses_total counter"));
+
+        forget_target(&target);
+        forget_target(&same_name);
+        assert!(!render().contains("metrics-test"));
+    }
+
+    #[test]
+    fn test_repo_size_measured_once_per_interval() {
+        let target = TargetConfig::new(PathBuf::from("/metrics-test/size"), 60);
+        assert!(repo_size_due(&target));
+        assert!(!repo_size_due(&target));
+    }
+
+    #[tokio::test]
+    async fn test_serves_metrics_over_http() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let handle = spawn(addr);
+
+        let mut stream = None;
+        for _ in 0..50 {
+            if let Ok(s) = TcpStream::connect(addr).await {
+                stream = Some(s);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let mut stream = stream.expect("listener should accept connections");
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("gsd_git_subprocesses_total"));
+        handle.abort();
+    }
+}
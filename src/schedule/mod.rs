@@ -0,0 +1,469 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+
+use crate::config::TargetConfig;
+
+/// How far ahead `next_after` searches before giving up
+const SEARCH_DAYS: u64 = 366 * 5;
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("invalid cron expression '{expr}': {message}")]
+    Cron { expr: String, message: String },
+
+    #[error("invalid days '{0}': expected names or numbers like mon-fri or 0,6")]
+    Days(String),
+
+    #[error("invalid hours '{0}': expected HH:MM-HH:MM")]
+    Hours(String),
+
+    #[error("schedule '{0}' never runs")]
+    NeverRuns(String),
+}
+
+/// When a target's timer fires: an interval or cron expression, optionally
+/// limited to an active window of weekdays and hours in local time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    trigger: Trigger,
+    window: Option<Window>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Trigger {
+    Every(Duration),
+    Cron(Cron),
+}
+
+/// Parsed 5-field cron expression; each field is a bit set of allowed values
+#[derive(Debug, Clone, PartialEq)]
+struct Cron {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Both day fields restricted: cron matches either of them
+    either_day: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Window {
+    /// Days and hours as configured, for display
+    spec: String,
+    /// Weekdays the window opens on, bit 0 = Sunday
+    days: u64,
+    /// Minutes since midnight; `start > end` wraps past midnight
+    start: u32,
+    end: u32,
+}
+
+impl Schedule {
+    /// Plain interval with no active window
+    pub fn every(interval: Duration) -> Self {
+        Self {
+            trigger: Trigger::Every(interval),
+            window: None,
+        }
+    }
+
+    pub fn from_target(target: &TargetConfig) -> Result<Self, ScheduleError> {
+        let schedule = target.schedule.as_ref();
+
+        let trigger = match schedule.and_then(|s| s.cron.as_deref()) {
+            Some(expr) => Trigger::Cron(Cron::parse(expr)?),
+            None => Self::every(Duration::from_secs(target.interval_seconds)).trigger,
+        };
+
+        let days = schedule.and_then(|s| s.days.as_deref());
+        let hours = schedule.and_then(|s| s.hours.as_deref());
+        let window = if days.is_none() && hours.is_none() {
+            None
+        } else {
+            Some(Window::parse(days, hours)?)
+        };
+
+        let schedule = Self { trigger, window };
+        if schedule.next_after(Local::now()).is_none() {
+            return Err(ScheduleError::NeverRuns(schedule.describe()));
+        }
+        Ok(schedule)
+    }
+
+    /// Interval of a non-cron schedule
+    pub fn interval(&self) -> Option<Duration> {
+        match self.trigger {
+            Trigger::Every(interval) => Some(interval),
+            Trigger::Cron(_) => None,
+        }
+    }
+
+    /// Whether `time` falls in the active window (always, without one)
+    pub fn is_active(&self, time: DateTime<Local>) -> bool {
+        self.window
+            .as_ref()
+            .is_none_or(|w| w.contains(time.naive_local()))
+    }
+
+    /// First run strictly after `after`, or `None` if there is none within
+    /// five years
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match &self.trigger {
+            Trigger::Every(interval) => {
+                let candidate = after + chrono::Duration::from_std(*interval).ok()?;
+                match &self.window {
+                    Some(window) if !window.contains(candidate.naive_local()) => {
+                        to_local(window.next_open(candidate.naive_local())?)
+                    }
+                    _ => Some(candidate),
+                }
+            }
+            Trigger::Cron(cron) => {
+                let limit = after.naive_local() + chrono::Duration::days(SEARCH_DAYS as i64);
+                let mut from = after.naive_local();
+                while from < limit {
+                    let time = cron.next_match(from)?;
+                    let in_window = self.window.as_ref().is_none_or(|w| w.contains(time));
+                    if in_window {
+                        // Times skipped by a DST change are not run
+                        if let Some(local) = to_local(time) {
+                            return Some(local);
+                        }
+                    }
+                    from = time;
+                }
+                None
+            }
+        }
+    }
+
+    /// Short human-readable form, e.g. "every 300s, mon-fri 09:00-18:00"
+    pub fn describe(&self) -> String {
+        let trigger = match &self.trigger {
+            Trigger::Every(interval) => format!("every {}s", interval.as_secs()),
+            Trigger::Cron(cron) => format!("cron \"{}\"", cron.expr),
+        };
+        match &self.window {
+            Some(window) => format!("{}, {}", trigger, window.spec),
+            None => trigger,
+        }
+    }
+}
+
+fn to_local(time: NaiveDateTime) -> Option<DateTime<Local>> {
+    Local.from_local_datetime(&time).earliest()
+}
+
+fn bit(value: u32) -> u64 {
+    1 << value
+}
+
+impl Cron {
+    fn parse(expr: &str) -> Result<Self, ScheduleError> {
+        let error = |message: String| ScheduleError::Cron {
+            expr: expr.to_string(),
+            message,
+        };
+
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields[..] else {
+            return Err(error(format!("expected 5 fields, found {}", fields.len())));
+        };
+
+        let mut days_of_week = parse_field(dow, 0, 7, DAY_NAMES).map_err(error)?;
+        // 7 is another name for Sunday
+        if days_of_week & bit(7) != 0 {
+            days_of_week = (days_of_week & !bit(7)) | bit(0);
+        }
+
+        Ok(Self {
+            expr: expr.to_string(),
+            minutes: parse_field(minute, 0, 59, &[]).map_err(error)?,
+            hours: parse_field(hour, 0, 23, &[]).map_err(error)?,
+            days_of_month: parse_field(dom, 1, 31, &[]).map_err(error)?,
+            months: parse_field(month, 1, 12, MONTH_NAMES).map_err(error)?,
+            days_of_week,
+            either_day: !dom.starts_with('*') && !dow.starts_with('*'),
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let dom = self.days_of_month & bit(date.day()) != 0;
+        let dow = self.days_of_week & bit(date.weekday().num_days_from_sunday()) != 0;
+        if self.either_day {
+            dom || dow
+        } else {
+            dom && dow
+        }
+    }
+
+    /// First matching minute strictly after `after`
+    fn next_match(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        let mut date = start.date();
+        let mut first_day = true;
+
+        for _ in 0..SEARCH_DAYS {
+            if self.months & bit(date.month()) != 0 && self.matches_day(date) {
+                let from = if first_day {
+                    (start.hour(), start.minute())
+                } else {
+                    (0, 0)
+                };
+                for hour in from.0..24 {
+                    if self.hours & bit(hour) == 0 {
+                        continue;
+                    }
+                    let first_minute = if hour == from.0 { from.1 } else { 0 };
+                    if let Some(minute) = (first_minute..60).find(|m| self.minutes & bit(*m) != 0) {
+                        return date.and_hms_opt(hour, minute, 0);
+                    }
+                }
+            }
+            date = date.checked_add_days(Days::new(1))?;
+            first_day = false;
+        }
+        None
+    }
+}
+
+/// Parse one cron field (`*`, `a-b`, `*/n`, `a-b/n`, lists) into a bit set
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let lower = s.to_ascii_lowercase();
+        let parsed = match names.iter().position(|n| *n == lower) {
+            // Month names start at 1, day names at 0
+            Some(i) => i as u32 + min,
+            None => s
+                .parse::<u32>()
+                .map_err(|_| format!("invalid value '{}'", s))?,
+        };
+        if parsed < min || parsed > max {
+            return Err(format!("{} is outside {}-{}", parsed, min, max));
+        }
+        Ok(parsed)
+    };
+
+    let mut set = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("invalid step '{}'", step))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (low, high) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((low, high)) => (value(low)?, value(high)?),
+                // `5/15` means from 5 to the end
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let single = value(range)?;
+                    (single, single)
+                }
+            },
+        };
+        if low > high {
+            return Err(format!("invalid range '{}'", range));
+        }
+        for v in (low..=high).step_by(step as usize) {
+            set |= bit(v);
+        }
+    }
+    Ok(set)
+}
+
+impl Window {
+    fn parse(days_spec: Option<&str>, hours: Option<&str>) -> Result<Self, ScheduleError> {
+        let days = match days_spec {
+            Some(spec) => {
+                let set = parse_field(spec, 0, 7, DAY_NAMES)
+                    .map_err(|_| ScheduleError::Days(spec.to_string()))?;
+                if set & bit(7) != 0 {
+                    (set & !bit(7)) | bit(0)
+                } else {
+                    set
+                }
+            }
+            None => 0b111_1111,
+        };
+
+        let (start, end) = match hours {
+            Some(spec) => {
+                let invalid = || ScheduleError::Hours(spec.to_string());
+                let (start, end) = spec.split_once('-').ok_or_else(invalid)?;
+                let start = parse_clock(start.trim()).ok_or_else(invalid)?;
+                let end = parse_clock(end.trim()).ok_or_else(invalid)?;
+                if start == end {
+                    return Err(invalid());
+                }
+                (start, end)
+            }
+            None => (0, 24 * 60),
+        };
+
+        let spec = [days_spec, hours]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(Self {
+            spec,
+            days,
+            start,
+            end,
+        })
+    }
+
+    fn opens_on(&self, date: NaiveDate) -> bool {
+        self.days & bit(date.weekday().num_days_from_sunday()) != 0
+    }
+
+    fn contains(&self, time: NaiveDateTime) -> bool {
+        let minute = time.hour() * 60 + time.minute();
+        let date = time.date();
+        if self.start < self.end {
+            self.opens_on(date) && minute >= self.start && minute < self.end
+        } else {
+            // Overnight windows belong to the day they open on
+            (minute >= self.start && self.opens_on(date))
+                || (minute < self.end && date.pred_opt().is_some_and(|d| self.opens_on(d)))
+        }
+    }
+
+    /// Next time the window opens after `after`
+    fn next_open(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut date = after.date();
+        for _ in 0..8 {
+            let open = date.and_hms_opt(self.start / 60, self.start % 60, 0)?;
+            if open > after && self.opens_on(date) {
+                return Some(open);
+            }
+            date = date.checked_add_days(Days::new(1))?;
+        }
+        None
+    }
+}
+
+/// Parse `HH:MM`; `24:00` is allowed as the end of the day
+fn parse_clock(value: &str) -> Option<u32> {
+    let (hour, minute) = value.split_once(':')?;
+    let (hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+    let total = hour * 60 + minute;
+    (minute < 60 && total <= 24 * 60).then_some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScheduleConfig;
+
+    fn local(s: &str) -> DateTime<Local> {
+        let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&naive).earliest().unwrap()
+    }
+
+    fn schedule(cron: Option<&str>, days: Option<&str>, hours: Option<&str>) -> Schedule {
+        let mut target = TargetConfig::new("/tmp/x".into(), 300);
+        target.schedule = Some(ScheduleConfig {
+            cron: cron.map(String::from),
+            days: days.map(String::from),
+            hours: hours.map(String::from),
+        });
+        Schedule::from_target(&target).unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_run() {
+        // 2026-01-21 is a Wednesday
+        let nightly = schedule(Some("30 2 * * *"), None, None);
+        assert_eq!(
+            nightly.next_after(local("2026-01-21 10:00")),
+            Some(local("2026-01-22 02:30"))
+        );
+
+        let weekdays = schedule(Some("*/15 9-17 * * mon-fri"), None, None);
+        assert_eq!(
+            weekdays.next_after(local("2026-01-21 09:07")),
+            Some(local("2026-01-21 09:15"))
+        );
+        assert_eq!(
+            weekdays.next_after(local("2026-01-23 17:45")),
+            Some(local("2026-01-26 09:00"))
+        );
+
+        let first_of_month = schedule(Some("0 0 1 * *"), None, None);
+        assert_eq!(
+            first_of_month.next_after(local("2026-01-21 10:00")),
+            Some(local("2026-02-01 00:00"))
+        );
+    }
+
+    #[test]
+    fn test_interval_waits_for_active_window() {
+        let office = schedule(None, Some("mon-fri"), Some("09:00-18:00"));
+        assert_eq!(
+            office.next_after(local("2026-01-21 10:00")),
+            Some(local("2026-01-21 10:05"))
+        );
+        assert_eq!(
+            office.next_after(local("2026-01-21 17:58")),
+            Some(local("2026-01-22 09:00"))
+        );
+        // Friday evening waits for Monday
+        assert_eq!(
+            office.next_after(local("2026-01-23 18:30")),
+            Some(local("2026-01-26 09:00"))
+        );
+        assert!(!office.is_active(local("2026-01-24 12:00")));
+
+        let overnight = schedule(None, Some("fri"), Some("22:00-06:00"));
+        assert!(overnight.is_active(local("2026-01-24 03:00")));
+        assert!(!overnight.is_active(local("2026-01-25 03:00")));
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        for cron in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "* * * foo *",
+        ] {
+            assert!(Cron::parse(cron).is_err(), "{} should be rejected", cron);
+        }
+        assert!(Window::parse(Some("someday"), None).is_err());
+        assert!(Window::parse(None, Some("9-17")).is_err());
+        assert!(Window::parse(None, Some("09:00-09:00")).is_err());
+        let mut target = TargetConfig::new("/tmp/x".into(), 300);
+        for (cron, hours) in [("0 0 31 2 *", None), ("0 3 * * *", Some("09:00-18:00"))] {
+            target.schedule = Some(ScheduleConfig {
+                cron: Some(cron.to_string()),
+                days: None,
+                hours: hours.map(String::from),
+            });
+            assert!(
+                matches!(
+                    Schedule::from_target(&target),
+                    Err(ScheduleError::NeverRuns(_))
+                ),
+                "{} should be rejected",
+                cron
+            );
+        }
+    }
+}
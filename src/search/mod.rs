@@ -0,0 +1,551 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::git::{run_git, run_snapshot_git, GitError, GSD_DIR};
+
+/// Snapshots searched per `git grep` invocation
+const GREP_BATCH: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SearchError {
+    #[error("invalid pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Git(#[from] GitError),
+}
+
+/// What to search for and where
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub pattern: String,
+    /// Treat `pattern` as a POSIX extended regular expression, as git does,
+    /// rather than literal text
+    pub regex: bool,
+    /// Only snapshots newer than this
+    pub since: Option<DateTime<Utc>>,
+    /// Only files matching this glob; globs without a '/' match at any depth
+    pub file_glob: Option<String>,
+}
+
+impl SearchOptions {
+    /// `git grep` flag selecting how the pattern is read
+    fn grep_mode(&self) -> &'static str {
+        if self.regex {
+            "-E"
+        } else {
+            "-F"
+        }
+    }
+
+    fn since_arg(&self) -> Option<String> {
+        self.since.map(|t| format!("--since=@{}", t.timestamp()))
+    }
+
+    fn pathspec(&self) -> Option<String> {
+        self.file_glob.as_ref().map(|glob| {
+            if glob.contains('/') {
+                format!(":(glob){}", glob)
+            } else {
+                format!(":(glob)**/{}", glob)
+            }
+        })
+    }
+}
+
+/// One matching line
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    /// Absolute path of the target
+    pub target: String,
+    pub commit: String,
+    /// RFC 3339 time of the snapshot
+    pub time: String,
+    pub path: String,
+    /// `added` or `removed` when searching for changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<&'static str>,
+    /// Line number when searching each snapshot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+    pub text: String,
+}
+
+fn failed(result: crate::git::GitCommandResult) -> GitError {
+    GitError::CommandFailed {
+        message: result.stderr.trim().to_string(),
+    }
+}
+
+/// Snapshots where the number of occurrences of the pattern in a file
+/// changed, i.e. where it first appeared or was removed, oldest first
+pub async fn changes(dir: &Path, options: &SearchOptions) -> Result<Vec<SearchMatch>, SearchError> {
+    validate(options).await?;
+    let pickaxe = format!("-S{}", options.pattern);
+    let since = options.since_arg();
+    let pathspec = options.pathspec();
+
+    let mut args = vec![
+        "log",
+        "--reverse",
+        "-M",
+        "-p",
+        "--no-color",
+        "--no-ext-diff",
+        "--format=%x01%H%x00%cI",
+        &pickaxe,
+    ];
+    if options.regex {
+        args.push("--pickaxe-regex");
+    }
+    args.extend(since.as_deref());
+    args.push("--");
+    args.extend(pathspec.as_deref());
+
+    let result = run_snapshot_git(dir, &args, Some(usize::MAX)).await?;
+    if result.exit_code != 0 {
+        if result.stderr.contains("does not have any commits") {
+            return Ok(Vec::new());
+        }
+        return Err(failed(result).into());
+    }
+
+    // The patches show whole files; keep the changed lines that match
+    let candidates = parse_pickaxe_log(&dir.to_string_lossy(), &result.stdout);
+    let lines: Vec<&str> = candidates.iter().map(|m| m.text.as_str()).collect();
+    let matching = matching_lines(options, &lines).await?;
+    Ok(candidates
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| matching.contains(index))
+        .map(|(_, m)| m)
+        .collect())
+}
+
+/// Indexes of the `lines` that match the pattern. Lines are checked by
+/// `git grep --no-index`, so the pattern means the same as in `git log -S`
+/// and `git grep`.
+async fn matching_lines(
+    options: &SearchOptions,
+    lines: &[&str],
+) -> Result<HashSet<usize>, SearchError> {
+    let scratch = std::env::temp_dir().join(format!(
+        "gsd-search-{}-{}",
+        std::process::id(),
+        fastrand::u64(..)
+    ));
+    std::fs::create_dir(&scratch)?;
+    let result = async {
+        std::fs::write(scratch.join("lines"), lines.join("\n"))?;
+        let args = [
+            "grep",
+            "--no-index",
+            "-h",
+            "-n",
+            "-a",
+            options.grep_mode(),
+            "-e",
+            &options.pattern,
+            "--",
+            "lines",
+        ];
+        Ok::<_, SearchError>(run_git(&scratch, &args, Some(usize::MAX)).await?)
+    }
+    .await;
+    let _ = std::fs::remove_dir_all(&scratch);
+    let result = result?;
+
+    // git grep exits 1 when nothing matches
+    match result.exit_code {
+        0 | 1 => Ok(result
+            .stdout
+            .lines()
+            .filter_map(|line| line.split_once(':')?.0.parse::<usize>().ok())
+            .map(|number| number - 1)
+            .collect()),
+        _ => Err(SearchError::InvalidPattern(
+            result
+                .stderr
+                .trim()
+                .trim_start_matches("fatal: ")
+                .to_string(),
+        )),
+    }
+}
+
+/// Check the pattern with git before searching
+async fn validate(options: &SearchOptions) -> Result<(), SearchError> {
+    matching_lines(options, &[]).await.map(|_| ())
+}
+
+/// Pull added and removed lines out of `log -p` output
+fn parse_pickaxe_log(target: &str, output: &str) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    for record in output.split('\x01').filter(|r| !r.is_empty()) {
+        let Some((header, patch)) = record.split_once('\n') else {
+            continue;
+        };
+        let Some((commit, time)) = header.split_once('\0') else {
+            continue;
+        };
+
+        let mut old_path = String::new();
+        let mut path = String::new();
+        let mut in_hunk = false;
+        for line in patch.lines() {
+            if line.starts_with("diff --git ") {
+                in_hunk = false;
+                continue;
+            }
+            if !in_hunk {
+                if let Some(name) = line.strip_prefix("--- ") {
+                    old_path = header_path(name, "a/");
+                } else if let Some(name) = line.strip_prefix("+++ ") {
+                    path = header_path(name, "b/");
+                    // Deleted files keep their old name
+                    if path == "/dev/null" {
+                        path = old_path.clone();
+                    }
+                } else if line.starts_with("@@") {
+                    in_hunk = true;
+                }
+                continue;
+            }
+
+            let (change, text) = match line.as_bytes().first() {
+                Some(b'+') => ("added", &line[1..]),
+                Some(b'-') => ("removed", &line[1..]),
+                _ => continue,
+            };
+            matches.push(SearchMatch {
+                target: target.to_string(),
+                commit: commit.to_string(),
+                time: time.to_string(),
+                path: path.clone(),
+                change: Some(change),
+                line: None,
+                text: text.to_string(),
+            });
+        }
+    }
+    matches
+}
+
+/// A file name from a `---`/`+++` patch header. Git appends a tab to names
+/// with spaces and C-quotes names with special or non-ASCII characters.
+fn header_path(name: &str, prefix: &str) -> String {
+    let name = name.strip_suffix('\t').unwrap_or(name);
+    let name = match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+        Some(quoted) => unquote(quoted),
+        None => name.to_string(),
+    };
+    match name.strip_prefix(prefix) {
+        Some(path) => path.to_string(),
+        None => name,
+    }
+}
+
+/// Undo git's C-style quoting: backslash escapes and octal bytes
+fn unquote(quoted: &str) -> String {
+    let mut bytes = Vec::with_capacity(quoted.len());
+    let mut chars = quoted.bytes().peekable();
+    while let Some(byte) = chars.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        match chars.next() {
+            Some(b'a') => bytes.push(0x07),
+            Some(b'b') => bytes.push(0x08),
+            Some(b't') => bytes.push(b'\t'),
+            Some(b'n') => bytes.push(b'\n'),
+            Some(b'v') => bytes.push(0x0b),
+            Some(b'f') => bytes.push(0x0c),
+            Some(b'r') => bytes.push(b'\r'),
+            Some(digit @ b'0'..=b'7') => {
+                let mut value = u32::from(digit - b'0');
+                for _ in 0..2 {
+                    match chars.peek() {
+                        Some(&d @ b'0'..=b'7') => {
+                            value = value * 8 + u32::from(d - b'0');
+                            chars.next();
+                        }
+                        _ => break,
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            Some(other) => bytes.push(other),
+            None => bytes.push(b'\\'),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Every matching line in every snapshot, oldest snapshot first
+pub async fn each_snapshot(
+    dir: &Path,
+    options: &SearchOptions,
+) -> Result<Vec<SearchMatch>, SearchError> {
+    validate(options).await?;
+    let since = options.since_arg();
+    let pathspec = options.pathspec();
+
+    let mut args = vec!["log", "--reverse", "--format=%H%x00%cI"];
+    args.extend(since.as_deref());
+    let result = run_snapshot_git(dir, &args, Some(usize::MAX)).await?;
+    if result.exit_code != 0 {
+        if result.stderr.contains("does not have any commits") {
+            return Ok(Vec::new());
+        }
+        return Err(failed(result).into());
+    }
+    let snapshots: Vec<(&str, &str)> = result
+        .stdout
+        .lines()
+        .filter_map(|line| line.split_once('\0'))
+        .collect();
+    let times: HashMap<&str, &str> = snapshots.iter().copied().collect();
+    let target = dir.to_string_lossy();
+
+    let mut matches = Vec::new();
+    for batch in snapshots.chunks(GREP_BATCH) {
+        let mut args = vec![
+            "grep",
+            "-n",
+            "-z",
+            "-I",
+            options.grep_mode(),
+            "-e",
+            &options.pattern,
+        ];
+        args.extend(batch.iter().map(|(commit, _)| *commit));
+        args.push("--");
+        args.extend(pathspec.as_deref());
+
+        let result = run_snapshot_git(dir, &args, Some(usize::MAX)).await?;
+        // git grep exits 1 when nothing matches
+        if result.exit_code > 1 {
+            return Err(failed(result).into());
+        }
+        // Trees are listed in argument order, so matches stay oldest first
+        matches.extend(parse_grep(&target, &result.stdout, &times));
+    }
+    Ok(matches)
+}
+
+/// Configured targets that have snapshot history to search
+pub fn searchable_targets(config: &Config) -> Vec<PathBuf> {
+    config
+        .targets
+        .iter()
+        .map(|t| t.path.clone())
+        .filter(|p| p.join(GSD_DIR).is_dir())
+        .collect()
+}
+
+/// Parse `git grep -n -z` over trees: `commit:path\0line\0text`
+fn parse_grep(target: &str, output: &str, times: &HashMap<&str, &str>) -> Vec<SearchMatch> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\0');
+            let (commit, path) = fields.next()?.split_once(':')?;
+            let number = fields.next()?.parse().ok()?;
+            Some(SearchMatch {
+                target: target.to_string(),
+                commit: commit.to_string(),
+                time: times.get(commit).copied().unwrap_or_default().to_string(),
+                path: path.to_string(),
+                change: None,
+                line: Some(number),
+                text: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{commit_all, ensure_repo_initialized};
+    use tempfile::TempDir;
+
+    fn options(pattern: &str) -> SearchOptions {
+        SearchOptions {
+            pattern: pattern.to_string(),
+            regex: false,
+            since: None,
+            file_glob: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_changes_and_each_snapshot() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        std::fs::create_dir(dir.join("skills")).unwrap();
+        std::fs::write(dir.join("skills/deploy.md"), "Run make.\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "use make (the tool)\n").unwrap();
+        let first = commit_all(dir, "v1").await.unwrap();
+        std::fs::write(
+            dir.join("skills/deploy.md"),
+            "Run make.\nThen use make (the tool)\n",
+        )
+        .unwrap();
+        let second = commit_all(dir, "v2").await.unwrap();
+        std::fs::write(dir.join("skills/deploy.md"), "Run cargo.\n").unwrap();
+        let third = commit_all(dir, "v3").await.unwrap();
+
+        let mut search = options("make (the");
+        search.file_glob = Some("skills/**".to_string());
+        let matches = changes(dir, &search).await.unwrap();
+        let found: Vec<(&str, &str, Option<&str>)> = matches
+            .iter()
+            .map(|m| (m.commit.as_str(), m.path.as_str(), m.change))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (second.as_str(), "skills/deploy.md", Some("added")),
+                (third.as_str(), "skills/deploy.md", Some("removed")),
+            ]
+        );
+        assert_eq!(matches[0].text, "Then use make (the tool)");
+
+        let mut search = options(r"make \(");
+        search.regex = true;
+        search.file_glob = Some("*.txt".to_string());
+        let matches = each_snapshot(dir, &search).await.unwrap();
+        let found: Vec<(&str, &str, Option<u64>)> = matches
+            .iter()
+            .map(|m| (m.commit.as_str(), m.path.as_str(), m.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (first.as_str(), "notes.txt", Some(1)),
+                (second.as_str(), "notes.txt", Some(1)),
+                (third.as_str(), "notes.txt", Some(1)),
+            ]
+        );
+        assert!(!matches[0].time.is_empty());
+
+        search.pattern = "(".to_string();
+        assert!(matches!(
+            each_snapshot(dir, &search).await,
+            Err(SearchError::InvalidPattern(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unusual_file_names() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        std::fs::write(dir.join("café notes.md"), "the phrase\n").unwrap();
+        std::fs::write(dir.join("say \"hi\".md"), "the phrase\n").unwrap();
+        commit_all(dir, "v1").await.unwrap();
+        std::fs::remove_file(dir.join("café notes.md")).unwrap();
+        commit_all(dir, "v2").await.unwrap();
+
+        let matches = changes(dir, &options("the phrase")).await.unwrap();
+        let found: Vec<(&str, Option<&str>)> = matches
+            .iter()
+            .map(|m| (m.path.as_str(), m.change))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("café notes.md", Some("added")),
+                ("say \"hi\".md", Some("added")),
+                ("café notes.md", Some("removed")),
+            ]
+        );
+
+        let matches = each_snapshot(dir, &options("the phrase")).await.unwrap();
+        assert_eq!(matches[0].path, "café notes.md");
+        assert_eq!(
+            header_path("\"a/tab\\there\\303\\251\"", "a/"),
+            "tab\there\u{e9}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_regex_uses_git_dialect() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        std::fs::write(dir.join("a.txt"), "remake it\nmake 12 things\n").unwrap();
+        commit_all(dir, "v1").await.unwrap();
+
+        // `\<` and `[[:digit:]]` are POSIX forms that git understands
+        let mut search = options(r"\<make [[:digit:]]+");
+        search.regex = true;
+        let matches = changes(dir, &search).await.unwrap();
+        let found: Vec<&str> = matches.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(found, vec!["make 12 things"]);
+        let matches = each_snapshot(dir, &search).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line, Some(2));
+
+        search.pattern = "x(".to_string();
+        assert!(matches!(
+            changes(dir, &search).await,
+            Err(SearchError::InvalidPattern(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_searchable_targets() {
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        let untracked = TempDir::new().unwrap();
+        for dir in [first.path(), second.path()] {
+            ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+                .await
+                .unwrap();
+            std::fs::write(dir.join("notes.txt"), "shared phrase\n").unwrap();
+            commit_all(dir, "v1").await.unwrap();
+        }
+
+        let mut config = Config::default();
+        for dir in [first.path(), untracked.path(), second.path()] {
+            config
+                .targets
+                .push(crate::config::TargetConfig::new(dir.to_path_buf(), 60));
+        }
+        let targets = searchable_targets(&config);
+        assert_eq!(
+            targets,
+            vec![first.path().to_path_buf(), second.path().to_path_buf()]
+        );
+
+        let mut found = Vec::new();
+        for target in &targets {
+            found.extend(changes(target, &options("shared phrase")).await.unwrap());
+        }
+        let found: Vec<String> = found.into_iter().map(|m| m.target).collect();
+        assert_eq!(
+            found,
+            vec![
+                first.path().to_string_lossy().to_string(),
+                second.path().to_string_lossy().to_string(),
+            ]
+        );
+    }
+}
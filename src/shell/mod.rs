@@ -0,0 +1,132 @@
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWriteExt};
+use tokio::process::Command;
+
+use crate::git::read_with_cap;
+
+/// Output kept from commands whose output is only logged
+pub const MAX_LOGGED_OUTPUT_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShellError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("timed out after {}s", .0.as_secs_f64())]
+    Timeout(Duration),
+}
+
+/// What a finished command exited with and printed
+#[derive(Debug)]
+pub struct ShellOutput {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// `sh -c script` with piped stdio, in its own process group so a timeout
+/// also kills anything it started
+pub fn command(script: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .kill_on_drop(true);
+    cmd
+}
+
+/// Run `cmd` with `input` on its stdin, keeping at most `max_output` bytes
+/// of each piped output stream. Kills the whole process group if it runs
+/// longer than `timeout`.
+pub async fn run(
+    mut cmd: Command,
+    input: &[u8],
+    timeout: Duration,
+    max_output: usize,
+) -> Result<ShellOutput, ShellError> {
+    let mut child = cmd.spawn()?;
+    let stdin = child.stdin.take();
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let pid = child.id();
+
+    let run = async {
+        let write = async {
+            if let Some(mut stdin) = stdin {
+                // Commands are free to ignore their input
+                let _ = stdin.write_all(input).await;
+            }
+        };
+        let (_, stdout, stderr) = tokio::join!(
+            write,
+            read_pipe(stdout, max_output),
+            read_pipe(stderr, max_output)
+        );
+        let status = child.wait().await?;
+        Ok::<_, std::io::Error>(ShellOutput {
+            status,
+            stdout: stdout?,
+            stderr: stderr?,
+        })
+    };
+
+    match tokio::time::timeout(timeout, run).await {
+        Ok(output) => Ok(output?),
+        Err(_) => {
+            if let Some(pid) = pid {
+                // SAFETY: kill(2) on the command's process group
+                unsafe { libc::kill(-(pid as i32), libc::SIGKILL) };
+            }
+            let _ = child.wait().await;
+            Err(ShellError::Timeout(timeout))
+        }
+    }
+}
+
+/// Up to `max_bytes` of a pipe, or nothing if it was not piped
+async fn read_pipe<R: AsyncRead + Unpin>(
+    pipe: Option<R>,
+    max_bytes: usize,
+) -> Result<Vec<u8>, std::io::Error> {
+    match pipe {
+        Some(pipe) => Ok(read_with_cap(pipe, max_bytes).await?.0),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_output_is_capped_and_group_killed_on_timeout() {
+        let output = run(
+            command("cat; head -c 100000 /dev/zero"),
+            b"input",
+            Duration::from_secs(10),
+            1024,
+        )
+        .await
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), 1024);
+        assert!(output.stdout.starts_with(b"input"));
+
+        // The background sleep shares the group and holds stdout open
+        let started = std::time::Instant::now();
+        let result = run(
+            command("sleep 30 & wait"),
+            b"",
+            Duration::from_millis(200),
+            1024,
+        )
+        .await;
+        assert!(matches!(result, Err(ShellError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -1,24 +1,124 @@
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Local, SecondsFormat};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
-use tokio::sync::{mpsc, RwLock};
-use tokio::task::JoinHandle;
-use tracing::{debug, info, warn};
-
-use crate::config::{Config, TargetConfig};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, RwLock, Semaphore};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, info, warn, Instrument};
+
+use crate::audit::{self, AuditAction, AuditEntry};
+use crate::config::{AuditConfig, Config, GitConfig, HooksConfig, MessageConfig, TargetConfig};
+use crate::control::{self, ControlCall, ControlRequest, ControlResponse, TargetStatus};
+use crate::events::{EventBus, EventDispatcher, EventKind, TargetRef};
 use crate::git::{
-    commit_all, ensure_repo_initialized, has_changes, is_detached_head, is_git_available,
-    list_changed_files, GitError,
+    self, commit_all, display_paths, ensure_repo_initialized, is_detached_head, is_git_available,
+    list_changed_files, stage_all, GitError, GSD_DIR,
 };
+use crate::hooks::{self, HookStage};
+use crate::logging::{self, target_span};
+use crate::schedule::Schedule;
+use crate::throttle::Throttle;
+use crate::{filter, message, metrics, sqlite};
+
+/// What caused a snapshot attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotTrigger {
+    /// Initial check when the daemon starts
+    Startup,
+    /// Regular per-target timer
+    Interval,
+    /// `gsd snapshot` from the command line
+    Manual,
+}
+
+impl SnapshotTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SnapshotTrigger::Startup => "startup",
+            SnapshotTrigger::Interval => "interval",
+            SnapshotTrigger::Manual => "manual",
+        }
+    }
+}
 
 #[derive(Debug)]
 struct TargetState {
     config: TargetConfig,
     in_flight: bool,
     task_handle: Option<JoinHandle<()>>,
+    next_run: NextRun,
+    health: TargetHealth,
+    /// Databases as last re-staged, taken by the running snapshot
+    databases: sqlite::RefreshState,
+}
+
+/// Consecutive failures before a target is reported unhealthy
+const UNHEALTHY_AFTER: u32 = 3;
+
+/// Outcome of recent snapshot attempts, reported by `gsd status`
+#[derive(Debug, Clone, Default)]
+struct TargetHealth {
+    consecutive_failures: u32,
+    /// Git timeouts among the consecutive failures
+    timeouts: u32,
+    last_error: Option<String>,
+    /// The last attempt found a detached HEAD
+    detached_head: bool,
+}
+
+impl TargetHealth {
+    /// Record an attempt; returns whether the detached HEAD state changed
+    fn record<T>(&mut self, result: &Result<T, GitError>) -> bool {
+        let was_detached = self.detached_head;
+        match result {
+            Ok(_) => *self = Self::default(),
+            Err(e) => {
+                self.consecutive_failures += 1;
+                if matches!(e, GitError::Timeout { .. }) {
+                    self.timeouts += 1;
+                }
+                self.last_error = Some(e.to_string());
+                self.detached_head = matches!(e, GitError::DetachedHead { .. });
+            }
+        }
+        self.detached_head != was_detached
+    }
+
+    fn state(&self) -> &'static str {
+        match self.consecutive_failures {
+            0 => "healthy",
+            n if n < UNHEALTHY_AFTER => "degraded",
+            _ => "unhealthy",
+        }
+    }
+}
+
+/// When a target's timer fires next, written by its task
+type NextRun = Arc<std::sync::Mutex<Option<DateTime<Local>>>>;
+
+/// Shared state handed to per-target tasks
+#[derive(Debug, Clone)]
+struct CommitContext {
+    targets: Arc<RwLock<HashMap<String, TargetState>>>,
+    hooks: Arc<RwLock<HooksConfig>>,
+    audit: Arc<RwLock<AuditConfig>>,
+    message: Arc<RwLock<MessageConfig>>,
+    events: EventBus,
+    /// Bounds snapshots running at once across all targets
+    permits: Arc<Semaphore>,
+    throttle: Arc<Throttle>,
+}
+
+/// A snapshot that was committed
+#[derive(Debug)]
+struct Snapshot {
+    commit: String,
+    files: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -26,6 +126,13 @@ pub struct SnapshotService {
     config: Config,
     config_path: Option<PathBuf>,
     targets: Arc<RwLock<HashMap<String, TargetState>>>,
+    hooks: Arc<RwLock<HooksConfig>>,
+    audit: Arc<RwLock<AuditConfig>>,
+    message: Arc<RwLock<MessageConfig>>,
+    events: EventBus,
+    event_dispatcher: Option<EventDispatcher>,
+    permits: Arc<Semaphore>,
+    throttle: Arc<Throttle>,
     shutdown_tx: Option<mpsc::Sender<()>>,
     shutdown_rx: Option<mpsc::Receiver<()>>,
 }
@@ -43,29 +150,115 @@ pub enum SnapshotError {
     TargetInitFailed { id: String, message: String },
 }
 
-fn format_commit_message(files: &[String], max_files: usize) -> String {
-    let visible: Vec<&str> = files.iter().take(max_files).map(|s| s.as_str()).collect();
-    let remaining = files.len().saturating_sub(max_files);
+/// Initialize a target's snapshot repo with merged ignore patterns and filters
+async fn init_target_repo(git: &GitConfig, target: &TargetConfig) -> Result<(), GitError> {
+    let mut all_patterns = git.default_ignore_patterns.clone();
+    all_patterns.extend(target.ignore_patterns.clone());
+
+    let filters = filter::target_drivers(target)?;
+
+    ensure_repo_initialized(
+        &target.path,
+        &git.author_name,
+        &git.author_email,
+        &all_patterns,
+        &filters,
+    )
+    .await
+}
+
+/// Refresh the `.gsd` size gauge without blocking the runtime, at most once
+/// per measurement interval since the walk is proportional to repo size
+async fn update_repo_size(target: &TargetConfig) {
+    if !metrics::repo_size_due(target) {
+        return;
+    }
+    let dir = target.path.join(GSD_DIR);
+    if let Ok(bytes) = tokio::task::spawn_blocking(move || metrics::dir_size(&dir)).await {
+        metrics::set_repo_size(target, bytes);
+    }
+}
+
+/// Append to the audit journal without blocking the runtime
+async fn record_audit(config: &AuditConfig, entry: AuditEntry) {
+    let config = config.clone();
+    let target = entry.target.clone();
+    match tokio::task::spawn_blocking(move || audit::record(&config, &entry)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!(target = %target, error = %e, "Failed to write audit journal"),
+        Err(e) => warn!(target = %target, error = %e, "Audit journal task failed"),
+    }
+}
+
+/// Parse a target's schedule, falling back to its plain interval if invalid
+fn target_schedule(target: &TargetConfig) -> Schedule {
+    Schedule::from_target(target).unwrap_or_else(|e| {
+        warn!(target = %target.name(), error = %e, "Invalid schedule, using interval_seconds");
+        Schedule::every(Duration::from_secs(target.interval_seconds))
+    })
+}
 
-    if remaining > 0 {
-        format!("{} +{} more", visible.join(", "), remaining)
-    } else {
-        visible.join(", ")
+/// Sleep until a wall-clock time, checking the clock at least once a minute so
+/// suspend and clock changes don't delay the run
+async fn sleep_until(time: DateTime<Local>) {
+    while let Ok(remaining) = (time - Local::now()).to_std() {
+        if remaining.is_zero() {
+            break;
+        }
+        tokio::time::sleep(remaining.min(Duration::from_secs(60))).await;
+    }
+}
+
+/// Aborts a background task when the owner goes out of scope
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
     }
 }
 
 impl SnapshotService {
     pub fn new(config: Config, config_path: Option<PathBuf>) -> Self {
         let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>(1);
+        let hooks = Arc::new(RwLock::new(config.hooks.clone()));
+        let audit = Arc::new(RwLock::new(config.audit.clone()));
+        let message = Arc::new(RwLock::new(config.message.clone()));
+        let (events, event_dispatcher) = EventBus::new(config.events.sinks.clone());
+        // Like the metrics address, the limit is read once
+        let permits = Arc::new(Semaphore::new(
+            config.scheduler.max_concurrent_snapshots.max(1),
+        ));
+        let throttle = Arc::new(Throttle::new(config.throttle.clone()));
+        git::configure(&config.git);
         Self {
             config,
             config_path,
             targets: Arc::new(RwLock::new(HashMap::new())),
+            hooks,
+            audit,
+            message,
+            events,
+            event_dispatcher: Some(event_dispatcher),
+            permits,
+            throttle,
             shutdown_tx: Some(shutdown_tx),
             shutdown_rx: Some(shutdown_rx),
         }
     }
 
+    fn commit_context(&self) -> CommitContext {
+        CommitContext {
+            targets: Arc::clone(&self.targets),
+            hooks: Arc::clone(&self.hooks),
+            audit: Arc::clone(&self.audit),
+            message: Arc::clone(&self.message),
+            events: self.events.clone(),
+            permits: Arc::clone(&self.permits),
+            throttle: Arc::clone(&self.throttle),
+        }
+    }
+
     /// Get a sender that can be used to trigger shutdown from another task
     pub fn get_shutdown_sender(&self) -> mpsc::Sender<()> {
         self.shutdown_tx.clone().expect("shutdown_tx should exist")
@@ -79,6 +272,8 @@ impl SnapshotService {
         let mut initialized_count = 0;
         let mut skipped_count = 0;
 
+        // Initialize in parallel, bounded like snapshots
+        let mut inits = JoinSet::new();
         for target in &self.config.targets {
             if !target.enabled {
                 info!(target = %target.name(), "Target is disabled, skipping");
@@ -86,44 +281,59 @@ impl SnapshotService {
                 continue;
             }
 
-            // Merge ignore patterns
-            let mut all_patterns = self.config.git.default_ignore_patterns.clone();
-            all_patterns.extend(target.ignore_patterns.clone());
+            let git = self.config.git.clone();
+            let target = target.clone();
+            let permits = Arc::clone(&self.permits);
+            inits.spawn(async move {
+                let _permit = permits.acquire_owned().await.ok();
+                let span = target_span(target.name(), &target.path);
+                let result = init_target_repo(&git, &target)
+                    .instrument(span.clone())
+                    .await;
+                (target, span, result)
+            });
+        }
 
-            match ensure_repo_initialized(
-                &target.path,
-                &self.config.git.author_name,
-                &self.config.git.author_email,
-                &all_patterns,
-            )
-            .await
-            {
+        while let Some(joined) = inits.join_next().await {
+            let Ok((target, span, result)) = joined else {
+                skipped_count += 1;
+                continue;
+            };
+            match result {
                 Ok(()) => {
-                    info!(
-                        target = %target.name(),
-                        path = %target.path.display(),
-                        interval_seconds = target.interval_seconds,
-                        "Initialized target"
-                    );
+                    span.in_scope(|| {
+                        info!(
+                            target = %target.name(),
+                            path = %target.path.display(),
+                            interval_seconds = target.interval_seconds,
+                            "Initialized target"
+                        )
+                    });
+                    update_repo_size(&target).await;
 
                     let mut targets = self.targets.write().await;
                     targets.insert(
                         target.path.to_string_lossy().to_string(),
                         TargetState {
-                            config: target.clone(),
+                            config: target,
                             in_flight: false,
                             task_handle: None,
+                            next_run: NextRun::default(),
+                            health: TargetHealth::default(),
+                            databases: sqlite::RefreshState::default(),
                         },
                     );
                     initialized_count += 1;
                 }
                 Err(e) => {
-                    warn!(
-                        target = %target.name(),
-                        path = %target.path.display(),
-                        error = %e,
-                        "Failed to initialize target"
-                    );
+                    span.in_scope(|| {
+                        warn!(
+                            target = %target.name(),
+                            path = %target.path.display(),
+                            error = %e,
+                            "Failed to initialize target"
+                        )
+                    });
                     skipped_count += 1;
                 }
             }
@@ -145,6 +355,37 @@ impl SnapshotService {
     pub async fn run(&mut self) -> Result<(), SnapshotError> {
         let mut shutdown_rx = self.shutdown_rx.take().expect("shutdown_rx should exist");
 
+        // Deliver events from here on; anything emitted earlier is buffered
+        if let Some(dispatcher) = self.event_dispatcher.take() {
+            dispatcher.spawn();
+        }
+
+        // The listen address is read once; changing it needs a restart
+        let _metrics = match self.config.metrics.listen_addr() {
+            Ok(Some(addr)) => Some(AbortOnDrop(metrics::spawn(addr))),
+            Ok(None) => None,
+            Err(e) => {
+                warn!(error = %e, "Metrics listener disabled");
+                None
+            }
+        };
+
+        // Like the metrics address, the socket path is read once
+        let (control_tx, mut control_rx) = mpsc::channel::<ControlCall>(8);
+        let _control = match self.config.control.socket_path() {
+            Some(path) => match control::spawn(path.clone(), control_tx) {
+                Ok(listener) => Some(listener),
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "Control socket disabled");
+                    None
+                }
+            },
+            None => {
+                warn!("Control socket disabled: no runtime directory");
+                None
+            }
+        };
+
         // Initial commit check for all targets
         self.commit_all_targets().await;
 
@@ -170,6 +411,9 @@ impl SnapshotService {
                         warn!(error = %e, "Failed to reload config");
                     }
                 }
+                Some((request, reply)) = control_rx.recv() => {
+                    let _ = reply.send(self.handle_control(request).await);
+                }
             }
         }
 
@@ -179,6 +423,103 @@ impl SnapshotService {
         Ok(())
     }
 
+    /// Answer a request from the control socket
+    async fn handle_control(&self, request: ControlRequest) -> ControlResponse {
+        match request {
+            ControlRequest::LogLevel {
+                target,
+                level,
+                minutes,
+            } => self.boost_log_level(target, &level, minutes).await,
+            ControlRequest::Status => self.status().await,
+        }
+    }
+
+    async fn status(&self) -> ControlResponse {
+        let targets = self.targets.read().await;
+        let mut statuses: Vec<TargetStatus> = targets
+            .values()
+            .map(|state| TargetStatus {
+                name: state.config.name().to_string(),
+                path: state.config.path.clone(),
+                schedule: target_schedule(&state.config).describe(),
+                health: state.health.state().to_string(),
+                consecutive_failures: state.health.consecutive_failures,
+                timeouts: state.health.timeouts,
+                last_error: state.health.last_error.clone(),
+                next_run: state
+                    .next_run
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, false)),
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut response = ControlResponse::ok(format!("{} active target(s)", statuses.len()));
+        response.targets = statuses;
+        response
+    }
+
+    async fn boost_log_level(
+        &self,
+        target: Option<PathBuf>,
+        level: &str,
+        minutes: u64,
+    ) -> ControlResponse {
+        let level = match logging::parse_level(level) {
+            Ok(level) => level,
+            Err(e) => return ControlResponse::error(e.to_string()),
+        };
+        if minutes == 0 || minutes > logging::MAX_BOOST_MINUTES {
+            return ControlResponse::error(format!(
+                "minutes must be between 1 and {}",
+                logging::MAX_BOOST_MINUTES
+            ));
+        }
+
+        let target_path = match &target {
+            Some(path) => {
+                let key = path.to_string_lossy().to_string();
+                match self.targets.read().await.get(&key) {
+                    Some(state) => Some(state.config.path.clone()),
+                    None => {
+                        return ControlResponse::error(format!(
+                            "{} is not an active target",
+                            path.display()
+                        ))
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let Some(duration) = minutes.checked_mul(60).map(Duration::from_secs) else {
+            return ControlResponse::error("minutes is out of range");
+        };
+        if let Err(e) = logging::boost_level(target_path.as_deref(), level, duration) {
+            return ControlResponse::error(e.to_string());
+        }
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            if let Err(e) = logging::expire_boosts() {
+                warn!(error = %e, "Failed to restore log level");
+            }
+        });
+
+        let scope = match &target {
+            Some(path) => path.display().to_string(),
+            None => "all targets".to_string(),
+        };
+        info!(scope = %scope, level = %level, minutes, "Log level raised temporarily");
+        ControlResponse::ok(format!(
+            "Logging {} at {} for {} minutes",
+            scope,
+            level.as_str().to_ascii_lowercase(),
+            minutes
+        ))
+    }
+
     /// Start timer tasks for all targets
     async fn start_all_target_tasks(&self) {
         let mut targets = self.targets.write().await;
@@ -186,7 +527,8 @@ impl SnapshotService {
             if state.task_handle.is_some() {
                 continue; // Already running
             }
-            let handle = self.spawn_target_task(id.clone(), state.config.clone());
+            let handle =
+                self.spawn_target_task(id.clone(), state.config.clone(), state.next_run.clone());
             state.task_handle = Some(handle);
         }
     }
@@ -201,19 +543,52 @@ impl SnapshotService {
         }
     }
 
-    /// Spawn a timer task for a single target
-    fn spawn_target_task(&self, target_id: String, config: TargetConfig) -> JoinHandle<()> {
-        let interval = Duration::from_secs(config.interval_seconds);
-        let targets_ref = Arc::clone(&self.targets);
-        let path = config.path.clone();
+    /// Spawn a timer task for a single target, running on its schedule
+    fn spawn_target_task(
+        &self,
+        target_id: String,
+        config: TargetConfig,
+        next_run: NextRun,
+    ) -> JoinHandle<()> {
+        let schedule = target_schedule(&config);
+        let ctx = self.commit_context();
+        let jitter = self.config.scheduler.jitter_seconds;
+
+        // Targets started together would otherwise all fire together
+        let mut previous = Local::now();
+        if let Some(interval) = schedule
+            .interval()
+            .filter(|_| self.config.scheduler.spread_start)
+        {
+            let offset = fastrand::u64(..interval.as_millis().max(1) as u64);
+            previous -= chrono::Duration::milliseconds(offset as i64);
+        }
 
         tokio::spawn(async move {
-            let mut interval_timer = tokio::time::interval(interval);
-            interval_timer.tick().await; // Skip immediate first tick
-
             loop {
-                interval_timer.tick().await;
-                Self::commit_target_static(&targets_ref, &target_id, &path).await;
+                // Runs missed during a slow snapshot are skipped
+                let now = Local::now();
+                let next = schedule
+                    .next_after(previous)
+                    .filter(|next| *next > now)
+                    .or_else(|| schedule.next_after(now));
+                *next_run.lock().unwrap_or_else(|e| e.into_inner()) = next;
+                let Some(next) = next else {
+                    warn!(target = %config.name(), "Schedule has no upcoming runs");
+                    return;
+                };
+
+                sleep_until(next).await;
+                if jitter > 0 {
+                    tokio::time::sleep(Duration::from_millis(fastrand::u64(
+                        ..=jitter.saturating_mul(1000),
+                    )))
+                    .await;
+                }
+                previous = next;
+                if ctx.throttle.allow(&config).await {
+                    Self::commit_target_static(&ctx, &target_id, SnapshotTrigger::Interval).await;
+                }
             }
         })
     }
@@ -283,7 +658,13 @@ impl SnapshotService {
         // Remove targets that are no longer in config or disabled
         for path in &current_paths {
             if !new_target_paths.contains(path) {
-                self.remove_target(path).await;
+                if let Some(removed) = self.remove_target(path).await {
+                    metrics::forget_target(&removed);
+                    self.throttle.forget(&removed.path);
+                    self.events.emit(EventKind::TargetRemoved {
+                        target: TargetRef::from(&removed),
+                    });
+                }
             }
         }
 
@@ -297,15 +678,15 @@ impl SnapshotService {
             let needs_restart = {
                 let targets = self.targets.read().await;
                 if let Some(state) = targets.get(&path_key) {
-                    // Check if interval changed
-                    state.config.interval_seconds != target.interval_seconds
+                    // Restart on any change so the repo is re-initialized too
+                    state.config != *target
                 } else {
                     false
                 }
             };
 
             if needs_restart {
-                // Interval changed, restart the task
+                // Settings changed, restart the task
                 self.remove_target(&path_key).await;
             }
 
@@ -315,42 +696,58 @@ impl SnapshotService {
                 targets.contains_key(&path_key)
             };
 
-            if !exists {
-                self.add_target(target.clone()).await;
+            if !exists && self.add_target(target.clone()).await && !needs_restart {
+                self.events.emit(EventKind::TargetAdded {
+                    target: TargetRef::from(target),
+                });
+            }
+        }
+
+        if new_config.logging.level != self.config.logging.level {
+            match logging::parse_level(&new_config.logging.level).and_then(logging::set_base_level)
+            {
+                Ok(true) => info!(level = %new_config.logging.level, "Log level changed"),
+                Ok(false) => {}
+                Err(e) => warn!(error = %e, "Failed to change log level"),
             }
         }
 
+        *self.hooks.write().await = new_config.hooks.clone();
+        self.throttle.reconfigure(new_config.throttle.clone());
+        git::configure(&new_config.git);
+        *self.audit.write().await = new_config.audit.clone();
+        *self.message.write().await = new_config.message.clone();
+        if new_config.events != self.config.events {
+            self.events
+                .reconfigure(new_config.events.sinks.clone())
+                .await;
+        }
         self.config = new_config;
+
+        let active = self.targets.read().await.len();
+        self.events
+            .emit(EventKind::ConfigReloaded { targets: active });
         info!("Config reloaded successfully");
         Ok(())
     }
 
-    /// Add a new target at runtime
-    async fn add_target(&self, target: TargetConfig) {
+    /// Add a new target at runtime; returns false if it could not be initialized
+    async fn add_target(&self, target: TargetConfig) -> bool {
         let path_key = target.path.to_string_lossy().to_string();
 
         // Initialize the repo
-        let mut all_patterns = self.config.git.default_ignore_patterns.clone();
-        all_patterns.extend(target.ignore_patterns.clone());
-
-        if let Err(e) = ensure_repo_initialized(
-            &target.path,
-            &self.config.git.author_name,
-            &self.config.git.author_email,
-            &all_patterns,
-        )
-        .await
-        {
+        if let Err(e) = init_target_repo(&self.config.git, &target).await {
             warn!(
                 target = %target.name(),
                 error = %e,
                 "Failed to initialize new target"
             );
-            return;
+            return false;
         }
 
         // Spawn task and add to targets
-        let handle = self.spawn_target_task(path_key.clone(), target.clone());
+        let next_run = NextRun::default();
+        let handle = self.spawn_target_task(path_key.clone(), target.clone(), next_run.clone());
 
         let mut targets = self.targets.write().await;
         targets.insert(
@@ -359,93 +756,203 @@ impl SnapshotService {
                 config: target.clone(),
                 in_flight: false,
                 task_handle: Some(handle),
+                next_run,
+                health: TargetHealth::default(),
+                databases: sqlite::RefreshState::default(),
             },
         );
 
         info!(target = %target.name(), "Added target");
+        true
     }
 
-    /// Remove a target at runtime
-    async fn remove_target(&self, path_key: &str) {
+    /// Remove a target at runtime, returning its config if it was active
+    async fn remove_target(&self, path_key: &str) -> Option<TargetConfig> {
         let mut targets = self.targets.write().await;
-        if let Some(mut state) = targets.remove(path_key) {
-            if let Some(handle) = state.task_handle.take() {
-                handle.abort();
-            }
-            info!(target = %path_key, "Removed target");
+        let mut state = targets.remove(path_key)?;
+        if let Some(handle) = state.task_handle.take() {
+            handle.abort();
         }
+        info!(target = %path_key, "Removed target");
+        Some(state.config)
     }
 
     async fn commit_all_targets(&self) {
-        let target_entries: Vec<(String, PathBuf)> = {
+        // Targets outside their active window wait for their first run
+        let now = Local::now();
+        let due: Vec<(String, TargetConfig)> = {
             let targets = self.targets.read().await;
             targets
                 .iter()
-                .map(|(id, state)| (id.clone(), state.config.path.clone()))
+                .filter(|(_, state)| target_schedule(&state.config).is_active(now))
+                .map(|(id, state)| (id.clone(), state.config.clone()))
                 .collect()
         };
 
-        for (id, path) in target_entries {
-            Self::commit_target_static(&self.targets, &id, &path).await;
+        // Run concurrently; the snapshot limit keeps git processes bounded
+        let mut commits = JoinSet::new();
+        for (id, config) in due {
+            let ctx = self.commit_context();
+            commits.spawn(async move {
+                if ctx.throttle.allow(&config).await {
+                    Self::commit_target_static(&ctx, &id, SnapshotTrigger::Startup).await;
+                }
+            });
         }
+        while commits.join_next().await.is_some() {}
     }
 
-    async fn commit_target_static(
-        targets: &Arc<RwLock<HashMap<String, TargetState>>>,
-        target_id: &str,
-        path: &Path,
-    ) {
+    async fn commit_target_static(ctx: &CommitContext, target_id: &str, trigger: SnapshotTrigger) {
         // Check and set in_flight
-        {
-            let mut targets_write = targets.write().await;
+        let (target, mut databases) = {
+            let mut targets_write = ctx.targets.write().await;
             if let Some(state) = targets_write.get_mut(target_id) {
                 if state.in_flight {
                     debug!(target = %target_id, "Commit already in progress, skipping");
                     return;
                 }
                 state.in_flight = true;
+                (state.config.clone(), std::mem::take(&mut state.databases))
             } else {
                 return;
             }
-        }
+        };
+        let global_hooks = ctx.hooks.read().await.clone();
+        let message_config = ctx.message.read().await.clone();
+
+        // Wait for a slot; in_flight already stops this target from queueing twice
+        let permit = ctx.permits.acquire().await.ok();
 
         // Do the actual commit work
-        let result = Self::do_commit(target_id, path).await;
+        let started = Instant::now();
+        let result = Self::do_commit(
+            target_id,
+            &target,
+            &global_hooks,
+            &message_config,
+            trigger,
+            &mut databases,
+        )
+        .instrument(target_span(target.name(), &target.path))
+        .await;
+        drop(permit);
 
         // Clear in_flight
-        {
-            let mut targets_write = targets.write().await;
-            if let Some(state) = targets_write.get_mut(target_id) {
-                state.in_flight = false;
+        let detached_changed = {
+            let mut targets_write = ctx.targets.write().await;
+            match targets_write.get_mut(target_id) {
+                Some(state) => {
+                    state.in_flight = false;
+                    state.databases = databases;
+                    state.health.record(&result)
+                }
+                None => false,
             }
+        };
+        if detached_changed && result.is_ok() {
+            info!(target = %target_id, "HEAD reattached, resuming snapshots");
         }
 
-        if let Err(e) = result {
-            warn!(target = %target_id, error = %e, "Failed to commit");
-        }
+        let event = match result {
+            Ok(Some(snapshot)) => {
+                let elapsed = started.elapsed();
+                metrics::record_snapshot(&target, elapsed, snapshot.files.len());
+                update_repo_size(&target).await;
+                let entry = AuditEntry::new(
+                    &target.path,
+                    AuditAction::Snapshot {
+                        commit: snapshot.commit.clone(),
+                        files: snapshot.files.clone(),
+                        trigger,
+                        duration_ms: elapsed.as_millis() as u64,
+                    },
+                );
+                record_audit(&ctx.audit.read().await.clone(), entry).await;
+                EventKind::SnapshotCreated {
+                    target: TargetRef::from(&target),
+                    trigger,
+                    commit: snapshot.commit,
+                    files: snapshot.files,
+                    duration_ms: elapsed.as_millis() as u64,
+                }
+            }
+            Ok(None) => return,
+            Err(e @ GitError::DetachedHead { .. }) => {
+                metrics::record_failure(&target);
+                // Reported once, not on every tick while it lasts
+                if !detached_changed {
+                    return;
+                }
+                warn!(target = %target_id, "Detached HEAD detected, skipping snapshots");
+                EventKind::Anomaly {
+                    target: TargetRef::from(&target),
+                    anomaly: "detached_head".to_string(),
+                    message: e.to_string(),
+                }
+            }
+            Err(e) => {
+                warn!(target = %target_id, error = %e, "Failed to commit");
+                metrics::record_failure(&target);
+                EventKind::SnapshotFailed {
+                    target: TargetRef::from(&target),
+                    trigger,
+                    error: e.to_string(),
+                }
+            }
+        };
+        ctx.events.emit(event);
     }
 
-    async fn do_commit(target_id: &str, path: &Path) -> Result<(), GitError> {
+    async fn do_commit(
+        target_id: &str,
+        target: &TargetConfig,
+        global_hooks: &HooksConfig,
+        message_config: &MessageConfig,
+        trigger: SnapshotTrigger,
+        databases: &mut sqlite::RefreshState,
+    ) -> Result<Option<Snapshot>, GitError> {
+        let path = target.path.as_path();
+
         // Check for detached HEAD
         if is_detached_head(path).await? {
-            warn!(
-                target = %target_id,
-                "Detached HEAD detected, skipping commit"
-            );
             return Err(GitError::DetachedHead {
                 path: path.to_path_buf(),
             });
         }
 
-        // Check for changes
-        if !has_changes(path).await? {
-            debug!(target = %target_id, "No changes to commit");
-            return Ok(());
+        // Pick up database writes that only reached the WAL
+        sqlite::refresh_databases(path, databases).await?;
+
+        // Stage everything so clean filters decide what counts as a change
+        stage_all(path).await?;
+
+        // Pre-snapshot hooks run on every attempt, so they can flush state
+        // that becomes the change; a failure vetoes this snapshot
+        if global_hooks.pre_snapshot.is_some() || target.hooks.pre_snapshot.is_some() {
+            let pending = display_paths(&list_changed_files(path).await?);
+            if let Err(e) = hooks::run_stage(
+                HookStage::PreSnapshot,
+                global_hooks,
+                target,
+                trigger,
+                None,
+                &pending,
+            )
+            .await
+            {
+                info!(target = %target_id, reason = %e, "Snapshot vetoed by pre_snapshot hook");
+                return Ok(None);
+            }
         }
 
-        // Get changed files for commit message
-        let changed_files = list_changed_files(path).await?;
-        let message = format_commit_message(&changed_files, 10);
+        // Check for changes, keeping the file list for the commit message
+        let entries = list_changed_files(path).await?;
+        if entries.is_empty() {
+            debug!(target = %target_id, "No changes to commit");
+            return Ok(None);
+        }
+        let changed_files = display_paths(&entries);
+        let message = message::generate(message_config, target, &entries).await?;
 
         info!(
             target = %target_id,
@@ -454,27 +961,69 @@ impl SnapshotService {
             "Committing changes"
         );
 
-        commit_all(path, &message).await?;
+        let commit_id = commit_all(path, &message).await?;
 
-        Ok(())
+        if let Err(e) = hooks::run_stage(
+            HookStage::PostSnapshot,
+            global_hooks,
+            target,
+            trigger,
+            Some(&commit_id),
+            &changed_files,
+        )
+        .await
+        {
+            warn!(target = %target_id, error = %e, "post_snapshot hook failed");
+        }
+
+        Ok(Some(Snapshot {
+            commit: commit_id,
+            files: changed_files,
+        }))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::git::GSD_DIR;
     use tempfile::TempDir;
     use tokio::fs;
 
     #[test]
-    fn test_format_commit_message() {
-        let files = vec!["a.txt".to_string(), "b.txt".to_string()];
-        assert_eq!(format_commit_message(&files, 10), "a.txt, b.txt");
+    fn test_health_tracks_consecutive_failures() {
+        let mut health = TargetHealth::default();
+        let timeout: Result<(), GitError> = Err(GitError::Timeout {
+            command: "add -A".to_string(),
+            limit: Duration::from_secs(600),
+        });
+        let failed: Result<(), GitError> = Err(GitError::CommandFailed {
+            message: "boom".to_string(),
+        });
+
+        health.record(&timeout);
+        assert_eq!(health.state(), "degraded");
+        health.record(&failed);
+        health.record(&failed);
+        assert_eq!(health.state(), "unhealthy");
+        assert_eq!(health.timeouts, 1);
+        assert_eq!(
+            health.last_error.as_deref(),
+            Some("git command failed: boom")
+        );
 
-        let files: Vec<String> = (0..15).map(|i| format!("file{}.txt", i)).collect();
-        let msg = format_commit_message(&files, 10);
-        assert!(msg.ends_with("+5 more"));
+        health.record(&Ok(()));
+        assert_eq!(health.state(), "healthy");
+        assert!(health.last_error.is_none());
+
+        // Only entering and leaving a detached HEAD count as changes
+        let detached: Result<(), GitError> = Err(GitError::DetachedHead {
+            path: PathBuf::from("/tmp/x"),
+        });
+        assert!(health.record(&detached));
+        assert!(!health.record(&detached));
+        assert!(!health.record(&detached));
+        assert!(health.record(&Ok(())));
+        assert!(!health.record(&Ok(())));
     }
 
     #[tokio::test]
@@ -484,12 +1033,7 @@ mod tests {
         fs::create_dir_all(&target_path).await.unwrap();
 
         let config = Config {
-            targets: vec![crate::config::TargetConfig {
-                path: target_path.clone(),
-                interval_seconds: 60,
-                ignore_patterns: vec![],
-                enabled: true,
-            }],
+            targets: vec![crate::config::TargetConfig::new(target_path.clone(), 60)],
             ..Default::default()
         };
 
@@ -501,6 +1045,56 @@ mod tests {
         assert!(!target_path.join(".git").exists());
     }
 
+    #[tokio::test]
+    async fn test_log_level_boost_rejects_out_of_range_minutes() {
+        let service = SnapshotService::new(Config::default(), None);
+        for minutes in [0, logging::MAX_BOOST_MINUTES + 1, u64::MAX] {
+            let response = service
+                .handle_control(ControlRequest::LogLevel {
+                    level: "debug".to_string(),
+                    target: None,
+                    minutes,
+                })
+                .await;
+            assert!(!response.ok, "{} minutes", minutes);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parallel_initialization_and_startup_commits() {
+        let temp = TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.scheduler.max_concurrent_snapshots = 2;
+        for i in 0..5 {
+            let path = temp.path().join(format!("target{}", i));
+            fs::create_dir_all(&path).await.unwrap();
+            fs::write(path.join("note.txt"), "hello").await.unwrap();
+            config
+                .targets
+                .push(crate::config::TargetConfig::new(path, 60));
+        }
+
+        let mut service = SnapshotService::new(config, None);
+        service.initialize().await.unwrap();
+        assert_eq!(service.targets.read().await.len(), 5);
+
+        service.commit_all_targets().await;
+        for i in 0..5 {
+            let path = temp.path().join(format!("target{}", i));
+            // The initial commit is empty, so look for the file itself
+            let tree =
+                crate::git::run_snapshot_git(&path, &["ls-tree", "--name-only", "HEAD"], None)
+                    .await
+                    .unwrap();
+            assert!(
+                tree.stdout.lines().any(|name| name == "note.txt"),
+                "target{} should have committed note.txt",
+                i
+            );
+        }
+        assert_eq!(service.permits.available_permits(), 2);
+    }
+
     #[tokio::test]
     async fn test_service_coexists_with_regular_git() {
         let temp = TempDir::new().unwrap();
@@ -514,12 +1108,7 @@ mod tests {
         assert!(target_path.join(".git").exists());
 
         let config = Config {
-            targets: vec![crate::config::TargetConfig {
-                path: target_path.clone(),
-                interval_seconds: 60,
-                ignore_patterns: vec![],
-                enabled: true,
-            }],
+            targets: vec![crate::config::TargetConfig::new(target_path.clone(), 60)],
             ..Default::default()
         };
 
@@ -534,4 +1123,67 @@ mod tests {
         let targets = service.targets.read().await;
         assert_eq!(targets.len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_pre_hook_vetoes_and_post_hook_sees_commit() {
+        let temp = TempDir::new().unwrap();
+        let target_path = temp.path().join("hooked");
+        fs::create_dir_all(&target_path).await.unwrap();
+
+        let mut target = crate::config::TargetConfig::new(target_path.clone(), 60);
+        init_target_repo(&GitConfig::default(), &target)
+            .await
+            .unwrap();
+        fs::write(target_path.join("plan.md"), "draft")
+            .await
+            .unwrap();
+
+        let head = |dir: PathBuf| async move {
+            crate::git::run_snapshot_git(&dir, &["rev-parse", "HEAD"], None)
+                .await
+                .unwrap()
+                .stdout
+                .trim()
+                .to_string()
+        };
+        let initial = head(target_path.clone()).await;
+
+        // A failing pre-hook leaves the change uncommitted
+        let veto = HooksConfig {
+            pre_snapshot: Some("exit 1".to_string()),
+            ..Default::default()
+        };
+        let message = MessageConfig::default();
+        SnapshotService::do_commit(
+            "hooked",
+            &target,
+            &veto,
+            &message,
+            SnapshotTrigger::Interval,
+            &mut sqlite::RefreshState::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(head(target_path.clone()).await, initial);
+
+        // The post-hook receives the new commit ID
+        target.hooks.post_snapshot = Some("echo \"$GSD_COMMIT\" > ../commit.txt".to_string());
+        SnapshotService::do_commit(
+            "hooked",
+            &target,
+            &HooksConfig::default(),
+            &message,
+            SnapshotTrigger::Interval,
+            &mut sqlite::RefreshState::default(),
+        )
+        .await
+        .unwrap();
+
+        let committed = head(target_path.clone()).await;
+        assert_ne!(committed, initial);
+        let reported = fs::read_to_string(temp.path().join("commit.txt"))
+            .await
+            .unwrap();
+        assert_eq!(reported.trim(), committed);
+    }
 }
@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags, MAIN_DB};
+
+use crate::config::TargetConfig;
+use crate::git::{run_snapshot_git, shell_quote, FilterDriver, GitError, GSD_DIR};
+
+/// Filter driver name used for SQLite databases in .gsd/info/attributes
+pub const SQLITE_FILTER: &str = "gsd-sqlite";
+
+/// Magic header at the start of every SQLite 3 database file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// How long the backup waits for a writer holding the database lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SqliteError {
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Build the filter driver that swaps raw database files for backups.
+///
+/// Files that are not databases get the content filters in `content_spec`,
+/// since this driver replaces the content filter driver for its patterns.
+/// Returns `None` when SQLite snapshots are disabled for the target.
+pub fn filter_driver(
+    target: &TargetConfig,
+    content_spec: Option<&str>,
+) -> io::Result<Option<FilterDriver>> {
+    if !target.sqlite_snapshots {
+        return Ok(None);
+    }
+
+    let exe = std::env::current_exe()?;
+    let exe = shell_quote(&exe.to_string_lossy());
+    let spec = content_spec
+        .map(|spec| format!("--spec {} ", shell_quote(spec)))
+        .unwrap_or_default();
+
+    Ok(Some(FilterDriver {
+        name: SQLITE_FILTER.to_string(),
+        clean: format!("{} filter sqlite-clean {}%f", exe, spec),
+        smudge: Some(format!("{} filter sqlite-smudge %f", exe)),
+        patterns: target.sqlite_patterns.clone(),
+    }))
+}
+
+/// Size and modification time of a database and its WAL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    db: (u64, Option<SystemTime>),
+    wal: (u64, Option<SystemTime>),
+}
+
+/// What each database of one target looked like when it was last re-staged
+#[derive(Debug, Default)]
+pub struct RefreshState {
+    stamps: HashMap<PathBuf, FileStamp>,
+}
+
+/// Re-stage tracked databases whose WAL holds frames not yet checkpointed.
+///
+/// Writes to a WAL-mode database land in the -wal file, so the main file's
+/// stat info (which git uses to skip unchanged files) stays the same. Running
+/// the clean filter again for those files lets `list_changed_files` see new data.
+/// A database whose file and WAL are unchanged since it was last re-staged
+/// with the same `state` is skipped, since the WAL stays non-empty between
+/// checkpoints. Does nothing unless the SQLite filter is installed in
+/// .gsd/info/attributes. Returns the number of databases re-staged.
+pub async fn refresh_databases(dir: &Path, state: &mut RefreshState) -> Result<usize, GitError> {
+    let attributes_path = dir.join(GSD_DIR).join("info").join("attributes");
+    let attributes = match tokio::fs::read_to_string(&attributes_path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(GitError::Io(e)),
+    };
+
+    let driver_attr = format!("filter={}", SQLITE_FILTER);
+    let patterns: Vec<&str> = attributes
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(_, attr)| *attr == driver_attr)
+        .map(|(pattern, _)| pattern)
+        .collect();
+    if patterns.is_empty() {
+        return Ok(0);
+    }
+
+    let mut args = vec!["ls-files", "-z", "--"];
+    args.extend(&patterns);
+    let result = run_snapshot_git(dir, &args, None).await?;
+    if result.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            message: result.stderr.trim().to_string(),
+        });
+    }
+
+    let mut stale = Vec::new();
+    let mut stamps = Vec::new();
+    for file in result.stdout.split('\0').filter(|s| !s.is_empty()) {
+        let db_path = dir.join(file);
+        let [wal, _] = sidecar_paths(&db_path);
+        let stamp = FileStamp {
+            db: stat(&db_path).await,
+            wal: stat(&wal).await,
+        };
+        if stamp.wal.0 > 0 && state.stamps.get(&db_path) != Some(&stamp) {
+            stale.push(file);
+            stamps.push((db_path, stamp));
+        }
+    }
+    if stale.is_empty() {
+        return Ok(0);
+    }
+    let count = stale.len();
+
+    let mut args = vec!["add", "--renormalize", "--"];
+    args.extend(stale);
+    let result = run_snapshot_git(dir, &args, None).await?;
+    if result.exit_code != 0 {
+        return Err(GitError::CommandFailed {
+            message: result.stderr.trim().to_string(),
+        });
+    }
+    state.stamps.extend(stamps);
+
+    Ok(count)
+}
+
+async fn stat(path: &Path) -> (u64, Option<SystemTime>) {
+    match tokio::fs::metadata(path).await {
+        Ok(metadata) => (metadata.len(), metadata.modified().ok()),
+        Err(_) => (0, None),
+    }
+}
+
+/// Returns true if the content starts with the SQLite 3 file header
+pub fn is_sqlite_content(content: &[u8]) -> bool {
+    content.starts_with(SQLITE_HEADER)
+}
+
+/// Produce a consistent copy of a live database using the online backup API.
+///
+/// The copy is taken in a single read transaction, so it includes any data
+/// still sitting in the WAL. The header is switched to rollback-journal mode
+/// so a restored file opens cleanly without its original -wal/-shm files.
+pub fn backup_bytes(path: &Path) -> Result<Vec<u8>, SqliteError> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let source = Connection::open_with_flags(path, flags)?;
+    source.busy_timeout(BUSY_TIMEOUT)?;
+
+    let page_size: i64 = source.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+
+    let mut dest = Connection::open_in_memory()?;
+    dest.execute_batch(&format!("PRAGMA page_size = {}", page_size))?;
+
+    {
+        let backup = Backup::new(&source, &mut dest)?;
+        // A single step copies every page inside one read transaction
+        backup.step(-1)?;
+    }
+
+    let mut bytes = dest.serialize(MAIN_DB)?.to_vec();
+
+    // Bytes 18 and 19 are the file format write/read versions; 2 means WAL
+    if bytes.len() >= 20 {
+        bytes[18] = 1;
+        bytes[19] = 1;
+    }
+
+    Ok(bytes)
+}
+
+/// Clean filter: replace a database with a consistent backup of it.
+///
+/// Git passes the raw file on stdin. Anything that is not an SQLite database
+/// goes through the content filters in `spec`, if any; a database that
+/// cannot be backed up is stored unchanged.
+pub fn clean(
+    path: &Path,
+    spec: Option<&str>,
+    mut input: impl Read,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut raw = Vec::new();
+    input.read_to_end(&mut raw)?;
+
+    if !is_sqlite_content(&raw) {
+        return match spec {
+            Some(spec) => crate::filter::clean(spec, path, &raw[..], output),
+            None => output.write_all(&raw),
+        };
+    }
+
+    match backup_bytes(path) {
+        Ok(bytes) => output.write_all(&bytes),
+        Err(e) => {
+            eprintln!(
+                "gsd: sqlite backup of {} failed, storing raw file: {}",
+                path.display(),
+                e
+            );
+            output.write_all(&raw)
+        }
+    }
+}
+
+/// Smudge filter: drop stale -wal/-shm files before a database is restored.
+///
+/// A leftover WAL from the previous database would otherwise be replayed on
+/// top of the restored file. A database that is still open elsewhere is
+/// refused instead, since removing its WAL would lose committed writes.
+pub fn smudge(path: &Path, mut input: impl Read, mut output: impl Write) -> io::Result<()> {
+    let [wal, shm] = sidecar_paths(path);
+    if shm_in_use(&shm)? {
+        return Err(io::Error::other(format!(
+            "{} is open by another process; close it before restoring",
+            path.display()
+        )));
+    }
+
+    for sidecar in [wal, shm] {
+        match std::fs::remove_file(&sidecar) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    io::copy(&mut input, &mut output)?;
+    Ok(())
+}
+
+/// Every connection to a WAL database holds a read lock on its -shm file, so
+/// any conflicting lock means the database is still open
+fn shm_in_use(shm: &Path) -> io::Result<bool> {
+    // Open file description locks also see locks held by this process
+    #[cfg(target_os = "linux")]
+    const GET_LOCK: libc::c_int = libc::F_OFD_GETLK;
+    #[cfg(not(target_os = "linux"))]
+    const GET_LOCK: libc::c_int = libc::F_GETLK;
+
+    let file = match std::fs::File::open(shm) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    // SAFETY: an all-zero flock is valid; l_len 0 covers the whole file
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    // SAFETY: fcntl(2) on a descriptor owned by `file` with a valid flock
+    if unsafe { libc::fcntl(file.as_raw_fd(), GET_LOCK, &mut lock) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(i32::from(lock.l_type) != libc::F_UNLCK)
+}
+
+fn sidecar_paths(path: &Path) -> [PathBuf; 2] {
+    let with_suffix = |suffix: &str| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+    [with_suffix("-wal"), with_suffix("-shm")]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn wal_database(path: &Path, rows: i64) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA wal_autocheckpoint = 0;
+             CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);",
+        )
+        .unwrap();
+        for i in 0..rows {
+            conn.execute(
+                "INSERT INTO items (name) VALUES (?1)",
+                [format!("item{}", i)],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn test_backup_includes_wal_contents() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.path().join("app.db");
+
+        // Keep the connection open so the data stays in the WAL
+        let _conn = wal_database(&db_path, 25);
+        assert!(temp.path().join("app.db-wal").exists());
+
+        let bytes = backup_bytes(&db_path).unwrap();
+        assert!(is_sqlite_content(&bytes));
+        assert_eq!(&bytes[18..20], &[1, 1]);
+
+        let restored = temp.path().join("restored.db");
+        std::fs::write(&restored, &bytes).unwrap();
+        let conn = Connection::open(&restored).unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 25);
+    }
+
+    #[test]
+    fn test_clean_passes_through_non_sqlite_content() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("notes.db");
+        std::fs::write(&path, "not a database").unwrap();
+
+        let mut output = Vec::new();
+        clean(&path, None, &b"not a database"[..], &mut output).unwrap();
+        assert_eq!(output, b"not a database");
+    }
+
+    #[test]
+    fn test_smudge_removes_stale_sidecars() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("app.db");
+        std::fs::write(temp.path().join("app.db-wal"), "stale").unwrap();
+        std::fs::write(temp.path().join("app.db-shm"), "stale").unwrap();
+
+        let mut output = Vec::new();
+        smudge(&path, &b"content"[..], &mut output).unwrap();
+
+        assert_eq!(output, b"content");
+        assert!(!temp.path().join("app.db-wal").exists());
+        assert!(!temp.path().join("app.db-shm").exists());
+    }
+
+    #[test]
+    fn test_smudge_refuses_open_database() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("app.db");
+        let _conn = wal_database(&path, 5);
+
+        let mut output = Vec::new();
+        assert!(smudge(&path, &b"content"[..], &mut output).is_err());
+        assert!(output.is_empty());
+        assert!(temp.path().join("app.db-wal").exists());
+        assert!(temp.path().join("app.db-shm").exists());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_skips_unchanged_databases() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        crate::git::ensure_repo_initialized(dir, "Test", "test@test.com", &[], &[])
+            .await
+            .unwrap();
+        std::fs::write(
+            dir.join(GSD_DIR).join("info").join("attributes"),
+            format!("*.db filter={}\n", SQLITE_FILTER),
+        )
+        .unwrap();
+
+        let db_path = dir.join("app.db");
+        let conn = wal_database(&db_path, 5);
+        run_snapshot_git(dir, &["add", "app.db"], None)
+            .await
+            .unwrap();
+
+        let mut state = RefreshState::default();
+        assert_eq!(refresh_databases(dir, &mut state).await.unwrap(), 1);
+        assert_eq!(refresh_databases(dir, &mut state).await.unwrap(), 0);
+
+        // New frames in the WAL re-stage the database once more
+        conn.execute("INSERT INTO items (name) VALUES ('late')", [])
+            .unwrap();
+        assert_eq!(refresh_databases(dir, &mut state).await.unwrap(), 1);
+        assert_eq!(refresh_databases(dir, &mut state).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use tracing::info;
+
+use crate::config::{TargetConfig, ThrottleAction, ThrottleConfig};
+use crate::metrics;
+
+const LOADAVG_PATH: &str = "/proc/loadavg";
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Condition that throttled a snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reason {
+    Load,
+    Disk,
+    Battery,
+}
+
+impl Reason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Reason::Load => "load",
+            Reason::Disk => "disk",
+            Reason::Battery => "battery",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Run,
+    Stretch(Reason),
+    Skip(Reason),
+}
+
+impl Decision {
+    fn reason(&self) -> Option<Reason> {
+        match self {
+            Decision::Run => None,
+            Decision::Stretch(reason) | Decision::Skip(reason) => Some(*reason),
+        }
+    }
+}
+
+/// System state read for a throttle decision; `None` when not configured or
+/// not available on this system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Readings {
+    pub load: Option<f64>,
+    pub free_bytes: Option<u64>,
+    pub on_battery: Option<bool>,
+}
+
+impl Readings {
+    /// Read only what `config` needs. Blocking.
+    pub fn read(config: &ThrottleConfig, target: &Path) -> Self {
+        Self {
+            load: config
+                .max_load
+                .and_then(|_| read_load(Path::new(LOADAVG_PATH))),
+            free_bytes: config
+                .min_free_disk_mb
+                .and_then(|_| free_disk_bytes(target)),
+            on_battery: config
+                .on_battery
+                .and_then(|_| on_battery(Path::new(POWER_SUPPLY_DIR))),
+        }
+    }
+}
+
+/// Pick the strongest action among the conditions that apply
+pub fn decide(config: &ThrottleConfig, readings: &Readings) -> Decision {
+    let mut hits = Vec::new();
+    if let (Some(min_mb), Some(free)) = (config.min_free_disk_mb, readings.free_bytes) {
+        if free < min_mb.saturating_mul(1024 * 1024) {
+            hits.push((ThrottleAction::Skip, Reason::Disk));
+        }
+    }
+    if let (Some(action), Some(true)) = (config.on_battery, readings.on_battery) {
+        hits.push((action, Reason::Battery));
+    }
+    if let (Some(max), Some(load)) = (config.max_load, readings.load) {
+        if load > max {
+            hits.push((config.on_high_load, Reason::Load));
+        }
+    }
+
+    if let Some((_, reason)) = hits.iter().find(|(a, _)| *a == ThrottleAction::Skip) {
+        return Decision::Skip(*reason);
+    }
+    match hits.first() {
+        Some((_, reason)) => Decision::Stretch(*reason),
+        None => Decision::Run,
+    }
+}
+
+fn read_load(path: &Path) -> Option<f64> {
+    fs::read_to_string(path)
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Free space available to unprivileged users on the filesystem holding `path`
+fn free_disk_bytes(path: &Path) -> Option<u64> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out-pointer
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// On battery if a mains adapter exists and none is online, or failing that,
+/// if a battery reports discharging. `None` on machines without either.
+fn on_battery(dir: &Path) -> Option<bool> {
+    let read = |path: PathBuf| fs::read_to_string(path).map(|s| s.trim().to_string());
+
+    let mut mains_online = None;
+    let mut discharging = None;
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let supply = entry.path();
+        match read(supply.join("type")).as_deref() {
+            Ok("Mains") => {
+                let online = read(supply.join("online")).is_ok_and(|s| s == "1");
+                mains_online = Some(mains_online.unwrap_or(false) || online);
+            }
+            Ok("Battery") => {
+                let status = read(supply.join("status")).unwrap_or_default();
+                discharging = Some(discharging.unwrap_or(false) || status == "Discharging");
+            }
+            _ => {}
+        }
+    }
+    mains_online.map(|online| !online).or(discharging)
+}
+
+#[derive(Debug, Default)]
+struct TargetThrottle {
+    reason: Option<Reason>,
+    /// Scheduled runs seen while stretched
+    ticks: u64,
+}
+
+/// Decides whether timed snapshots run, logging only when a target's
+/// throttle state changes
+#[derive(Debug)]
+pub struct Throttle {
+    config: RwLock<ThrottleConfig>,
+    targets: Mutex<HashMap<PathBuf, TargetThrottle>>,
+}
+
+impl Throttle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            targets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn reconfigure(&self, config: ThrottleConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config;
+    }
+
+    fn config(&self) -> ThrottleConfig {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Whether a scheduled snapshot of `target` should run now
+    pub async fn allow(&self, target: &TargetConfig) -> bool {
+        let config = self.config();
+        if config.is_default() {
+            return true;
+        }
+
+        let path = target.path.clone();
+        let reading_config = config.clone();
+        let readings = tokio::task::spawn_blocking(move || Readings::read(&reading_config, &path))
+            .await
+            .unwrap_or_default();
+        self.apply(target, &config, decide(&config, &readings), &readings)
+    }
+
+    fn apply(
+        &self,
+        target: &TargetConfig,
+        config: &ThrottleConfig,
+        decision: Decision,
+        readings: &Readings,
+    ) -> bool {
+        let name = target.name();
+        let mut targets = self.targets.lock().unwrap_or_else(|e| e.into_inner());
+        let state = targets.entry(target.path.clone()).or_default();
+
+        let reason = decision.reason();
+        if reason != state.reason {
+            let action = match decision {
+                Decision::Skip(_) => "skip",
+                _ => "stretch",
+            };
+            match decision {
+                Decision::Run => info!(
+                    target = %name,
+                    path = %target.path.display(),
+                    "Snapshot throttling lifted"
+                ),
+                Decision::Stretch(reason) | Decision::Skip(reason) => info!(
+                    target = %name,
+                    path = %target.path.display(),
+                    reason = reason.as_str(),
+                    action,
+                    load = ?readings.load,
+                    free_bytes = ?readings.free_bytes,
+                    "Throttling snapshots"
+                ),
+            }
+            state.reason = reason;
+            state.ticks = 0;
+        }
+
+        let run = match decision {
+            Decision::Run => true,
+            Decision::Skip(_) => false,
+            Decision::Stretch(_) => {
+                state.ticks += 1;
+                state
+                    .ticks
+                    .is_multiple_of(u64::from(config.stretch_factor.max(1)))
+            }
+        };
+        if let (false, Some(reason)) = (run, reason) {
+            metrics::record_throttled(target, reason.as_str());
+        }
+        run
+    }
+
+    /// Drop state for a removed target
+    pub fn forget(&self, path: &Path) {
+        let mut targets = self.targets.lock().unwrap_or_else(|e| e.into_inner());
+        targets.remove(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn supply(dir: &Path, name: &str, files: &[(&str, &str)]) {
+        let path = dir.join(name);
+        fs::create_dir_all(&path).unwrap();
+        for (file, content) in files {
+            fs::write(path.join(file), format!("{}\n", content)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_decide_prefers_skip() {
+        let config = ThrottleConfig {
+            max_load: Some(2.0),
+            min_free_disk_mb: Some(100),
+            on_battery: Some(ThrottleAction::Skip),
+            ..Default::default()
+        };
+        let calm = Readings {
+            load: Some(0.5),
+            free_bytes: Some(1 << 30),
+            on_battery: Some(false),
+        };
+        assert_eq!(decide(&config, &calm), Decision::Run);
+
+        let busy = Readings {
+            load: Some(3.0),
+            ..calm
+        };
+        assert_eq!(decide(&config, &busy), Decision::Stretch(Reason::Load));
+
+        let unplugged = Readings {
+            on_battery: Some(true),
+            ..busy
+        };
+        assert_eq!(decide(&config, &unplugged), Decision::Skip(Reason::Battery));
+
+        let full = Readings {
+            free_bytes: Some(1024),
+            ..calm
+        };
+        assert_eq!(decide(&config, &full), Decision::Skip(Reason::Disk));
+    }
+
+    #[test]
+    fn test_stretch_runs_every_nth_tick() {
+        let config = ThrottleConfig {
+            max_load: Some(1.0),
+            stretch_factor: 3,
+            ..Default::default()
+        };
+        let throttle = Throttle::new(config.clone());
+        let readings = Readings::default();
+        let stretched = Decision::Stretch(Reason::Load);
+        let target = TargetConfig::new(PathBuf::from("/throttle-test/t"), 60);
+
+        let runs: Vec<bool> = (0..6)
+            .map(|_| throttle.apply(&target, &config, stretched, &readings))
+            .collect();
+        assert_eq!(runs, vec![false, false, true, false, false, true]);
+        assert!(throttle.apply(&target, &config, Decision::Run, &readings));
+        assert!(metrics::render().contains(
+            "gsd_snapshots_throttled_total{target=\"t\",path=\"/throttle-test/t\",reason=\"load\"} 4"
+        ));
+        metrics::forget_target(&target);
+    }
+
+    #[test]
+    fn test_state_is_kept_per_path() {
+        let config = ThrottleConfig {
+            max_load: Some(1.0),
+            stretch_factor: 2,
+            ..Default::default()
+        };
+        let throttle = Throttle::new(config.clone());
+        let readings = Readings::default();
+        let stretched = Decision::Stretch(Reason::Load);
+        let a = TargetConfig::new(PathBuf::from("/throttle-test/a/notes"), 60);
+        let b = TargetConfig::new(PathBuf::from("/throttle-test/b/notes"), 60);
+
+        assert!(!throttle.apply(&a, &config, stretched, &readings));
+        assert!(!throttle.apply(&b, &config, stretched, &readings));
+        throttle.forget(&b.path);
+        assert!(throttle.apply(&a, &config, stretched, &readings));
+
+        metrics::forget_target(&a);
+        metrics::forget_target(&b);
+    }
+
+    #[test]
+    fn test_huge_disk_minimum_does_not_overflow() {
+        let config = ThrottleConfig {
+            min_free_disk_mb: Some(u64::MAX),
+            ..Default::default()
+        };
+        let readings = Readings {
+            free_bytes: Some(u64::MAX - 1),
+            ..Default::default()
+        };
+        assert_eq!(decide(&config, &readings), Decision::Skip(Reason::Disk));
+    }
+
+    #[test]
+    fn test_power_supply_and_load_readings() {
+        let temp = TempDir::new().unwrap();
+        supply(temp.path(), "AC", &[("type", "Mains"), ("online", "0")]);
+        supply(
+            temp.path(),
+            "BAT0",
+            &[("type", "Battery"), ("status", "Discharging")],
+        );
+        assert_eq!(on_battery(temp.path()), Some(true));
+
+        fs::write(temp.path().join("AC/online"), "1\n").unwrap();
+        assert_eq!(on_battery(temp.path()), Some(false));
+
+        let desktop = TempDir::new().unwrap();
+        assert_eq!(on_battery(desktop.path()), None);
+
+        let loadavg = temp.path().join("loadavg");
+        fs::write(&loadavg, "1.52 0.80 0.40 2/300 1234\n").unwrap();
+        assert_eq!(read_load(&loadavg), Some(1.52));
+
+        assert!(free_disk_bytes(temp.path()).is_some());
+    }
+}
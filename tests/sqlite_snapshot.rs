@@ -0,0 +1,101 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use rusqlite::Connection;
+use tempfile::TempDir;
+
+fn gsd(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gsd"))
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("GSD_CONFIG", home.join("config.toml"))
+        .output()
+        .unwrap()
+}
+
+fn count_rows(path: &Path) -> i64 {
+    let conn = Connection::open(path).unwrap();
+    conn.query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))
+        .unwrap()
+}
+
+fn insert_rows(conn: &Connection, rows: usize) {
+    for i in 0..rows {
+        conn.execute(
+            "INSERT INTO items (name) VALUES (?1)",
+            [format!("item{}", i)],
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn test_sqlite_snapshot_commits_wal_contents_and_restores() {
+    let temp = TempDir::new().unwrap();
+    let home = temp.path();
+    let target = home.join("target");
+    fs::create_dir_all(&target).unwrap();
+    let db_path = target.join("app.db");
+
+    // Keep the writer open with autocheckpoint off so new rows stay in the WAL
+    let conn = Connection::open(&db_path).unwrap();
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         PRAGMA wal_autocheckpoint = 0;
+         CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);",
+    )
+    .unwrap();
+
+    // Snapshots must not pick up the user's signing or hooks settings
+    let hooks = home.join("hooks");
+    fs::create_dir_all(&hooks).unwrap();
+    let pre_commit = hooks.join("pre-commit");
+    fs::write(&pre_commit, "#!/bin/sh\nexit 1\n").unwrap();
+    fs::set_permissions(&pre_commit, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(
+        home.join(".gitconfig"),
+        format!(
+            "[commit]\n\tgpgSign = true\n[gpg]\n\tprogram = false\n[core]\n\thooksPath = {}\n",
+            hooks.display()
+        ),
+    )
+    .unwrap();
+
+    let target_arg = target.to_str().unwrap();
+    let output = gsd(home, &["add", target_arg, "-y", "--sqlite"]);
+    assert!(output.status.success(), "{output:?}");
+
+    // Step past the index timestamp so only the WAL can reveal the change
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    insert_rows(&conn, 10);
+    let output = gsd(home, &["snapshot", target_arg, "-m", "ten rows"]);
+    assert!(output.status.success(), "{output:?}");
+
+    // The committed blob is a complete database even though data is in the WAL
+    let show = gsd(home, &["git", "-C", target_arg, "show", "HEAD:app.db"]);
+    assert!(show.status.success(), "{show:?}");
+    let committed = home.join("committed.db");
+    fs::write(&committed, &show.stdout).unwrap();
+    assert_eq!(count_rows(&committed), 10);
+
+    // Writes that only reach the WAL are still detected on the next tick
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    insert_rows(&conn, 5);
+    let output = gsd(home, &["snapshot", target_arg, "-m", "fifteen rows"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("app.db"), "stdout:\n{stdout}");
+
+    // Restoring over a newer database yields the snapshotted rows
+    insert_rows(&conn, 5);
+    drop(conn);
+    let output = gsd(
+        home,
+        &["git", "-C", target_arg, "checkout", "HEAD", "--", "app.db"],
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(count_rows(&db_path), 15);
+}